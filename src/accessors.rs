@@ -0,0 +1,109 @@
+use crate::utils::{coverage_off, newtype_variants, snake_case, Flags};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::{DeriveInput, Token};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "Accessors")?;
+    // With `#[accessors(const)]` the methods are `const fn`, except for `into_*`: the compiler
+    // can't evaluate the destructor of the partially moved value in the const contexts.
+    let flags = Flags::parse(&input.attrs, "accessors", &["const", "doc_cfg"])?;
+    let constness = flags.has("const").then(<Token!(const)>::default);
+    let doc_cfg = flags.has("doc_cfg");
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let methods = variants.iter().map(|variant| {
+        let variant_name = variant.name;
+        let ty = variant.ty;
+        let mut cfg_attrs = variant
+            .cfg_attrs()
+            .map(ToTokens::to_token_stream)
+            .collect::<Vec<_>>();
+
+        if doc_cfg {
+            cfg_attrs.extend(variant.doc_cfg_attrs());
+        }
+        let deprecated_attrs = variant.deprecated_attrs().collect::<Vec<_>>();
+        let allow_deprecated = variant.allow_deprecated();
+        let snake_name = snake_case(variant_name);
+
+        let is_name = format_ident!("is_{}", snake_name);
+        let as_name = format_ident!("as_{}", snake_name);
+        let as_mut_name = format_ident!("as_{}_mut", snake_name);
+        let into_name = format_ident!("into_{}", snake_name);
+
+        let is_doc = format!(
+            "Returns `true` if the value is [`{}::{}`].",
+            name, variant_name
+        );
+        let as_doc = format!(
+            "Returns the payload of [`{}::{}`], if it's the variant.",
+            name, variant_name
+        );
+        let as_mut_doc = format!(
+            "Returns the mutable payload of [`{}::{}`], if it's the variant.",
+            name, variant_name
+        );
+        let into_doc = format!(
+            "Returns the payload of [`{}::{}`], or the value itself if it's another variant.",
+            name, variant_name
+        );
+
+        quote! {
+            #(#cfg_attrs)*
+            #[doc = #is_doc]
+            #(#deprecated_attrs)*
+            #allow_deprecated
+            #vis #constness fn #is_name(&self) -> bool {
+                ::core::matches!(self, Self::#variant_name(_))
+            }
+
+            #(#cfg_attrs)*
+            #[doc = #as_doc]
+            #(#deprecated_attrs)*
+            #allow_deprecated
+            #vis #constness fn #as_name(&self) -> ::core::option::Option<&#ty> {
+                match self {
+                    Self::#variant_name(payload) => ::core::option::Option::Some(payload),
+                    #[allow(unreachable_patterns)]
+                    _ => ::core::option::Option::None,
+                }
+            }
+
+            #(#cfg_attrs)*
+            #[doc = #as_mut_doc]
+            #(#deprecated_attrs)*
+            #allow_deprecated
+            #vis #constness fn #as_mut_name(&mut self) -> ::core::option::Option<&mut #ty> {
+                match self {
+                    Self::#variant_name(payload) => ::core::option::Option::Some(payload),
+                    #[allow(unreachable_patterns)]
+                    _ => ::core::option::Option::None,
+                }
+            }
+
+            #(#cfg_attrs)*
+            #[doc = #into_doc]
+            #(#deprecated_attrs)*
+            #allow_deprecated
+            #vis fn #into_name(self) -> ::core::result::Result<#ty, Self> {
+                match self {
+                    Self::#variant_name(payload) => ::core::result::Result::Ok(payload),
+                    #[allow(unreachable_patterns)]
+                    other => ::core::result::Result::Err(other),
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        #coverage_off
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#methods)*
+        }
+    })
+}
@@ -0,0 +1,81 @@
+use crate::utils::{coverage_off, newtype_variants, with_bound};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, DeriveInput, GenericParam, LifetimeDef};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "DelegateArbitrary")?;
+
+    let name = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let mut generics = with_bound(
+        &input.generics,
+        parse_quote!(::arbitrary::Arbitrary<'__arbitrary>),
+    );
+
+    // Like the derive of `arbitrary`, the input outlives the borrowed payloads, so they can be
+    // taken from it, e.g. `&'a str`.
+    let mut lifetime: LifetimeDef = parse_quote!('__arbitrary);
+
+    lifetime.bounds.extend(
+        input
+            .generics
+            .lifetimes()
+            .map(|param| param.lifetime.clone()),
+    );
+
+    generics.params.insert(0, GenericParam::Lifetime(lifetime));
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    let counters = variants.iter().map(|variant| {
+        let cfg_attrs = variant.cfg_attrs();
+
+        quote! {
+            #(#cfg_attrs)*
+            {
+                variant_count += 1;
+            }
+        }
+    });
+
+    let constructors = variants.iter().map(|variant| {
+        let variant_name = variant.name;
+        let cfg_attrs = variant.cfg_attrs();
+        let allow_deprecated = variant.allow_deprecated();
+
+        quote! {
+            #(#cfg_attrs)*
+            #allow_deprecated
+            {
+                if index == 0 {
+                    return ::core::result::Result::Ok(Self::#variant_name(
+                        ::arbitrary::Arbitrary::arbitrary(u)?,
+                    ));
+                }
+
+                index -= 1;
+            }
+        }
+    });
+
+    Ok(quote! {
+        #coverage_off
+        impl #impl_generics ::arbitrary::Arbitrary<'__arbitrary> for #name #ty_generics #where_clause {
+            fn arbitrary(
+                u: &mut ::arbitrary::Unstructured<'__arbitrary>,
+            ) -> ::arbitrary::Result<Self> {
+                let mut variant_count = 0;
+
+                #(#counters)*
+
+                let mut index = u.choose_index(variant_count)?;
+
+                #(#constructors)*
+
+                ::core::unreachable!()
+            }
+        }
+    })
+}
@@ -0,0 +1,162 @@
+use crate::utils::{coverage_off, newtype_variants, snake_case, with_bound, NewtypeVariant};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::token::{Comma, Paren};
+use syn::{parenthesized, parse_quote, DeriveInput, Ident, Path};
+
+/// Trait of the `#[as_dyn(..)]` attribute, the ones wrapped in `pin(..)` also get the accessor
+/// for the pinned trait object.
+struct DynTrait {
+    path: Path,
+    pin: bool,
+}
+
+struct DynTraits(Vec<DynTrait>);
+
+impl Parse for DynTraits {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut traits = Vec::new();
+
+        while !input.is_empty() {
+            if input.peek(Ident) && input.peek2(Paren) && input.fork().parse::<Ident>()? == "pin" {
+                let content;
+
+                input.parse::<Ident>()?;
+                parenthesized!(content in input);
+
+                for path in Punctuated::<Path, Comma>::parse_terminated(&content)? {
+                    traits.push(DynTrait { path, pin: true });
+                }
+            } else {
+                traits.push(DynTrait {
+                    path: input.parse()?,
+                    pin: false,
+                });
+            }
+
+            if !input.is_empty() {
+                input.parse::<Comma>()?;
+            }
+        }
+
+        Ok(DynTraits(traits))
+    }
+}
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "AsDyn")?;
+    let mut traits = Vec::new();
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("as_dyn"))
+    {
+        traits.extend(attr.parse_args::<DynTraits>()?.0);
+    }
+
+    if traits.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "expected `#[as_dyn(...)]` attribute listing the traits of the trait objects",
+        ));
+    }
+
+    let vis = &input.vis;
+    let name = &input.ident;
+
+    let impls = traits.iter().map(|DynTrait { path, pin }| {
+        let generics = with_bound(&input.generics, parse_quote!(#path));
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+        // The method names are derived from the trait name, without the path and arguments.
+        let trait_name = snake_case(&path.segments.last().unwrap().ident);
+        let as_name = format_ident!("as_{}", trait_name);
+        let as_mut_name = format_ident!("as_{}_mut", trait_name);
+        let as_pin_name = format_ident!("as_{}_pin", trait_name);
+
+        let path_str = quote!(#path).to_string().replace(' ', "");
+        let as_doc = format!("Returns the payload as `&dyn {}`.", path_str);
+        let as_mut_doc = format!("Returns the payload as `&mut dyn {}`.", path_str);
+
+        let ref_arms = arms(&variants, quote!(payload));
+
+        let pin_impl = pin.then(|| {
+            // Like with the async I/O derives, the pinned payload is obtained with `Pin::new`, which
+            // requires the payloads to be `Unpin`. It's bound before the coercion, otherwise the
+            // trait object would be inferred as the type to pin.
+            let pin_arms = arms(
+                &variants,
+                quote!({
+                    let payload = ::core::pin::Pin::new(payload);
+
+                    payload
+                }),
+            );
+
+            let generics = with_bound(&generics, parse_quote!(::core::marker::Unpin));
+            let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+            let as_pin_doc = format!(
+                "Returns the pinned payload as `Pin<&mut dyn {}>`.",
+                path_str
+            );
+
+            quote! {
+                #coverage_off
+                impl #impl_generics #name #ty_generics #where_clause {
+                    #[doc = #as_pin_doc]
+                    #vis fn #as_pin_name(
+                        self: ::core::pin::Pin<&mut Self>,
+                    ) -> ::core::pin::Pin<&mut (dyn #path + '_)> {
+                        match ::core::pin::Pin::get_mut(self) {
+                            #(#pin_arms,)*
+                        }
+                    }
+                }
+            }
+        });
+
+        quote! {
+            #coverage_off
+            impl #impl_generics #name #ty_generics #where_clause {
+                #[doc = #as_doc]
+                #vis fn #as_name(&self) -> &(dyn #path + '_) {
+                    match self {
+                        #(#ref_arms,)*
+                    }
+                }
+
+                #[doc = #as_mut_doc]
+                #vis fn #as_mut_name(&mut self) -> &mut (dyn #path + '_) {
+                    match self {
+                        #(#ref_arms,)*
+                    }
+                }
+            }
+
+            #pin_impl
+        }
+    });
+
+    Ok(impls.collect())
+}
+
+fn arms(variants: &[NewtypeVariant], payload: TokenStream) -> Vec<TokenStream> {
+    variants
+        .iter()
+        .map(|variant| {
+            let variant_name = variant.name;
+            let cfg_attrs = variant.cfg_attrs();
+            let allow_deprecated = variant.allow_deprecated();
+
+            quote! {
+                #(#cfg_attrs)*
+                #allow_deprecated
+                Self::#variant_name(payload) => #payload
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,36 @@
+use crate::utils::{coverage_off, newtype_variants, with_bound};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, DeriveInput};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "DelegateClone")?;
+
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, parse_quote!(::core::clone::Clone));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let arms = variants.iter().map(|variant| {
+        let variant_name = variant.name;
+        let cfg_attrs = variant.cfg_attrs();
+        let allow_deprecated = variant.allow_deprecated();
+
+        quote! {
+            #(#cfg_attrs)*
+            #allow_deprecated
+            Self::#variant_name(payload) => Self::#variant_name(::core::clone::Clone::clone(payload))
+        }
+    });
+
+    Ok(quote! {
+        #coverage_off
+        impl #impl_generics ::core::clone::Clone for #name #ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                match self {
+                    #(#arms,)*
+                }
+            }
+        }
+    })
+}
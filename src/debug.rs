@@ -0,0 +1,57 @@
+use crate::utils::{coverage_off, newtype_variants, with_bound, Flags};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, DeriveInput};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "DelegateDebug")?;
+    let compact = Flags::parse(&input.attrs, "debug", &["compact"])?.has("compact");
+
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, parse_quote!(::core::fmt::Debug));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let arms = variants
+        .iter()
+        .map(|variant| {
+            let variant_name = variant.name;
+            let cfg_attrs = variant.cfg_attrs();
+            let allow_deprecated = variant.allow_deprecated();
+            let redact = Flags::parse(variant.attrs, "debug", &["redact"])?.has("redact");
+
+            // Like the derived `Debug`, raw identifiers are printed without the `r#` prefix.
+            let unraw_name = variant_name.to_string();
+            let unraw_name = unraw_name.trim_start_matches("r#");
+
+            let tuple_name = if compact {
+                unraw_name.to_string()
+            } else {
+                format!("{}::{}", name, unraw_name)
+            };
+
+            let field = if redact {
+                quote!(&::core::format_args!("<redacted>"))
+            } else {
+                quote!(payload)
+            };
+
+            Ok(quote! {
+                #(#cfg_attrs)*
+                #allow_deprecated
+                Self::#variant_name(payload) => f.debug_tuple(#tuple_name).field(#field).finish()
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        #coverage_off
+        impl #impl_generics ::core::fmt::Debug for #name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                match self {
+                    #(#arms,)*
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,994 @@
+use crate::forward::{arg_names, forward_fn, is_pinned, ForwardedFn, Targets};
+use crate::pin::projection;
+use crate::utils::{
+    coverage_off, first_payload_ty, newtype_variants, snake_case, tokens_eq, with_bound,
+    with_same_assoc, CratePath, NewtypeVariant,
+};
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use std::ptr;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::token::{Comma, Paren};
+use syn::{
+    braced, bracketed, parenthesized, parse_quote, Attribute, DeriveInput, Expr, FnArg, Ident,
+    ItemTrait, Path, ReturnType, Token, TraitItem, Type, Visibility,
+};
+
+pub(crate) fn expand_trait(item: &ItemTrait, krate: &CratePath) -> syn::Result<TokenStream> {
+    let name = &item.ident;
+    let module_name = format_ident!("__all_the_same_delegate_{}", name);
+    let unsafety = &item.unsafety;
+
+    if !item.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item.generics,
+            "generic traits can't be delegated",
+        ));
+    }
+
+    let mut items = Vec::new();
+
+    for trait_item in &item.items {
+        match trait_item {
+            TraitItem::Method(method) if method.sig.receiver().is_some() => {
+                let sig = &method.sig;
+
+                items.push(quote!(#sig;));
+            }
+            // The default implementations of the methods without `self` and the constants are
+            // kept, the others are implemented as the `#[delegate(...)]` options of the enum say.
+            TraitItem::Method(method) if method.default.is_none() => {
+                let sig = &method.sig;
+
+                items.push(quote!(#sig;));
+            }
+            TraitItem::Type(item) if !item.generics.params.is_empty() => {
+                return Err(syn::Error::new_spanned(
+                    &item.generics,
+                    "generic associated types can't be delegated",
+                ))
+            }
+            TraitItem::Type(item) => {
+                let ident = &item.ident;
+
+                items.push(quote!(type #ident;));
+            }
+            TraitItem::Const(item) if item.default.is_none() => {
+                let ident = &item.ident;
+                let ty = &item.ty;
+
+                items.push(quote!(const #ident: #ty;));
+            }
+            _ => {}
+        }
+    }
+
+    let async_trait = async_trait_attrs(item);
+
+    // Like with `#[variants]`, the macro has the same name as the trait, so it's imported along
+    // with it, and the derive can invoke it by the trait path.
+    Ok(quote! {
+        #item
+
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        mod #module_name {
+            #[allow(unused_macros)]
+            macro_rules! #name {
+                ([$($trait_path:tt)*] $($tokens:tt)*) => {
+                    #krate::__delegate! {
+                        #(#async_trait)*
+                        #unsafety trait $($trait_path)* { #(#items)* }
+                        $($tokens)*
+                    }
+                };
+            }
+
+            #[allow(unused_imports)]
+            pub(crate) use #name;
+        }
+
+        #[allow(unused_imports)]
+        pub(crate) use #module_name::#name;
+    })
+}
+
+/// `#[async_trait]` attributes, that follow `#[delegatable]`, so the trait still has `async fn`s,
+/// which the impl has to be desugared the same way as the trait. The attribute is resolved where
+/// the enum is, so the bare `async_trait` is replaced with the full path.
+fn async_trait_attrs(item: &ItemTrait) -> Vec<Attribute> {
+    if !cfg!(feature = "async-trait") {
+        return Vec::new();
+    }
+
+    item.attrs
+        .iter()
+        .filter(|attr| {
+            attr.path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "async_trait")
+        })
+        .map(|attr| {
+            let mut attr = attr.clone();
+
+            if attr.path.is_ident("async_trait") {
+                attr.path = parse_quote!(::async_trait::async_trait);
+            }
+
+            attr
+        })
+        .collect()
+}
+
+/// Options of the trait, that follow it in the `#[delegate(..)]` attribute.
+#[derive(Default)]
+struct TraitOptions {
+    /// `unsafe` in front of the trait, that confirms the payloads uphold the contract of the
+    /// `unsafe trait`, so the enum can implement it.
+    unsafety: Option<Token!(unsafe)>,
+    /// Methods that are implemented by the inherent methods with the same names.
+    except: Vec<Ident>,
+    /// Associated items with the explicitly given values.
+    assoc: Vec<AssocValue>,
+    /// Associated items that are taken from the payload of the first variant.
+    first: Vec<Ident>,
+    /// `&Self`, `&mut Self` or `Box<Self>`, that the trait is implemented for as well.
+    impl_for: Vec<Type>,
+    /// `AsDyn`, whose trait object accessors the methods are called through, rather than
+    /// matching on the variants.
+    via: Option<Ident>,
+}
+
+impl TraitOptions {
+    const NAMES: &'static [&'static str] = &["except", "assoc", "first", "impl_for", "via"];
+
+    fn parse_option(&mut self, name: &Ident, content: ParseStream) -> syn::Result<()> {
+        if name == "except" {
+            self.except
+                .extend(Punctuated::<Ident, Comma>::parse_terminated(content)?);
+        } else if name == "assoc" {
+            self.assoc
+                .extend(Punctuated::<AssocValue, Comma>::parse_terminated(content)?);
+        } else if name == "first" {
+            self.first
+                .extend(Punctuated::<Ident, Comma>::parse_terminated(content)?);
+        } else if name == "via" {
+            let via = content.parse::<Ident>()?;
+
+            if via != "AsDyn" {
+                return Err(syn::Error::new_spanned(via, "expected `AsDyn`"));
+            }
+
+            self.via = Some(via);
+        } else {
+            self.impl_for
+                .extend(Punctuated::<Type, Comma>::parse_terminated(content)?);
+        }
+
+        Ok(())
+    }
+}
+
+/// `unsafe except(...), assoc(...), first(...), impl_for(...), via(...)` list, that is passed to
+/// the hidden macro.
+impl Parse for TraitOptions {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut options = TraitOptions {
+            unsafety: input.parse()?,
+            ..TraitOptions::default()
+        };
+
+        while !input.is_empty() {
+            let name = input.parse::<Ident>()?;
+            let content;
+
+            parenthesized!(content in input);
+            options.parse_option(&name, &content)?;
+
+            if !input.is_empty() {
+                input.parse::<Comma>()?;
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+impl ToTokens for TraitOptions {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let TraitOptions {
+            unsafety,
+            except,
+            assoc,
+            first,
+            impl_for,
+            via,
+        } = self;
+
+        let via = via.as_ref().map(|via| quote!(, via(#via)));
+
+        tokens.extend(quote! {
+            #unsafety
+            except(#(#except),*),
+            assoc(#(#assoc),*),
+            first(#(#first),*),
+            impl_for(#(#impl_for),*)
+            #via
+        });
+    }
+}
+
+/// `Name = value` of the associated type, constant or function without `self`. Which of them it
+/// is, is only known to the hidden macro, so the value is kept as tokens.
+struct AssocValue {
+    name: Ident,
+    value: TokenStream,
+}
+
+impl Parse for AssocValue {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+
+        input.parse::<Token!(=)>()?;
+
+        // Types may have commas in the generic arguments, so the value is parsed as a type if it
+        // can be, and as an expression otherwise.
+        let fork = input.fork();
+        let is_type = fork.parse::<Type>().is_ok() && (fork.is_empty() || fork.peek(Comma));
+
+        let value = if is_type {
+            input.parse::<Type>()?.into_token_stream()
+        } else {
+            input.parse::<Expr>()?.into_token_stream()
+        };
+
+        Ok(AssocValue { name, value })
+    }
+}
+
+impl ToTokens for AssocValue {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let AssocValue { name, value } = self;
+
+        tokens.extend(quote!(#name = #value));
+    }
+}
+
+/// Trait of the `#[delegate(..)]` attribute, with its options.
+struct DelegatedTrait {
+    path: Path,
+    options: TraitOptions,
+}
+
+struct DelegatedTraits(Vec<DelegatedTrait>);
+
+impl Parse for DelegatedTraits {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut traits: Vec<DelegatedTrait> = Vec::new();
+
+        while !input.is_empty() {
+            let is_option = input.peek(Ident)
+                && input.peek2(Paren)
+                && TraitOptions::NAMES.iter().any(|name| {
+                    input
+                        .fork()
+                        .parse::<Ident>()
+                        .is_ok_and(|ident| ident == name)
+                });
+
+            if is_option {
+                let name = input.parse::<Ident>()?;
+                let content;
+
+                parenthesized!(content in input);
+
+                match traits.last_mut() {
+                    Some(delegated) => delegated.options.parse_option(&name, &content)?,
+                    None => {
+                        return Err(syn::Error::new_spanned(
+                            &name,
+                            format!(
+                                "expected the trait, that `{}(..)` applies to, before it",
+                                name
+                            ),
+                        ))
+                    }
+                }
+            } else {
+                let unsafety = input.parse()?;
+
+                traits.push(DelegatedTrait {
+                    path: input.parse()?,
+                    options: TraitOptions {
+                        unsafety,
+                        ..TraitOptions::default()
+                    },
+                });
+            }
+
+            if !input.is_empty() {
+                input.parse::<Comma>()?;
+            }
+        }
+
+        Ok(DelegatedTraits(traits))
+    }
+}
+
+/// `Trait::method = function` override in the `#[delegate(..)]` attribute of a variant.
+struct MethodOverride {
+    method: Path,
+    function: Path,
+}
+
+impl Parse for MethodOverride {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let method = input.parse()?;
+
+        input.parse::<Token!(=)>()?;
+
+        Ok(MethodOverride {
+            method,
+            function: input.parse()?,
+        })
+    }
+}
+
+impl MethodOverride {
+    /// Trait and method names of the `Trait::method` path.
+    fn names(&self) -> Option<(&Ident, &Ident)> {
+        let segments = &self.method.segments;
+
+        match segments.len() {
+            0 | 1 => None,
+            len => Some((&segments[len - 2].ident, &segments[len - 1].ident)),
+        }
+    }
+}
+
+fn method_overrides(variant: &NewtypeVariant) -> syn::Result<Vec<MethodOverride>> {
+    let mut overrides = Vec::new();
+
+    for attr in variant
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("delegate"))
+    {
+        overrides
+            .extend(attr.parse_args_with(Punctuated::<MethodOverride, Comma>::parse_terminated)?);
+    }
+
+    Ok(overrides)
+}
+
+pub(crate) fn expand_derive(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let mut traits = Vec::new();
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("delegate"))
+    {
+        traits.extend(attr.parse_args::<DelegatedTraits>()?.0);
+    }
+
+    if traits.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "expected `#[delegate(...)]` attribute listing the traits to delegate",
+        ));
+    }
+
+    // The variants are checked here, so the errors point at the enum rather than the macro. The
+    // methods are checked by the macro, which knows the signatures of the trait.
+    for variant in newtype_variants(input, "Delegate")? {
+        for method_override in method_overrides(&variant)? {
+            let is_delegated = method_override.names().is_some_and(|(trait_name, _)| {
+                traits.iter().any(|delegated| {
+                    delegated
+                        .path
+                        .segments
+                        .last()
+                        .is_some_and(|segment| segment.ident == *trait_name)
+                })
+            });
+
+            if !is_delegated {
+                return Err(syn::Error::new_spanned(
+                    &method_override.method,
+                    "expected `Trait::method` of one of the delegated traits",
+                ));
+            }
+        }
+    }
+
+    Ok(traits
+        .iter()
+        .map(|DelegatedTrait { path, options }| quote!(#path! { [#path] [#options] #input }))
+        .collect())
+}
+
+/// `#[attrs] unsafe trait Trait { fn method(&self); type Assoc; } [options] enum ...` input of the
+/// hidden macro, that is invoked by the macro registered with `#[delegatable]`.
+pub(crate) struct DelegateImpl {
+    attrs: Vec<Attribute>,
+    unsafety: Option<Token!(unsafe)>,
+    trait_path: Path,
+    items: Vec<TraitItem>,
+    options: TraitOptions,
+    input: DeriveInput,
+}
+
+impl Parse for DelegateImpl {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let unsafety = input.parse()?;
+
+        input.parse::<Token!(trait)>()?;
+
+        let trait_path = input.parse()?;
+        let items_content;
+
+        braced!(items_content in input);
+
+        let mut items = Vec::new();
+
+        while !items_content.is_empty() {
+            items.push(items_content.parse()?);
+        }
+
+        let options_content;
+
+        bracketed!(options_content in input);
+
+        Ok(DelegateImpl {
+            attrs,
+            unsafety,
+            trait_path,
+            items,
+            options: options_content.parse()?,
+            input: input.parse()?,
+        })
+    }
+}
+
+pub(crate) fn expand_impl(delegate: &DelegateImpl) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&delegate.input.attrs)?;
+    let DelegateImpl {
+        attrs,
+        unsafety,
+        trait_path,
+        items,
+        options,
+        input,
+    } = delegate;
+
+    let variants = newtype_variants(input, "Delegate")?;
+    let trait_name = &trait_path.segments.last().unwrap().ident;
+    let projection = projection(input)?;
+
+    // Implementing an `unsafe trait` asserts that its contract is upheld, so it's never done
+    // without `unsafe` in the attribute.
+    match (unsafety, &options.unsafety) {
+        (Some(_), None) => {
+            return Err(syn::Error::new_spanned(
+                trait_path,
+                format!(
+                    "`{}` is an `unsafe trait`, make sure the payloads uphold its contract and \
+                     delegate it with `#[delegate(unsafe {})]`",
+                    trait_name, trait_name
+                ),
+            ))
+        }
+        (None, Some(marker)) => {
+            return Err(syn::Error::new_spanned(
+                marker,
+                format!("`{}` is not an `unsafe trait`", trait_name),
+            ))
+        }
+        _ => {}
+    }
+
+    let unsafety = &options.unsafety;
+
+    let item_name = |item: &TraitItem| match item {
+        TraitItem::Method(method) => Some(method.sig.ident.clone()),
+        TraitItem::Type(item) => Some(item.ident.clone()),
+        TraitItem::Const(item) => Some(item.ident.clone()),
+        _ => None,
+    };
+
+    let is_delegated_method = |item: &TraitItem| matches!(item, TraitItem::Method(method) if method.sig.receiver().is_some());
+
+    let check_item = |name: &Ident, expected: &dyn Fn(&TraitItem) -> bool, kind: &str| {
+        if items
+            .iter()
+            .any(|item| item_name(item).as_ref() == Some(name) && expected(item))
+        {
+            Ok(())
+        } else {
+            Err(syn::Error::new_spanned(
+                name,
+                format!("`{}` has no {} `{}`", trait_name, kind, name),
+            ))
+        }
+    };
+
+    for name in &options.except {
+        check_item(name, &|item| matches!(item, TraitItem::Method(_)), "method")?;
+    }
+
+    let assoc_names = options.assoc.iter().map(|assoc| &assoc.name);
+
+    for name in assoc_names.chain(&options.first) {
+        check_item(
+            name,
+            &|item| !is_delegated_method(item),
+            "associated type, constant or `self`-less function",
+        )?;
+    }
+
+    let mut overrides = Vec::new();
+
+    for variant in &variants {
+        let variant_overrides = method_overrides(variant)?;
+
+        for method_override in &variant_overrides {
+            if let Some((override_trait, method)) = method_override.names() {
+                if override_trait == trait_name {
+                    check_item(method, &is_delegated_method, "delegated method")?;
+
+                    if options.via.is_some() {
+                        return Err(syn::Error::new_spanned(
+                            &method_override.method,
+                            format!(
+                                "the methods of `{}` are called through the trait object with \
+                                 `via(AsDyn)`, so they can't be overridden for the variants",
+                                trait_name
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+
+        overrides.push(variant_overrides);
+    }
+
+    // `first(...)` items are taken from the first variant, that is always compiled in.
+    let first_variant = || {
+        variants
+            .iter()
+            .find(|variant| variant.cfg_attrs().next().is_none())
+            .ok_or_else(|| {
+                syn::Error::new_spanned(
+                    &input.ident,
+                    "expected a variant without `#[cfg]`, that `first(..)` items are taken from",
+                )
+            })
+    };
+
+    let assoc_value = |name: &Ident| {
+        options
+            .assoc
+            .iter()
+            .find(|assoc| assoc.name == *name)
+            .map(|assoc| &assoc.value)
+    };
+
+    let is_first = |name: &Ident| options.first.iter().any(|first| first == name);
+    let is_excluded = |name: &Ident| options.except.iter().any(|except| except == name);
+
+    let missing_value = |name: &Ident| {
+        syn::Error::new_spanned(
+            &input.ident,
+            format!(
+                "expected `assoc({} = ...)` or `first({})` option of `{}` with the value of `{}`",
+                name, name, trait_name, name
+            ),
+        )
+    };
+
+    let mut impl_items = Vec::new();
+
+    // The associated types, that are not given explicitly, have to be the same for all payloads.
+    let mut same_types = Vec::new();
+
+    for item in items {
+        match item {
+            TraitItem::Type(item) => {
+                let name = &item.ident;
+
+                let ty = match assoc_value(name) {
+                    Some(value) => syn::parse2::<Type>(value.clone())?.into_token_stream(),
+                    None if is_first(name) => {
+                        let first_ty = first_variant()?.ty;
+
+                        quote!(<#first_ty as #trait_path>::#name)
+                    }
+                    None => {
+                        let first_ty = first_payload_ty(input, &variants, &name.to_string())?;
+
+                        same_types.push((name, first_ty));
+
+                        quote!(<#first_ty as #trait_path>::#name)
+                    }
+                };
+
+                impl_items.push(quote!(type #name = #ty;));
+            }
+            TraitItem::Const(item) => {
+                let name = &item.ident;
+                let ty = &item.ty;
+
+                let value = match assoc_value(name) {
+                    Some(value) => syn::parse2::<Expr>(value.clone())?.into_token_stream(),
+                    None if is_first(name) => {
+                        let first_ty = first_variant()?.ty;
+
+                        quote!(<#first_ty as #trait_path>::#name)
+                    }
+                    None => return Err(missing_value(name)),
+                };
+
+                impl_items.push(quote!(const #name: #ty = #value;));
+            }
+            TraitItem::Method(method) => {
+                let sig = &method.sig;
+                let fn_name = &sig.ident;
+                let args = arg_names(sig)?;
+                let await_suffix = sig.asyncness.map(|_| quote!(.await));
+                let receiver = sig.receiver().map(|_| quote!(self,));
+
+                // The excluded methods are implemented by the inherent methods with the same
+                // names. If there is none, the call resolves to the method itself, which is an
+                // error rather than the infinite recursion.
+                if is_excluded(fn_name) {
+                    impl_items.push(quote! {
+                        #[deny(unconditional_recursion)]
+                        #sig {
+                            Self::#fn_name(#receiver #(#args),*) #await_suffix
+                        }
+                    });
+
+                    continue;
+                }
+
+                if receiver.is_none() {
+                    let call = match assoc_value(fn_name) {
+                        Some(value) => {
+                            let path = syn::parse2::<Path>(value.clone())?;
+
+                            quote!(#path(#(#args),*) #await_suffix)
+                        }
+                        None if is_first(fn_name) => {
+                            let first = first_variant()?;
+                            let first_ty = first.ty;
+                            let call = quote! {
+                                <#first_ty as #trait_path>::#fn_name(#(#args),*) #await_suffix
+                            };
+
+                            // The constructors return the payload, that is wrapped in the variant.
+                            match &sig.output {
+                                ReturnType::Type(_, ty) if tokens_eq(ty, &quote!(Self)) => {
+                                    let variant_name = first.name;
+
+                                    quote!(Self::#variant_name(#call))
+                                }
+                                _ => call,
+                            }
+                        }
+                        None => return Err(missing_value(fn_name)),
+                    };
+
+                    impl_items.push(quote!(#sig { #call }));
+
+                    continue;
+                }
+
+                // With `via(AsDyn)` the methods are called on the trait object, returned by the
+                // accessors of `AsDyn`, so every enum only has the matches of the accessors.
+                if options.via.is_some() {
+                    let accessor = match sig.receiver() {
+                        Some(FnArg::Receiver(receiver)) if receiver.reference.is_some() => {
+                            let mutability = receiver.mutability.map(|_| "_mut");
+
+                            format!("as_{}{}", snake_case(trait_name), mutability.unwrap_or(""))
+                        }
+                        Some(receiver) if is_pinned(receiver) => {
+                            format!("as_{}_pin", snake_case(trait_name))
+                        }
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                sig,
+                                format!(
+                                    "`{}` can't be called through the trait object with \
+                                     `via(AsDyn)`, that only supports `&self`, `&mut self` and \
+                                     `Pin<&mut Self>` receivers",
+                                    fn_name
+                                ),
+                            ))
+                        }
+                    };
+
+                    let accessor = format_ident!("{}", accessor);
+
+                    impl_items.push(quote! {
+                        #sig {
+                            #trait_path::#fn_name(Self::#accessor(self), #(#args),*) #await_suffix
+                        }
+                    });
+
+                    continue;
+                }
+
+                let fn_overrides = overrides
+                    .iter()
+                    .map(|variant_overrides| {
+                        variant_overrides
+                            .iter()
+                            .find(|method_override| {
+                                method_override.names() == Some((trait_name, fn_name))
+                            })
+                            .map(|method_override| method_override.function.clone())
+                    })
+                    .collect::<Vec<_>>();
+
+                let forwarded = ForwardedFn {
+                    attrs: Vec::new(),
+                    vis: Visibility::Inherited,
+                    sig: sig.clone(),
+                };
+
+                impl_items.push(forward_fn(
+                    &forwarded,
+                    Targets::Overridden(&variants, &fn_overrides),
+                    Some(trait_path),
+                    projection.as_ref(),
+                )?);
+            }
+            _ => {}
+        }
+    }
+
+    let name = &input.ident;
+    let mut generics = with_bound(&input.generics, parse_quote!(#trait_path));
+
+    if let Some((_, first_ty)) = same_types.first() {
+        let same_types = same_types.iter().map(|(name, _)| name);
+
+        generics = with_same_assoc(
+            &generics,
+            &variants,
+            first_ty,
+            parse_quote!(#trait_path<#(#same_types = <#first_ty as #trait_path>::#same_types),*>),
+        );
+    }
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let pointer_impls = options
+        .impl_for
+        .iter()
+        .map(|pointer| expand_pointer_impl(delegate, pointer))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        #coverage_off
+        #(#attrs)*
+        #unsafety impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+            #(#impl_items)*
+        }
+
+        #(#pointer_impls)*
+    })
+}
+
+/// Pointer to the enum, that the trait is implemented for with `impl_for(...)`.
+#[derive(Clone, Copy, PartialEq)]
+enum Pointer {
+    Ref,
+    RefMut,
+    Box,
+}
+
+impl Pointer {
+    fn name(self) -> &'static str {
+        match self {
+            Pointer::Ref => "&Self",
+            Pointer::RefMut => "&mut Self",
+            Pointer::Box => "Box<Self>",
+        }
+    }
+
+    fn from_type(ty: &Type) -> Option<Self> {
+        [Pointer::Ref, Pointer::RefMut, Pointer::Box]
+            .into_iter()
+            .find(|pointer| tokens_eq(ty, &pointer.ty()))
+    }
+
+    fn ty(self) -> Type {
+        match self {
+            Pointer::Ref => parse_quote!(&Self),
+            Pointer::RefMut => parse_quote!(&mut Self),
+            Pointer::Box => parse_quote!(Box<Self>),
+        }
+    }
+}
+
+/// Whether the tokens mention `Self` other than in the paths of the associated items, e.g.
+/// `Self::Item`, which are the same for the pointer.
+fn mentions_self(tokens: TokenStream) -> bool {
+    let mut tokens = tokens.into_iter().peekable();
+
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Ident(ident) if ident == "Self" => {
+                let is_assoc_path = matches!(
+                    tokens.peek(),
+                    Some(TokenTree::Punct(punct)) if punct.as_char() == ':'
+                );
+
+                if !is_assoc_path {
+                    return true;
+                }
+            }
+            TokenTree::Group(group) if mentions_self(group.stream()) => return true,
+            _ => {}
+        }
+    }
+
+    false
+}
+
+/// Implements the trait for the pointer to the enum, forwarding to the impl for the enum.
+fn expand_pointer_impl(delegate: &DelegateImpl, pointer_ty: &Type) -> syn::Result<TokenStream> {
+    let DelegateImpl {
+        attrs,
+        trait_path,
+        items,
+        options,
+        input,
+        ..
+    } = delegate;
+
+    let unsafety = &options.unsafety;
+
+    let pointer = Pointer::from_type(pointer_ty).ok_or_else(|| {
+        syn::Error::new_spanned(
+            pointer_ty,
+            "expected `&Self`, `&mut Self` or `Box<Self>` to implement the trait for",
+        )
+    })?;
+
+    let name = &input.ident;
+    let trait_name = &trait_path.segments.last().unwrap().ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    let enum_ty = quote!(#name #ty_generics);
+
+    let mut generics = input.generics.clone();
+
+    generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote!(#enum_ty: #trait_path));
+
+    let self_ty = match pointer {
+        Pointer::Ref => {
+            generics.params.insert(0, parse_quote!('__delegate));
+
+            quote!(&'__delegate #enum_ty)
+        }
+        Pointer::RefMut => {
+            generics.params.insert(0, parse_quote!('__delegate));
+
+            quote!(&'__delegate mut #enum_ty)
+        }
+        Pointer::Box => quote!(::alloc::boxed::Box<#enum_ty>),
+    };
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    let unsupported = |reason: String| {
+        syn::Error::new_spanned(
+            pointer_ty,
+            format!(
+                "`{}` can't be implemented for `{}`, as {}",
+                trait_name,
+                pointer.name(),
+                reason
+            ),
+        )
+    };
+
+    let mut impl_items = Vec::new();
+
+    for item in items {
+        match item {
+            TraitItem::Type(item) => {
+                let item_name = &item.ident;
+
+                impl_items.push(quote!(type #item_name = <#enum_ty as #trait_path>::#item_name;));
+            }
+            TraitItem::Const(item) => {
+                let item_name = &item.ident;
+                let ty = &item.ty;
+
+                impl_items.push(quote! {
+                    const #item_name: #ty = <#enum_ty as #trait_path>::#item_name;
+                });
+            }
+            TraitItem::Method(method) => {
+                let sig = &method.sig;
+                let fn_name = &sig.ident;
+                let args = arg_names(sig)?;
+                let await_suffix = sig.asyncness.map(|_| quote!(.await));
+
+                let receiver = match sig.receiver() {
+                    None => None,
+                    Some(FnArg::Receiver(receiver)) => {
+                        let receiver = match (&receiver.reference, &receiver.mutability, pointer) {
+                            (Some(_), None, _) => quote!(&**self),
+                            (Some(_), Some(_), Pointer::RefMut | Pointer::Box) => {
+                                quote!(&mut **self)
+                            }
+                            (None, _, Pointer::Box) => quote!(*self),
+                            (Some(_), Some(_), _) => {
+                                return Err(unsupported(format!("`{}` takes `&mut self`", fn_name)))
+                            }
+                            (None, _, _) => {
+                                return Err(unsupported(format!("`{}` takes `self`", fn_name)))
+                            }
+                        };
+
+                        Some(quote!(#receiver,))
+                    }
+                    Some(_) => {
+                        return Err(unsupported(format!(
+                            "`{}` takes `self` of a custom type",
+                            fn_name
+                        )))
+                    }
+                };
+
+                let mut sig_without_receiver = sig.clone();
+
+                sig_without_receiver.inputs = sig
+                    .inputs
+                    .iter()
+                    .filter(|&arg| {
+                        sig.receiver()
+                            .is_none_or(|receiver| !ptr::eq(arg, receiver))
+                    })
+                    .cloned()
+                    .collect();
+
+                if mentions_self(sig_without_receiver.to_token_stream()) {
+                    return Err(unsupported(format!(
+                        "`{}` mentions `Self` in the signature",
+                        fn_name
+                    )));
+                }
+
+                impl_items.push(quote! {
+                    #sig {
+                        <#enum_ty as #trait_path>::#fn_name(#receiver #(#args),*) #await_suffix
+                    }
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let coverage_off = coverage_off(&delegate.input.attrs)?;
+
+    Ok(quote! {
+        #coverage_off
+        #(#attrs)*
+        #unsafety impl #impl_generics #trait_path for #self_ty #where_clause {
+            #(#impl_items)*
+        }
+    })
+}
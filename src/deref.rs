@@ -0,0 +1,119 @@
+use crate::utils::{coverage_off, newtype_variants, with_bound, NewtypeVariant};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_quote, DeriveInput, Token, Type};
+
+/// `Target` or `Target, mut` arguments of the `#[deref(..)]` attribute.
+struct DerefArgs {
+    target: Type,
+    mutable: bool,
+}
+
+impl Parse for DerefArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let target = input.parse()?;
+        let mut mutable = false;
+
+        if input.parse::<Option<Token!(,)>>()?.is_some() && !input.is_empty() {
+            input.parse::<Token!(mut)>()?;
+            input.parse::<Option<Token!(,)>>()?;
+
+            mutable = true;
+        }
+
+        Ok(DerefArgs { target, mutable })
+    }
+}
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "DelegateDeref")?;
+
+    let DerefArgs { target, mutable } =
+        match input.attrs.iter().find(|attr| attr.path.is_ident("deref")) {
+            Some(attr) => attr.parse_args()?,
+            None => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "expected `#[deref(Type)]` attribute with the target type",
+                ))
+            }
+        };
+
+    let name = &input.ident;
+    let as_ref: Type = parse_quote!(::core::convert::AsRef<#target>);
+    let as_mut: Type = parse_quote!(::core::convert::AsMut<#target>);
+
+    let generics = with_bound(&input.generics, parse_quote!(#as_ref));
+
+    let generics = if mutable {
+        with_bound(&generics, parse_quote!(#as_mut))
+    } else {
+        generics
+    };
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let ref_arms = arms(&variants, quote!(<_ as #as_ref>::as_ref(payload)));
+
+    let mut impls = quote! {
+        #coverage_off
+        impl #impl_generics ::core::ops::Deref for #name #ty_generics #where_clause {
+            type Target = #target;
+
+            fn deref(&self) -> &Self::Target {
+                match self {
+                    #(#ref_arms,)*
+                }
+            }
+        }
+
+        #coverage_off
+        impl #impl_generics #as_ref for #name #ty_generics #where_clause {
+            fn as_ref(&self) -> &#target {
+                ::core::ops::Deref::deref(self)
+            }
+        }
+    };
+
+    if mutable {
+        let mut_arms = arms(&variants, quote!(<_ as #as_mut>::as_mut(payload)));
+
+        impls.extend(quote! {
+            #coverage_off
+            impl #impl_generics ::core::ops::DerefMut for #name #ty_generics #where_clause {
+                fn deref_mut(&mut self) -> &mut Self::Target {
+                    match self {
+                        #(#mut_arms,)*
+                    }
+                }
+            }
+
+            #coverage_off
+            impl #impl_generics #as_mut for #name #ty_generics #where_clause {
+                fn as_mut(&mut self) -> &mut #target {
+                    ::core::ops::DerefMut::deref_mut(self)
+                }
+            }
+        });
+    }
+
+    Ok(impls)
+}
+
+fn arms(variants: &[NewtypeVariant], call: TokenStream) -> Vec<TokenStream> {
+    variants
+        .iter()
+        .map(|variant| {
+            let variant_name = variant.name;
+            let cfg_attrs = variant.cfg_attrs();
+            let allow_deprecated = variant.allow_deprecated();
+
+            quote! {
+                #(#cfg_attrs)*
+                #allow_deprecated
+                Self::#variant_name(payload) => #call
+            }
+        })
+        .collect()
+}
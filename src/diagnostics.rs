@@ -0,0 +1,71 @@
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::quote;
+
+/// Reports the errors of `all_the_same!`. With the `nightly-diagnostics` feature they get a note
+/// pointing at the `match`, otherwise they are emitted as `compile_error!`.
+pub(crate) fn report(err: syn::Error, input: &TokenStream) -> TokenStream {
+    if cfg!(feature = "nightly-diagnostics") {
+        let keyword = keyword_span(input);
+
+        for err in err {
+            emit_error(err.span(), &err.to_string(), keyword);
+        }
+
+        // The errors are already emitted, the placeholder just prevents the follow-up ones.
+        quote!(::core::unreachable!())
+    } else {
+        err.to_compile_error()
+    }
+}
+
+/// Emits a warning with the `nightly-diagnostics` feature. Proc macros can't emit warnings on
+/// stable, so it's a no-op there.
+pub(crate) fn warn(span: Span, message: &str, help: &str) {
+    #[cfg(feature = "nightly-diagnostics")]
+    span.unwrap().warning(message).help(help).emit();
+
+    #[cfg(not(feature = "nightly-diagnostics"))]
+    let _ = (span, message, help);
+}
+
+/// Reports the expansion of `all_the_same!` with the `#[debug]` option. It's a note with the
+/// `nightly-diagnostics` feature, otherwise it's printed to stderr, that cargo shows as is.
+pub(crate) fn note_expansion(span: Span, expanded: &TokenStream) {
+    let message = format!("`all_the_same!` expands to:\n{}", expanded);
+
+    #[cfg(feature = "nightly-diagnostics")]
+    span.unwrap().note(message).emit();
+
+    #[cfg(not(feature = "nightly-diagnostics"))]
+    {
+        let _ = span;
+
+        eprintln!("note: {}", message);
+    }
+}
+
+#[cfg(feature = "nightly-diagnostics")]
+fn emit_error(span: Span, message: &str, keyword: Option<Span>) {
+    let mut diagnostic = span.unwrap().error(message);
+
+    if let Some(keyword) = keyword {
+        diagnostic = diagnostic.span_note(keyword.unwrap(), "in this `all_the_same!` expression");
+    }
+
+    diagnostic.emit();
+}
+
+#[cfg(not(feature = "nightly-diagnostics"))]
+fn emit_error(_span: Span, _message: &str, _keyword: Option<Span>) {
+    unreachable!("errors are emitted as `compile_error!` on stable")
+}
+
+/// Finds the `match`, `if` or `let` keyword, that starts the expression.
+fn keyword_span(input: &TokenStream) -> Option<Span> {
+    input.clone().into_iter().find_map(|token| match token {
+        TokenTree::Ident(ident) if ident == "match" || ident == "if" || ident == "let" => {
+            Some(ident.span())
+        }
+        _ => None,
+    })
+}
@@ -0,0 +1,147 @@
+use crate::match_expr::parse_enum_path;
+use crate::utils::CratePath;
+use proc_macro2::{Delimiter, Group, Spacing, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream};
+use syn::token::{Bracket, Comma};
+use syn::Ident;
+
+/// `Enum::[A, B]` variant list of the `#[dispatch(..)]` attribute. It's copied as is to the
+/// generated `all_the_same!` arms, so it supports everything the arms support, like `[*]`.
+pub(crate) struct VariantList {
+    tokens: TokenStream,
+    /// Path of the crate, that `all_the_same!` is invoked through.
+    pub(crate) krate: CratePath,
+}
+
+impl VariantList {
+    /// The list followed by the optional `crate = path` option, as in `#[dispatch(..)]`.
+    pub(crate) fn parse_attr(input: ParseStream) -> syn::Result<Self> {
+        let mut list = input.parse::<VariantList>()?;
+
+        if !input.is_empty() {
+            input.parse::<Comma>()?;
+            list.krate = input.parse()?;
+        }
+
+        Ok(list)
+    }
+}
+
+impl Parse for VariantList {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut tokens = parse_enum_path(input)?.into_token_stream();
+
+        tokens.extend(quote!(::));
+
+        if !input.peek(Bracket) {
+            return Err(input.error("expected the variant list, e.g. `Enum::[A, B]`"));
+        }
+
+        tokens.extend(Some(input.parse::<TokenTree>()?));
+
+        Ok(VariantList {
+            tokens,
+            krate: CratePath::default(),
+        })
+    }
+}
+
+impl ToTokens for VariantList {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.tokens.to_tokens(tokens);
+    }
+}
+
+pub(crate) fn expand(list: &VariantList, item: TokenStream) -> syn::Result<TokenStream> {
+    rewrite(list, item)
+}
+
+/// Replaces the `delegate!(..)` invocations in the tokens, including the nested ones.
+fn rewrite(list: &VariantList, tokens: TokenStream) -> syn::Result<TokenStream> {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let mut rewritten = TokenStream::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        // Paths, like `other::delegate!`, refer to the other macros.
+        let is_path =
+            i > 0 && matches!(&tokens[i - 1], TokenTree::Punct(punct) if punct.as_char() == ':');
+
+        if let [TokenTree::Ident(ident), TokenTree::Punct(bang), TokenTree::Group(group), ..] =
+            &tokens[i..]
+        {
+            if !is_path && ident == "delegate" && bang.as_char() == '!' {
+                rewritten.extend(delegate(list, group)?);
+                i += 3;
+
+                continue;
+            }
+        }
+
+        rewritten.extend(Some(match &tokens[i] {
+            TokenTree::Group(group) => {
+                let mut rewritten = Group::new(group.delimiter(), rewrite(list, group.stream())?);
+
+                rewritten.set_span(group.span());
+
+                TokenTree::Group(rewritten)
+            }
+            token => token.clone(),
+        }));
+
+        i += 1;
+    }
+
+    Ok(rewritten)
+}
+
+/// Turns `delegate!([scrutinee,] binding => body)` into the `all_the_same!` match, that matches
+/// on `self` if the scrutinee is omitted.
+fn delegate(list: &VariantList, group: &Group) -> syn::Result<TokenStream> {
+    let tokens = group.stream().into_iter().collect::<Vec<_>>();
+
+    let arrow = tokens.windows(2).position(|pair| match pair {
+        [TokenTree::Punct(eq), TokenTree::Punct(gt)] => {
+            eq.as_char() == '=' && eq.spacing() == Spacing::Joint && gt.as_char() == '>'
+        }
+        _ => false,
+    });
+
+    let arrow = match arrow {
+        Some(arrow) if arrow > 0 => arrow,
+        _ => {
+            return Err(syn::Error::new(
+                group.span(),
+                "expected `binding => expr` or `scrutinee, binding => expr`",
+            ))
+        }
+    };
+
+    // The binding can't have top-level commas, unlike the scrutinee, e.g. with the turbofish.
+    let comma = tokens[..arrow]
+        .iter()
+        .rposition(|token| matches!(token, TokenTree::Punct(punct) if punct.as_char() == ','));
+
+    let (scrutinee, binding) = match comma {
+        Some(comma) => (
+            tokens[..comma].iter().cloned().collect(),
+            &tokens[comma + 1..arrow],
+        ),
+        None => (
+            Ident::new("self", group.span()).into_token_stream(),
+            &tokens[..arrow],
+        ),
+    };
+
+    let binding = binding.iter().cloned().collect::<TokenStream>();
+    let body = rewrite(list, tokens[arrow + 2..].iter().cloned().collect())?;
+    let arm = Group::new(Delimiter::Parenthesis, binding);
+    let krate = &list.krate;
+
+    Ok(quote! {
+        #krate::all_the_same!(match #scrutinee {
+            #list #arm => #body
+        })
+    })
+}
@@ -0,0 +1,59 @@
+use crate::utils::{coverage_off, newtype_variants};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "EitherFrom")?;
+
+    let (left, right) = match &variants[..] {
+        [left, right] if left.cfg_attrs().chain(right.cfg_attrs()).next().is_none() => {
+            (left, right)
+        }
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "`EitherFrom` requires exactly two variants, that are not gated with `#[cfg]`",
+            ))
+        }
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let left_name = left.name;
+    let right_name = right.name;
+    let either = {
+        let left_ty = left.ty;
+        let right_ty = right.ty;
+
+        quote!(::either::Either<#left_ty, #right_ty>)
+    };
+
+    let allow_deprecated = left.allow_deprecated().or(right.allow_deprecated());
+
+    Ok(quote! {
+        #coverage_off
+        #allow_deprecated
+        impl #impl_generics ::core::convert::From<#either> for #name #ty_generics #where_clause {
+            fn from(value: #either) -> Self {
+                match value {
+                    ::either::Either::Left(payload) => Self::#left_name(payload),
+                    ::either::Either::Right(payload) => Self::#right_name(payload),
+                }
+            }
+        }
+
+        #coverage_off
+        #allow_deprecated
+        impl #impl_generics ::core::convert::From<#name #ty_generics> for #either #where_clause {
+            fn from(value: #name #ty_generics) -> Self {
+                match value {
+                    #name::#left_name(payload) => ::either::Either::Left(payload),
+                    #name::#right_name(payload) => ::either::Either::Right(payload),
+                }
+            }
+        }
+    })
+}
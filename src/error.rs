@@ -0,0 +1,67 @@
+use crate::forward::forward_trait_fns;
+use crate::utils::{coverage_off, newtype_variants, with_bound};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, DeriveInput, Path};
+
+pub(crate) fn expand_display(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "DelegateDisplay")?;
+    let trait_path: Path = parse_quote!(::core::fmt::Display);
+
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, parse_quote!(#trait_path));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let fns = forward_trait_fns(
+        &variants,
+        &trait_path,
+        vec![parse_quote! {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result
+        }],
+        None,
+    )?;
+
+    Ok(quote! {
+        #coverage_off
+        impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+            #(#fns)*
+        }
+    })
+}
+
+pub(crate) fn expand_error(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "DelegateError")?;
+    let trait_path: Path = parse_quote!(::core::error::Error);
+
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, parse_quote!(#trait_path));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // The deprecated methods are forwarded as well, as the payloads may still rely on them.
+    let fns = forward_trait_fns(
+        &variants,
+        &trait_path,
+        vec![
+            parse_quote! {
+                fn source(&self) -> ::core::option::Option<&(dyn #trait_path + 'static)>
+            },
+            parse_quote! {
+                fn description(&self) -> &str
+            },
+            parse_quote! {
+                fn cause(&self) -> ::core::option::Option<&dyn #trait_path>
+            },
+        ],
+        None,
+    )?;
+
+    Ok(quote! {
+        #coverage_off
+        #[allow(deprecated)]
+        impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+            #(#fns)*
+        }
+    })
+}
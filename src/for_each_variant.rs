@@ -0,0 +1,78 @@
+use crate::match_expr::{self, ClosureBody, EscapingControlFlowCheck};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{Expr, ExprClosure, Pat, Path, ReturnType, Token};
+
+/// `value, Enum, |binding| body` arguments.
+pub(crate) struct Args {
+    value: Expr,
+    enum_path: Path,
+    closure: ExprClosure,
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let args = Args {
+            value: input.parse()?,
+            enum_path: {
+                input.parse::<Token!(,)>()?;
+
+                input.parse()?
+            },
+            closure: {
+                input.parse::<Token!(,)>()?;
+
+                input.parse()?
+            },
+        };
+
+        input.parse::<Option<Token!(,)>>()?;
+
+        Ok(args)
+    }
+}
+
+/// Expands to the `all_the_same!` match with the single `Enum::[*](binding) => body` arm.
+pub(crate) fn expand(args: &Args) -> syn::Result<TokenStream> {
+    let Args {
+        value,
+        enum_path,
+        closure,
+    } = args;
+
+    let is_plain = closure.attrs.is_empty()
+        && closure.asyncness.is_none()
+        && closure.movability.is_none()
+        && closure.capture.is_none()
+        && matches!(closure.output, ReturnType::Default);
+
+    let binding = match closure.inputs.first() {
+        Some(binding) if is_plain && closure.inputs.len() == 1 => binding,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                closure,
+                "expected `|binding| body` with the binding of the payload",
+            ))
+        }
+    };
+
+    if let Pat::Type(binding) = binding {
+        return Err(syn::Error::new_spanned(
+            &binding.ty,
+            "the type of the payload is different for every variant, so it can't be specified",
+        ));
+    }
+
+    EscapingControlFlowCheck::check(&closure.body, ClosureBody::Inlined("`for_each_variant!`"))?;
+
+    let body = &closure.body;
+
+    let args = syn::parse2::<match_expr::Args>(quote! {
+        match #value {
+            #enum_path::[*](#binding) => #body
+        }
+    })?;
+
+    Ok(match_expr::expand(&args))
+}
@@ -0,0 +1,281 @@
+use crate::dispatch::VariantList;
+use crate::pin::projection;
+use crate::utils::{coverage_off, newtype_variants, CratePath, NewtypeVariant};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use std::ptr;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use syn::{Attribute, DeriveInput, FnArg, Ident, Pat, Path, Signature, Token, Type, Visibility};
+
+/// Variants that the methods are forwarded for.
+pub(crate) enum Targets<'a> {
+    /// Variants of the derive input.
+    Variants(&'a [NewtypeVariant<'a>]),
+    /// Variants of the derive input, some of which call the given functions instead of the
+    /// method of the payload.
+    Overridden(&'a [NewtypeVariant<'a>], &'a [Option<Path>]),
+    /// `Enum::[A, B]` list, that is matched with `all_the_same!`.
+    List(&'a VariantList),
+}
+
+pub(crate) struct ForwardedFn {
+    pub(crate) attrs: Vec<Attribute>,
+    pub(crate) vis: Visibility,
+    pub(crate) sig: Signature,
+}
+
+impl Parse for ForwardedFn {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(ForwardedFn {
+            attrs: input.call(Attribute::parse_outer)?,
+            vis: input.parse()?,
+            sig: input.parse()?,
+        })
+    }
+}
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "Forward")?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let projection = projection(input)?;
+
+    let fns = forwarded_fns(input)?
+        .iter()
+        .map(|forwarded| {
+            forward_fn(
+                forwarded,
+                Targets::Variants(&variants),
+                None,
+                projection.as_ref(),
+            )
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        #coverage_off
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#fns)*
+        }
+    })
+}
+
+fn forwarded_fns(input: &DeriveInput) -> syn::Result<Vec<ForwardedFn>> {
+    let mut fns = Vec::new();
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("forward"))
+    {
+        fns.extend(attr.parse_args_with(Punctuated::<ForwardedFn, Comma>::parse_terminated)?);
+    }
+
+    Ok(fns)
+}
+
+/// Generates the method that forwards to the payload. Trait methods are called by the trait path,
+/// so that they aren't shadowed by the inherent methods of the payload.
+pub(crate) fn forward_fn(
+    forwarded: &ForwardedFn,
+    targets: Targets,
+    trait_path: Option<&Path>,
+    projection: Option<&Ident>,
+) -> syn::Result<TokenStream> {
+    let ForwardedFn { attrs, vis, sig } = forwarded;
+    let fn_name = &sig.ident;
+    let await_suffix = sig.asyncness.map(|_| quote!(.await));
+
+    let receiver = sig.receiver();
+    let args = arg_names(sig)?;
+
+    let payload = Ident::new("payload", Span::mixed_site());
+
+    // `self: Pin<&mut Self>` is projected to `Pin<&mut Payload>` with the method generated by
+    // `PinProject`, or otherwise with `Pin::new`, which requires payloads to be `Unpin`.
+    let (scrutinee, receiver, enum_path) = match projection {
+        Some(project) if receiver.is_some_and(is_pinned) => (
+            quote!(Self::project(self)),
+            quote!(#payload),
+            quote!(#project),
+        ),
+        None if receiver.is_some_and(is_pinned) => (
+            quote!(::core::pin::Pin::get_mut(self)),
+            quote!(::core::pin::Pin::new(#payload)),
+            quote!(Self),
+        ),
+        _ => (quote!(self), quote!(#payload), quote!(Self)),
+    };
+
+    let call = match trait_path {
+        Some(trait_path) => quote!(#trait_path::#fn_name(#receiver, #(#args),*)),
+        None => quote!(#receiver.#fn_name(#(#args),*)),
+    };
+
+    let match_variants = |variants: &[NewtypeVariant], overrides: &[Option<Path>]| {
+        let arms = variants.iter().enumerate().map(|(i, variant)| {
+            let variant_name = variant.name;
+            let cfg_attrs = variant.cfg_attrs();
+            let allow_deprecated = variant.allow_deprecated();
+
+            let call = match overrides.get(i) {
+                Some(Some(path)) => quote!(#path(#receiver, #(#args),*)),
+                _ => call.clone(),
+            };
+
+            quote! {
+                #(#cfg_attrs)*
+                #allow_deprecated
+                #enum_path::#variant_name(#payload) => #call #await_suffix
+            }
+        });
+
+        quote! {
+            match #scrutinee {
+                #(#arms,)*
+            }
+        }
+    };
+
+    let body = match targets {
+        Targets::Variants(variants) => match_variants(variants, &[]),
+        Targets::Overridden(variants, overrides) => match_variants(variants, overrides),
+        Targets::List(list) => {
+            let krate = &list.krate;
+
+            quote! {
+                #krate::all_the_same!(match #scrutinee {
+                    #list(#payload) => #call #await_suffix
+                })
+            }
+        }
+    };
+
+    let doc = if trait_path.is_some() || attrs.iter().any(|attr| attr.path.is_ident("doc")) {
+        None
+    } else {
+        let doc = format!("Forwards to `{}` of the payload.", fn_name);
+
+        Some(quote!(#[doc = #doc]))
+    };
+
+    Ok(quote! {
+        #(#attrs)*
+        #doc
+        #vis #sig {
+            #body
+        }
+    })
+}
+
+/// Names of the arguments of the method, except the receiver.
+pub(crate) fn arg_names(sig: &Signature) -> syn::Result<Vec<&Ident>> {
+    let receiver = sig.receiver();
+
+    sig.inputs
+        .iter()
+        .filter(|&arg| receiver.is_none_or(|receiver| !ptr::eq(arg, receiver)))
+        .filter_map(|arg| match arg {
+            FnArg::Receiver(_) => None,
+            FnArg::Typed(arg) => Some(arg),
+        })
+        .map(|arg| match &*arg.pat {
+            Pat::Ident(pat) => Ok(&pat.ident),
+            pat => Err(syn::Error::new_spanned(
+                pat,
+                "arguments of forwarded methods must be plain identifiers",
+            )),
+        })
+        .collect()
+}
+
+/// Forwards the methods of the trait impl to the payloads.
+pub(crate) fn forward_trait_fns(
+    variants: &[NewtypeVariant],
+    trait_path: &Path,
+    sigs: Vec<Signature>,
+    projection: Option<&Ident>,
+) -> syn::Result<Vec<TokenStream>> {
+    sigs.into_iter()
+        .map(|sig| {
+            let forwarded = ForwardedFn {
+                attrs: Vec::new(),
+                vis: Visibility::Inherited,
+                sig,
+            };
+
+            forward_fn(
+                &forwarded,
+                Targets::Variants(variants),
+                Some(trait_path),
+                projection,
+            )
+        })
+        .collect()
+}
+
+/// `crate = path; Enum::[A, B]; fn a(&self); fn b(&self);` input of `delegate_fns!`, where the
+/// crate path is optional.
+pub(crate) struct DelegateFns {
+    list: VariantList,
+    fns: Vec<ForwardedFn>,
+}
+
+impl Parse for DelegateFns {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let krate = if CratePath::peek(input) {
+            let krate = input.parse()?;
+
+            input.parse::<Token!(;)>()?;
+
+            Some(krate)
+        } else {
+            None
+        };
+
+        let mut list = input.parse::<VariantList>()?;
+
+        if let Some(krate) = krate {
+            list.krate = krate;
+        }
+
+        input.parse::<Token!(;)>()?;
+
+        let fns = Punctuated::<ForwardedFn, Token!(;)>::parse_terminated(input)?;
+
+        if fns.is_empty() {
+            return Err(input.error("expected the signatures of the forwarded methods"));
+        }
+
+        Ok(DelegateFns {
+            list,
+            fns: fns.into_iter().collect(),
+        })
+    }
+}
+
+pub(crate) fn expand_fns(delegate: &DelegateFns) -> syn::Result<TokenStream> {
+    delegate
+        .fns
+        .iter()
+        .map(|forwarded| forward_fn(forwarded, Targets::List(&delegate.list), None, None))
+        .collect()
+}
+
+pub(crate) fn is_pinned(receiver: &FnArg) -> bool {
+    match receiver {
+        FnArg::Typed(receiver) => match &*receiver.ty {
+            Type::Path(ty) => ty
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Pin"),
+            _ => false,
+        },
+        FnArg::Receiver(_) => false,
+    }
+}
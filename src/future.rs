@@ -0,0 +1,151 @@
+use crate::forward::{forward_fn, ForwardedFn, Targets};
+use crate::pin::{guards, projection};
+use crate::utils::{
+    coverage_off, first_payload_ty, newtype_variants, with_bound, with_same_assoc, Flags,
+};
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{parse_quote, DeriveInput, Path, Visibility};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "DelegateFuture")?;
+    let flags = Flags::parse(&input.attrs, "future", &["unsafe_project"])?;
+    let unsafe_project = flags.get("unsafe_project");
+    let project = projection(input)?;
+    let trait_path: Path = parse_quote!(::core::future::Future);
+
+    if let (Some(unsafe_project), Some(_)) = (unsafe_project, &project) {
+        return Err(syn::Error::new_spanned(
+            unsafe_project,
+            "`unsafe_project` is not needed with `#[pin_project]`, the projection generated by \
+             `PinProject` is used instead",
+        ));
+    }
+
+    let first_ty = first_payload_ty(input, &variants, "Output")?;
+
+    let name = &input.ident;
+    let output = quote!(<#first_ty as #trait_path>::Output);
+
+    let mut generics = with_bound(&input.generics, parse_quote!(#trait_path));
+
+    generics = with_same_assoc(
+        &generics,
+        &variants,
+        first_ty,
+        parse_quote!(#trait_path<Output = #output>),
+    );
+
+    if unsafe_project.is_none() && project.is_none() {
+        generics = with_bound(&generics, parse_quote!(::core::marker::Unpin));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // With `#[pin_project]` the payloads are projected with the methods generated by
+    // `PinProject`, that also generates the guards.
+    if let Some(project) = project {
+        let arms = variants.iter().map(|variant| {
+            let variant_name = variant.name;
+            let cfg_attrs = variant.cfg_attrs();
+
+            quote! {
+                #(#cfg_attrs)*
+                #project::#variant_name(payload) => #trait_path::poll(payload, cx)
+            }
+        });
+
+        return Ok(quote! {
+            #coverage_off
+            impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+                type Output = #output;
+
+                fn poll(
+                    self: ::core::pin::Pin<&mut Self>,
+                    cx: &mut ::core::task::Context<'_>,
+                ) -> ::core::task::Poll<Self::Output> {
+                    match Self::project(self) {
+                        #(#arms,)*
+                    }
+                }
+            }
+        });
+    }
+
+    // By default the payloads are required to be `Unpin` and are pinned with `Pin::new`, that
+    // doesn't need `unsafe`.
+    let unsafe_project = match unsafe_project {
+        Some(unsafe_project) => unsafe_project,
+        None => {
+            let poll = forward_fn(
+                &ForwardedFn {
+                    attrs: Vec::new(),
+                    vis: Visibility::Inherited,
+                    sig: parse_quote! {
+                        fn poll(
+                            self: ::core::pin::Pin<&mut Self>,
+                            cx: &mut ::core::task::Context<'_>,
+                        ) -> ::core::task::Poll<Self::Output>
+                    },
+                },
+                Targets::Variants(&variants),
+                Some(&trait_path),
+                None,
+            )?;
+
+            return Ok(quote! {
+                #coverage_off
+                impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+                    type Output = #output;
+
+                    #poll
+                }
+            });
+        }
+    };
+
+    // With `#[future(unsafe_project)]` the guards are generated here, see `pin::guards`. The
+    // `unsafe` blocks are spanned to the option, so `unsafe_code` lints of the crate point at it.
+    let guards = guards(input, &variants);
+    let span = unsafe_project.span();
+    let pin_payload = quote_spanned!(span=> unsafe { ::core::pin::Pin::new_unchecked(payload) });
+    let get_mut = quote_spanned!(span=> unsafe { ::core::pin::Pin::get_unchecked_mut(self) });
+
+    let arms = variants.iter().map(|variant| {
+        let variant_name = variant.name;
+        let cfg_attrs = variant.cfg_attrs();
+        let allow_deprecated = variant.allow_deprecated();
+
+        quote! {
+            #(#cfg_attrs)*
+            #allow_deprecated
+            Self::#variant_name(payload) => #trait_path::poll(
+                // SAFETY: the payload is pinned along with the enum, see the guards.
+                #pin_payload,
+                cx,
+            )
+        }
+    });
+
+    Ok(quote! {
+        const _: () = {
+            #guards
+
+            #coverage_off
+            impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+                type Output = #output;
+
+                fn poll(
+                    self: ::core::pin::Pin<&mut Self>,
+                    cx: &mut ::core::task::Context<'_>,
+                ) -> ::core::task::Poll<Self::Output> {
+                    // SAFETY: the payloads are never moved out of the reference.
+                    match #get_mut {
+                        #(#arms,)*
+                    }
+                }
+            }
+        };
+    })
+}
@@ -0,0 +1,51 @@
+use crate::utils::{coverage_off, newtype_variants, with_bound, Flags};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, DeriveInput};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "DelegateHash")?;
+
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, parse_quote!(::core::hash::Hash));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let arms = variants
+        .iter()
+        .map(|variant| {
+            let variant_name = variant.name;
+            let cfg_attrs = variant.cfg_attrs();
+            let allow_deprecated = variant.allow_deprecated();
+
+            Ok(
+                if Flags::parse(variant.attrs, "hash", &["skip"])?.has("skip") {
+                    quote! {
+                        #(#cfg_attrs)*
+                        #allow_deprecated
+                        Self::#variant_name(_) => {}
+                    }
+                } else {
+                    quote! {
+                        #(#cfg_attrs)*
+                        #allow_deprecated
+                        Self::#variant_name(payload) => ::core::hash::Hash::hash(payload, state)
+                    }
+                },
+            )
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        #coverage_off
+        impl #impl_generics ::core::hash::Hash for #name #ty_generics #where_clause {
+            fn hash<__H: ::core::hash::Hasher>(&self, state: &mut __H) {
+                ::core::hash::Hash::hash(&::core::mem::discriminant(self), state);
+
+                match self {
+                    #(#arms,)*
+                }
+            }
+        }
+    })
+}
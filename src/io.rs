@@ -0,0 +1,266 @@
+use crate::forward::{forward_fn, ForwardedFn, Targets};
+use crate::pin::projection;
+use crate::utils::{coverage_off, newtype_variants, with_bound};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, DeriveInput, Path, Signature, Visibility};
+
+/// I/O trait of std or tokio, that is implemented by delegating its methods to the payload.
+pub(crate) enum IoTrait {
+    Read,
+    Write,
+    Seek,
+    BufRead,
+    #[cfg(feature = "tokio")]
+    AsyncRead,
+    #[cfg(feature = "tokio")]
+    AsyncWrite,
+    #[cfg(feature = "tokio")]
+    AsyncSeek,
+    #[cfg(feature = "tokio")]
+    AsyncBufRead,
+}
+
+impl IoTrait {
+    fn derive_name(&self) -> &'static str {
+        match self {
+            IoTrait::Read => "DelegateRead",
+            IoTrait::Write => "DelegateWrite",
+            IoTrait::Seek => "DelegateSeek",
+            IoTrait::BufRead => "DelegateBufRead",
+            #[cfg(feature = "tokio")]
+            IoTrait::AsyncRead => "DelegateAsyncRead",
+            #[cfg(feature = "tokio")]
+            IoTrait::AsyncWrite => "DelegateAsyncWrite",
+            #[cfg(feature = "tokio")]
+            IoTrait::AsyncSeek => "DelegateAsyncSeek",
+            #[cfg(feature = "tokio")]
+            IoTrait::AsyncBufRead => "DelegateAsyncBufRead",
+        }
+    }
+
+    fn trait_path(&self) -> Path {
+        match self {
+            IoTrait::Read => parse_quote!(::std::io::Read),
+            IoTrait::Write => parse_quote!(::std::io::Write),
+            IoTrait::Seek => parse_quote!(::std::io::Seek),
+            IoTrait::BufRead => parse_quote!(::std::io::BufRead),
+            #[cfg(feature = "tokio")]
+            IoTrait::AsyncRead => parse_quote!(::tokio::io::AsyncRead),
+            #[cfg(feature = "tokio")]
+            IoTrait::AsyncWrite => parse_quote!(::tokio::io::AsyncWrite),
+            #[cfg(feature = "tokio")]
+            IoTrait::AsyncSeek => parse_quote!(::tokio::io::AsyncSeek),
+            #[cfg(feature = "tokio")]
+            IoTrait::AsyncBufRead => parse_quote!(::tokio::io::AsyncBufRead),
+        }
+    }
+
+    /// The async traits have pinned receivers, that are projected to the pinned payloads.
+    fn is_async(&self) -> bool {
+        !matches!(
+            self,
+            IoTrait::Read | IoTrait::Write | IoTrait::Seek | IoTrait::BufRead
+        )
+    }
+
+    /// Signatures of the required methods, and the provided ones, that the payloads can
+    /// override, e.g. to support vectored writes.
+    fn sigs(&self) -> Vec<Signature> {
+        match self {
+            IoTrait::Read => vec![
+                parse_quote! {
+                    fn read(&mut self, buf: &mut [u8]) -> ::std::io::Result<usize>
+                },
+                parse_quote! {
+                    fn read_vectored(
+                        &mut self,
+                        bufs: &mut [::std::io::IoSliceMut<'_>],
+                    ) -> ::std::io::Result<usize>
+                },
+                parse_quote! {
+                    fn read_to_end(
+                        &mut self,
+                        buf: &mut ::std::vec::Vec<u8>,
+                    ) -> ::std::io::Result<usize>
+                },
+                parse_quote! {
+                    fn read_to_string(
+                        &mut self,
+                        buf: &mut ::std::string::String,
+                    ) -> ::std::io::Result<usize>
+                },
+                parse_quote! {
+                    fn read_exact(&mut self, buf: &mut [u8]) -> ::std::io::Result<()>
+                },
+            ],
+            IoTrait::Write => vec![
+                parse_quote! {
+                    fn write(&mut self, buf: &[u8]) -> ::std::io::Result<usize>
+                },
+                parse_quote! {
+                    fn write_vectored(
+                        &mut self,
+                        bufs: &[::std::io::IoSlice<'_>],
+                    ) -> ::std::io::Result<usize>
+                },
+                parse_quote! {
+                    fn flush(&mut self) -> ::std::io::Result<()>
+                },
+                parse_quote! {
+                    fn write_all(&mut self, buf: &[u8]) -> ::std::io::Result<()>
+                },
+                parse_quote! {
+                    fn write_fmt(
+                        &mut self,
+                        fmt: ::core::fmt::Arguments<'_>,
+                    ) -> ::std::io::Result<()>
+                },
+            ],
+            IoTrait::Seek => vec![
+                parse_quote! {
+                    fn seek(&mut self, pos: ::std::io::SeekFrom) -> ::std::io::Result<u64>
+                },
+                parse_quote! {
+                    fn rewind(&mut self) -> ::std::io::Result<()>
+                },
+                parse_quote! {
+                    fn stream_position(&mut self) -> ::std::io::Result<u64>
+                },
+            ],
+            IoTrait::BufRead => vec![
+                parse_quote! {
+                    fn fill_buf(&mut self) -> ::std::io::Result<&[u8]>
+                },
+                parse_quote! {
+                    fn consume(&mut self, amt: usize)
+                },
+                parse_quote! {
+                    fn read_until(
+                        &mut self,
+                        byte: u8,
+                        buf: &mut ::std::vec::Vec<u8>,
+                    ) -> ::std::io::Result<usize>
+                },
+                parse_quote! {
+                    fn read_line(
+                        &mut self,
+                        buf: &mut ::std::string::String,
+                    ) -> ::std::io::Result<usize>
+                },
+            ],
+            #[cfg(feature = "tokio")]
+            IoTrait::AsyncRead => vec![parse_quote! {
+                fn poll_read(
+                    self: ::core::pin::Pin<&mut Self>,
+                    cx: &mut ::core::task::Context<'_>,
+                    buf: &mut ::tokio::io::ReadBuf<'_>,
+                ) -> ::core::task::Poll<::std::io::Result<()>>
+            }],
+            #[cfg(feature = "tokio")]
+            IoTrait::AsyncWrite => vec![
+                parse_quote! {
+                    fn poll_write(
+                        self: ::core::pin::Pin<&mut Self>,
+                        cx: &mut ::core::task::Context<'_>,
+                        buf: &[u8],
+                    ) -> ::core::task::Poll<::std::io::Result<usize>>
+                },
+                parse_quote! {
+                    fn poll_flush(
+                        self: ::core::pin::Pin<&mut Self>,
+                        cx: &mut ::core::task::Context<'_>,
+                    ) -> ::core::task::Poll<::std::io::Result<()>>
+                },
+                parse_quote! {
+                    fn poll_shutdown(
+                        self: ::core::pin::Pin<&mut Self>,
+                        cx: &mut ::core::task::Context<'_>,
+                    ) -> ::core::task::Poll<::std::io::Result<()>>
+                },
+                parse_quote! {
+                    fn poll_write_vectored(
+                        self: ::core::pin::Pin<&mut Self>,
+                        cx: &mut ::core::task::Context<'_>,
+                        bufs: &[::std::io::IoSlice<'_>],
+                    ) -> ::core::task::Poll<::std::io::Result<usize>>
+                },
+                parse_quote! {
+                    fn is_write_vectored(&self) -> bool
+                },
+            ],
+            #[cfg(feature = "tokio")]
+            IoTrait::AsyncSeek => vec![
+                parse_quote! {
+                    fn start_seek(
+                        self: ::core::pin::Pin<&mut Self>,
+                        position: ::std::io::SeekFrom,
+                    ) -> ::std::io::Result<()>
+                },
+                parse_quote! {
+                    fn poll_complete(
+                        self: ::core::pin::Pin<&mut Self>,
+                        cx: &mut ::core::task::Context<'_>,
+                    ) -> ::core::task::Poll<::std::io::Result<u64>>
+                },
+            ],
+            #[cfg(feature = "tokio")]
+            IoTrait::AsyncBufRead => vec![
+                parse_quote! {
+                    fn poll_fill_buf(
+                        self: ::core::pin::Pin<&mut Self>,
+                        cx: &mut ::core::task::Context<'_>,
+                    ) -> ::core::task::Poll<::std::io::Result<&[u8]>>
+                },
+                parse_quote! {
+                    fn consume(self: ::core::pin::Pin<&mut Self>, amt: usize)
+                },
+            ],
+        }
+    }
+}
+
+pub(crate) fn expand(input: &DeriveInput, io: IoTrait) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, io.derive_name())?;
+    let trait_path = io.trait_path();
+
+    let name = &input.ident;
+
+    let mut generics = with_bound(&input.generics, parse_quote!(#trait_path));
+    let projection = projection(input)?;
+
+    // The pinned receiver is projected to the pinned payload, which requires it to be `Unpin`,
+    // unless the enum derives `PinProject`.
+    if io.is_async() && projection.is_none() {
+        generics = with_bound(&generics, parse_quote!(::core::marker::Unpin));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let fns = io
+        .sigs()
+        .into_iter()
+        .map(|sig| {
+            let forwarded = ForwardedFn {
+                attrs: Vec::new(),
+                vis: Visibility::Inherited,
+                sig,
+            };
+
+            forward_fn(
+                &forwarded,
+                Targets::Variants(&variants),
+                Some(&trait_path),
+                projection.as_ref(),
+            )
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        #coverage_off
+        impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+            #(#fns)*
+        }
+    })
+}
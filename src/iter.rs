@@ -0,0 +1,107 @@
+use crate::forward::forward_trait_fns;
+use crate::utils::{
+    coverage_off, first_payload_ty, newtype_variants, with_bound, with_same_assoc, Flags,
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, DeriveInput, Path, Signature, TypeParamBound};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "DelegateIterator")?;
+    let flags = Flags::parse(
+        &input.attrs,
+        "iterator",
+        &["double_ended", "exact_size", "fused"],
+    )?;
+
+    let first_ty = first_payload_ty(input, &variants, "Item")?;
+    let item = quote!(<#first_ty as ::core::iter::Iterator>::Item);
+
+    // The optional traits are implemented only if requested, as the impls require all the
+    // payloads to implement them.
+    let mut impls = vec![(
+        parse_quote!(::core::iter::Iterator),
+        Some(quote!(type Item = #item;)),
+        vec![
+            parse_quote! {
+                fn next(&mut self) -> ::core::option::Option<Self::Item>
+            },
+            parse_quote! {
+                fn size_hint(&self) -> (usize, ::core::option::Option<usize>)
+            },
+            parse_quote! {
+                fn nth(&mut self, n: usize) -> ::core::option::Option<Self::Item>
+            },
+            parse_quote! {
+                fn fold<B, F>(self, init: B, f: F) -> B
+                where
+                    F: ::core::ops::FnMut(B, Self::Item) -> B
+            },
+        ],
+    )];
+
+    if flags.has("double_ended") {
+        impls.push((
+            parse_quote!(::core::iter::DoubleEndedIterator),
+            None,
+            vec![
+                parse_quote! {
+                    fn next_back(&mut self) -> ::core::option::Option<Self::Item>
+                },
+                parse_quote! {
+                    fn nth_back(&mut self, n: usize) -> ::core::option::Option<Self::Item>
+                },
+                parse_quote! {
+                    fn rfold<B, F>(self, init: B, f: F) -> B
+                    where
+                        F: ::core::ops::FnMut(B, Self::Item) -> B
+                },
+            ],
+        ));
+    }
+
+    if flags.has("exact_size") {
+        impls.push((
+            parse_quote!(::core::iter::ExactSizeIterator),
+            None,
+            vec![parse_quote! {
+                fn len(&self) -> usize
+            }],
+        ));
+    }
+
+    if flags.has("fused") {
+        impls.push((parse_quote!(::core::iter::FusedIterator), None, Vec::new()));
+    }
+
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, parse_quote!(::core::iter::Iterator));
+    let generics = with_same_assoc(
+        &generics,
+        &variants,
+        first_ty,
+        parse_quote!(::core::iter::Iterator<Item = #item>),
+    );
+
+    impls
+        .into_iter()
+        .map(
+            |(trait_path, assoc_item, sigs): (Path, _, Vec<Signature>)| {
+                let bound: TypeParamBound = parse_quote!(#trait_path);
+                let generics = with_bound(&generics, bound);
+                let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+                let fns = forward_trait_fns(&variants, &trait_path, sigs, None)?;
+
+                Ok(quote! {
+                    #coverage_off
+                    impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+                        #assoc_item
+
+                        #(#fns)*
+                    }
+                })
+            },
+        )
+        .collect()
+}
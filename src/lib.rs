@@ -107,111 +107,4287 @@
 //!     }
 //! }
 //! ```
+//!
+//! Attributes are emitted on the corresponding arm exactly as written and in the same order, so
+//! lint attributes, like `#[expect]`, land on the arm that triggers the lint:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Variants {
+//!     Foo(String),
+//!
+//!     #[deprecated]
+//!     Bar(String)
+//! }
+//!
+//! impl Variants {
+//!     pub fn value(&self) -> &str {
+//!         all_the_same!(match self {
+//!             Self::[Foo, #[expect(deprecated, reason = "Bar is going away")] Bar](v) => v
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! Several variants can share the attributes by grouping them in braces:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Socket {
+//!     Tcp(String),
+//!     #[cfg(unix)]
+//!     Unix(String),
+//!     #[cfg(unix)]
+//!     UnixDatagram(String),
+//! }
+//!
+//! impl Socket {
+//!     pub fn path(&self) -> &str {
+//!         all_the_same!(match self {
+//!             Socket::[Tcp, #[cfg(unix)] { Unix, UnixDatagram }](v) => v
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! The expansion has an arm for every listed variant, that carries the attributes of the variant,
+//! so the arms of the compiled-out variants go away with them. The only `_` arms of the expansion
+//! are the fallbacks: the `else` or `_` arm of the match, the `else` branches of the `if let` and
+//! `let ... else` forms, the panicking arm added by `#[non_exhaustive]`, and the arms of the
+//! `#[table(..)]` entries that catch the other variants. They are emitted last with
+//! `#[allow(unreachable_patterns)]`, so they don't warn when the `#[cfg]`s happen to enable all the
+//! listed variants. The `if let` form is also expanded into a `match`, so it doesn't trigger
+//! `irrefutable_let_patterns` for the enums with a single variant either:
+//!
+//! ```
+//! #![deny(unreachable_patterns)]
+//!
+//! use all_the_same::all_the_same;
+//!
+//! enum Socket {
+//!     Tcp(String),
+//!     #[cfg(unix)]
+//!     Unix(String),
+//!     #[cfg(windows)]
+//!     NamedPipe(String),
+//! }
+//!
+//! impl Socket {
+//!     pub fn path(&self) -> Option<&str> {
+//!         all_the_same!(match self {
+//!             Socket::[Tcp, #[cfg(unix)] Unix](v) => Some(v),
+//!             _ => None
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! # Enum paths
+//!
+//! The enum can be referred to by a path, `Self` or, for generic enums, with the type arguments,
+//! with or without the turbofish:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! mod number {
+//!     pub enum Number<T> {
+//!         I32(i32),
+//!         Other(T),
+//!     }
+//! }
+//!
+//! fn is_zero(number: &number::Number<i64>) -> bool {
+//!     all_the_same!(match number {
+//!         number::Number<i64>::[I32, Other](n) => *n == 0
+//!     })
+//! }
+//! ```
+//!
+//! Inside the enum's own impls `Self` also works for generic enums:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Number<T> {
+//!     I32(i32),
+//!     Other(T),
+//! }
+//!
+//! impl<T: Into<i64> + Copy> Number<T> {
+//!     pub fn to_i64(&self) -> i64 {
+//!         all_the_same!(match self {
+//!             Self::[I32, Other](n) => (*n).into()
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! Variants named with raw identifiers are listed the same way, e.g. `Keyword::[r#fn, r#type]`:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! #[allow(non_camel_case_types)]
+//! enum Keyword {
+//!     r#fn(u32),
+//!     r#type(u32),
+//! }
+//!
+//! fn position(keyword: &Keyword) -> u32 {
+//!     all_the_same!(match keyword {
+//!         Keyword::[r#fn, r#type](pos) => *pos
+//!     })
+//! }
+//! ```
+//!
+//! # Struct variants
+//!
+//! Variants with named fields are supported too: the field is specified in braces instead of the
+//! binding in parentheses, optionally with its own binding, and the rest of the fields are ignored:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Shape {
+//!     Circle { radius: f64, x: f64, y: f64 },
+//!     Square { side: f64 },
+//! }
+//!
+//! impl Shape {
+//!     pub fn size(&self) -> f64 {
+//!         all_the_same!(match self {
+//!             Shape::[Circle]{ radius: size } => size * 2.0,
+//!             Shape::[Square]{ side } => *side
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! # Several fields
+//!
+//! Tuple variants with several fields are matched by listing a binding for each of them. Arms
+//! without a body then evaluate to a tuple of the fields:
+//!
+//! ```
+//! use std::net::{SocketAddr, TcpStream, UdpSocket};
+//! use all_the_same::all_the_same;
+//!
+//! enum Socket {
+//!     Tcp(TcpStream, SocketAddr),
+//!     Udp(UdpSocket, SocketAddr),
+//! }
+//!
+//! impl Socket {
+//!     pub fn is_local(&self) -> bool {
+//!         all_the_same!(match self {
+//!             Socket::[Tcp, Udp](_socket, addr) => addr.ip().is_loopback()
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! # Payload patterns
+//!
+//! Payload bindings can be arbitrary patterns. Arms without a body and the `#[coerce]` option still
+//! need plain bindings though:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Setting {
+//!     Timeout(Option<u64>),
+//!     Retries(Option<u64>),
+//! }
+//!
+//! impl Setting {
+//!     pub fn value_or_default(&self) -> u64 {
+//!         all_the_same!(match self {
+//!             Setting::[Timeout, Retries](Some(v)) => *v,
+//!             Setting::[Timeout, Retries](None) => 0
+//!         })
+//!     }
+//! }
+//! ```
+//!//!
+//! Bindings can be `mut`, also together with `#[coerce]`:
+//!
+//! ```
+//! use std::collections::VecDeque;
+//! use all_the_same::all_the_same;
+//!
+//! enum Buffer {
+//!     Vec(Vec<u8>),
+//!     Deque(VecDeque<u8>),
+//! }
+//!
+//! impl Buffer {
+//!     pub fn into_vec_with(self, byte: u8) -> Vec<u8> {
+//!         all_the_same!(#[coerce(Vec<u8>)] match self {
+//!             Buffer::[Vec, Deque](mut bytes) => {
+//!                 bytes.push(byte);
+//!                 bytes
+//!             }
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! # Pinned payloads
+//!
+//! The payload binding marked with `pin` is wrapped in
+//! [`Pin::new`](https://doc.rust-lang.org/std/pin/struct.Pin.html#method.new) in every arm, so the
+//! delegation to the `Unpin` payloads doesn't need to repeat it:
+//!
+//! ```
+//! use std::io;
+//! use std::pin::Pin;
+//! use std::task::{Context, Poll};
+//! use tokio::io::AsyncWrite;
+//! use tokio::net::{TcpStream, UnixStream};
+//! use all_the_same::all_the_same;
+//!
+//! enum Stream {
+//!     Tcp(TcpStream),
+//!     Unix(UnixStream),
+//! }
+//!
+//! impl AsyncWrite for Stream {
+//!     fn poll_write(
+//!         self: Pin<&mut Self>,
+//!         cx: &mut Context<'_>,
+//!         buf: &[u8],
+//!     ) -> Poll<Result<usize, io::Error>> {
+//!         all_the_same!(match self.get_mut() {
+//!             Stream::[Tcp, Unix](pin s) => s.poll_write(cx, buf)
+//!         })
+//!     }
+//!
+//!     fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), io::Error>> {
+//!         all_the_same!(match self.get_mut() {
+//!             Stream::[Tcp, Unix](pin s) => s.poll_shutdown(cx)
+//!         })
+//!     }
+//!
+//!     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+//!         all_the_same!(match self.get_mut() {
+//!             Stream::[Tcp, Unix](pin s) => s.poll_flush(cx)
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! With `pin mut s` the pinned binding is mutable, so it can be reborrowed with `s.as_mut()`.
+//!
+//! # Unit variants
+//!
+//! Without the payload binding the arms match unit variants, so they can be mixed with the newtype
+//! ones:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Connection {
+//!     Tcp(std::net::TcpStream),
+//!     Udp(std::net::UdpSocket),
+//!     Closed,
+//!     Failed,
+//! }
+//!
+//! impl Connection {
+//!     pub fn is_open(&self) -> bool {
+//!         all_the_same!(match self {
+//!             Connection::[Tcp, Udp](s) => s.local_addr().is_ok(),
+//!             Connection::[Closed, Failed] => false
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! # Binding the whole value
+//!
+//! Sometimes the arm needs the enum value itself alongside its payload. Prefix the arm with a
+//! binding and `@`, just like in a regular Rust pattern:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Shape {
+//!     Circle(f64),
+//!     Square(f64),
+//! }
+//!
+//! impl Shape {
+//!     pub fn kind(&self) -> &'static str {
+//!         match self {
+//!             Self::Circle(_) => "circle",
+//!             Self::Square(_) => "square",
+//!         }
+//!     }
+//!
+//!     pub fn describe(&self) -> String {
+//!         all_the_same!(match self {
+//!             shape @ Self::[Circle, Square](size) => format!("{} of size {}", shape.kind(), size)
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! # Per-variant overrides
+//!
+//! A variant in the list can have its own arm body, which replaces the shared one. This comes in
+//! handy when some variants need to diverge (e.g. with `return` or `panic!`), while others produce
+//! a value:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Number {
+//!     I32(i32),
+//!     I64(i64),
+//!     Invalid(String),
+//! }
+//!
+//! impl Number {
+//!     pub fn to_i64(&self) -> Option<i64> {
+//!         let n = all_the_same!(match self {
+//!             Number::[I32, I64, Invalid => return None](n) => i64::from(*n)
+//!         });
+//!
+//!         Some(n)
+//!     }
+//! }
+//! ```
+//!
+//! # Cold variants
+//!
+//! A variant in the list can be marked with `#[cold]`, if it's rarely matched. Its arm goes after
+//! the others and is hinted as unlikely, the same way as with `core::hint::cold_path`:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Source {
+//!     Memory(Vec<u8>),
+//!     Disk(Vec<u8>),
+//!     Network(Vec<u8>),
+//! }
+//!
+//! fn len(source: &Source) -> usize {
+//!     all_the_same!(match source {
+//!         Source::[Memory, Disk, #[cold] Network](data) => data.len()
+//!     })
+//! }
+//!
+//! assert_eq!(len(&Source::Network(vec![1, 2, 3])), 3);
+//! ```
+//!
+//! # Variant adapters
+//!
+//! If the payload of a variant needs a small adjustment before the shared body works with it, the
+//! variant can have an adapter expression, that rebinds the payload:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//! use std::io::{self, Write};
+//!
+//! enum Sink {
+//!     File(std::fs::File),
+//!     Buffer(Vec<u8>),
+//!     Custom(Box<dyn Write>),
+//! }
+//!
+//! impl Sink {
+//!     pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+//!         all_the_same!(match self {
+//!             Sink::[File, Buffer, Custom(&mut **w)](w) => writeln!(w, "{}", line)
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! The payloads behind smart pointers, like `Box<T>`, can be dereferenced with the `*Variant`
+//! shorthand instead, once for every `*`. The payload stays a shared or a mutable reference, like
+//! it's bound. The payloads, that need more than `Deref`, still get an adapter, e.g. to lock a
+//! mutex:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//! use std::io::{self, Write};
+//! use std::sync::{Arc, Mutex};
+//!
+//! enum Sink {
+//!     Buffer(Vec<u8>),
+//!     Custom(Box<dyn Write>),
+//!     Nested(Box<Box<Vec<u8>>>),
+//!     Shared(Arc<Mutex<Vec<u8>>>),
+//! }
+//!
+//! impl Sink {
+//!     pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+//!         all_the_same!(match self {
+//!             Sink::[Buffer, *Custom, **Nested, Shared(&mut *w.lock().unwrap())](w) => {
+//!                 writeln!(w, "{}", line)
+//!             }
+//!         })
+//!     }
+//! }
+//!
+//! let mut sink = Sink::Nested(Box::new(Box::new(Vec::new())));
+//!
+//! sink.write_line("foo").unwrap();
+//! ```
+//!
+//! # Variant constants
+//!
+//! Variants in the list can be assigned values with `= value`. The values are bound to the last
+//! binding of the payload, which helps with logging and metrics:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//! use std::collections::HashMap;
+//!
+//! enum Request {
+//!     Get(String),
+//!     Delete(String),
+//! }
+//!
+//! fn record(request: &Request, metrics: &mut HashMap<&'static str, usize>) {
+//!     all_the_same!(match request {
+//!         Request::[Get = "get", Delete = "delete"](path, method) => {
+//!             *metrics.entry(method).or_default() += path.len();
+//!         }
+//!     })
+//! }
+//! ```
+//!
+//! For unit variants the only binding is the one for the values:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Level {
+//!     Low,
+//!     High,
+//! }
+//!
+//! fn threshold(level: &Level) -> u32 {
+//!     all_the_same!(match level {
+//!         Level::[Low = 10, High = 100](threshold)
+//!     })
+//! }
+//! ```
+//!
+//! # Variant names
+//!
+//! The name of the variant can be bound as `&'static str` with `as name` after the payload. The
+//! binding is available in the arm body, but not in the match guard:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Event {
+//!     Click(u32),
+//!     Scroll(u32),
+//! }
+//!
+//! fn log(event: &Event) -> String {
+//!     all_the_same!(match event {
+//!         Event::[Click, Scroll](id) as kind => format!("{} #{}", kind, id)
+//!     })
+//! }
+//!
+//! assert_eq!(log(&Event::Scroll(7)), "Scroll #7");
+//! ```
+//!
+//! # Rebuilding the variant
+//!
+//! `same!(...)` in the arm body stands for the constructor of the matched variant, so the value can
+//! be rebuilt with a new payload:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum Temperature<T> {
+//!     Celsius(T),
+//!     Fahrenheit(T),
+//! }
+//!
+//! impl<T> Temperature<T> {
+//!     pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Temperature<U> {
+//!         // `Self` is `Temperature<T>`, so the enum is named explicitly to change the payload
+//!         // type.
+//!         all_the_same!(match self {
+//!             Temperature::[Celsius, Fahrenheit](t) => same!(f(t))
+//!         })
+//!     }
+//! }
+//!
+//! assert_eq!(Temperature::Celsius(36).map(f64::from), Temperature::Celsius(36.0));
+//! ```
+//!
+//! With the enum path in front, `Enum::same!(...)` constructs the variant with the same name of
+//! another enum, which is handy for conversions between parallel enums:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Request {
+//!     Get(String),
+//!     Delete(String),
+//! }
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum Response {
+//!     Get(usize),
+//!     Delete(usize),
+//! }
+//!
+//! fn handle(request: Request) -> Response {
+//!     all_the_same!(match request {
+//!         Request::[Get, Delete](path) => Response::same!(path.len())
+//!     })
+//! }
+//!
+//! assert_eq!(handle(Request::Delete("/tmp".into())), Response::Delete(4));
+//! ```
+//!
+//! In a nested `all_the_same!`, `same!(...)` constructs the variant of the nested match. The outer
+//! one can rename its constructor with `#[same(name)]`, so both variants can be rebuilt. The other
+//! names the expansion uses internally are hygienic, so they don't clash between the nested
+//! invocations:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum Number {
+//!     Int(i64),
+//!     Float(f64),
+//! }
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum Measurement {
+//!     Exact(Number),
+//!     Approx(Number),
+//! }
+//!
+//! fn double(measurement: Measurement) -> Measurement {
+//!     all_the_same!(#[same(outer)] match measurement {
+//!         Measurement::[Exact, Approx](number) => all_the_same!(match number {
+//!             Number::[Int, Float](n) => outer!(same!(n + n))
+//!         })
+//!     })
+//! }
+//!
+//! assert_eq!(
+//!     double(Measurement::Approx(Number::Float(1.5))),
+//!     Measurement::Approx(Number::Float(3.0))
+//! );
+//! ```
+//!
+//! # Several arms
+//!
+//! Different groups of variants can have their own arm bodies in the same match:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Value {
+//!     I32(i32),
+//!     I64(i64),
+//!     Text(String),
+//!     Bytes(Vec<u8>),
+//! }
+//!
+//! impl Value {
+//!     pub fn size(&self) -> usize {
+//!         all_the_same!(match self {
+//!             Value::[I32, I64](n) => std::mem::size_of_val(n),
+//!             Value::[Text, Bytes](b) => b.len()
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! Each list needs at least one variant, separated with commas, and a variant can't be listed
+//! twice in the same list, unless the occurrences are under different `#[cfg]`s:
+//!
+//! ```compile_fail
+//! use all_the_same::all_the_same;
+//!
+//! enum Value {
+//!     I32(i32),
+//!     I64(i64),
+//! }
+//!
+//! fn is_zero(value: &Value) -> bool {
+//!     all_the_same!(match value {
+//!         Value::[I32, I64, I32](n) => *n == 0
+//!     })
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use all_the_same::all_the_same;
+//!
+//! enum Value {
+//!     I32(i32),
+//!     I64(i64),
+//! }
+//!
+//! fn is_zero(value: &Value) -> bool {
+//!     all_the_same!(match value {
+//!         Value::[](n) => *n == 0,
+//!         _ => false
+//!     })
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use all_the_same::all_the_same;
+//!
+//! enum Value {
+//!     I32(i32),
+//!     I64(i64),
+//! }
+//!
+//! fn is_zero(value: &Value) -> bool {
+//!     all_the_same!(match value {
+//!         Value::[I32 I64](n) => *n == 0
+//!     })
+//! }
+//! ```
+//!
+//! # Regular arms
+//!
+//! The regular `match` arms can be mixed with the macro ones, they are emitted as is, in the same
+//! order. Note that the options of the macro only apply to the arms with the variant lists:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Shape {
+//!     Circle(f64),
+//!     Square(f64),
+//!     Rect(f64, f64),
+//! }
+//!
+//! fn scale(shape: &mut Shape, k: f64) {
+//!     all_the_same!(match shape {
+//!         Shape::[Circle, Square](size) => *size *= k,
+//!         Shape::Rect(w, h) => {
+//!             *w *= k;
+//!             *h *= k;
+//!         }
+//!     })
+//! }
+//! ```
+//!
+//! # All variants
+//!
+//! Instead of listing all the variants in every method, the enum can register them with the
+//! [`#[variants]`](variants) attribute, then `[*]` stands for all of them, including their
+//! `#[cfg]` attributes:
+//!
+//! ```
+//! use all_the_same::{all_the_same, variants};
+//! use tokio::net::{TcpStream, UnixStream};
+//!
+//! #[variants]
+//! pub enum Stream {
+//!     Tcp(TcpStream),
+//!     Unix(UnixStream),
+//!     #[cfg(feature = "tls")]
+//!     Tls(TcpStream),
+//! }
+//!
+//! impl Stream {
+//!     pub fn is_readable(&self) -> bool {
+//!         all_the_same!(match self {
+//!             Stream::[*](s) => s.try_read(&mut []).is_ok()
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! The variants are looked up by the enum path, so `[*]` only works for the enums of the current
+//! crate and doesn't work with `Self`. The match with the single `[*]` arm can be written with the
+//! [`for_each_variant!`](for_each_variant) shorthand, e.g.
+//! `for_each_variant!(self, Stream, |s| s.try_read(&mut []).is_ok())`.
+//!
+//! The enum can also be defined with the [`sum_type!`](sum_type) macro, that registers the variants
+//! the same way and generates the `From` conversions and the accessors of the payloads. The
+//! [`compose!`](compose) macro defines it from the variants of other enums, e.g. to add a variant
+//! to an enum of another crate.
+//!
+//! Some of the variants can be excluded with `except`, to be handled by other arms:
+//!
+//! ```
+//! use all_the_same::{all_the_same, variants};
+//!
+//! #[variants]
+//! pub enum Value {
+//!     I32(i32),
+//!     I64(i64),
+//!     U8(u8),
+//!     Text(String),
+//! }
+//!
+//! impl Value {
+//!     pub fn is_zero(&self) -> bool {
+//!         all_the_same!(match self {
+//!             Value::[* except Text](n) => *n == 0,
+//!             Value::Text(_) => false
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! # Variant sets
+//!
+//! Lists that are repeated in several methods can be defined once with the
+//! [`variant_set!`](variant_set) macro and referred to with `[..SET]`. Like `[*]`, the set can be
+//! combined with `except`:
+//!
+//! ```
+//! use all_the_same::{all_the_same, variant_set};
+//! use tokio::net::{TcpStream, UnixStream};
+//!
+//! pub enum Stream {
+//!     Tcp(TcpStream),
+//!     Unix(UnixStream),
+//!     Tls(TcpStream),
+//! }
+//!
+//! variant_set!(STREAMS = [Tcp, Unix, Tls]);
+//!
+//! impl Stream {
+//!     pub fn is_readable(&self) -> bool {
+//!         all_the_same!(match self {
+//!             Self::[..STREAMS](s) => s.try_read(&mut []).is_ok()
+//!         })
+//!     }
+//!
+//!     pub fn peer_port(&self) -> Option<u16> {
+//!         all_the_same!(match self {
+//!             Self::[..STREAMS except Unix](s) => s.peer_addr().ok().map(|addr| addr.port()),
+//!             Self::Unix(_) => None
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! # Strict lists
+//!
+//! With the `#[strict]` option, every variant of the enum marked with `#[variants]` has to be
+//! listed in the arms, even if there is a fallback. A newly added variant is then reported by name
+//! instead of silently falling into the catch-all arm:
+//!
+//! ```compile_fail
+//! use all_the_same::{all_the_same, variants};
+//!
+//! #[variants]
+//! enum Shape {
+//!     Circle(f64),
+//!     Square(f64),
+//!     Triangle(f64),
+//! }
+//!
+//! fn size(shape: &Shape) -> f64 {
+//!     // error: missing variants in the lists: `Triangle`
+//!     all_the_same!(#[strict] match shape {
+//!         Shape::[Circle, Square](size) => *size,
+//!         _ => 0.0,
+//!     })
+//! }
+//! ```
+//!
+//! The variants listed in the arms are also checked against the `#[cfg]` attributes they have in
+//! the enum, whenever the enum is looked up for `[*]` or `#[strict]`. A variant, that is listed
+//! without the gate it has in the enum, only compiles in some configurations, so it's an error with
+//! `#[strict]`, and a warning with the `nightly-diagnostics` feature otherwise. The gates are
+//! compared as they are written, with `all(..)` split into the separate predicates:
+//!
+//! ```compile_fail
+//! use all_the_same::{all_the_same, variants};
+//!
+//! #[variants]
+//! enum Shape {
+//!     Circle(f64),
+//!     #[cfg(feature = "polygons")]
+//!     Square(f64),
+//! }
+//!
+//! fn size(shape: &Shape) -> f64 {
+//!     // error: variant `Square` is gated with `#[cfg(feature = "polygons")]` in the enum
+//!     all_the_same!(#[strict] match shape {
+//!         Shape::[Circle, Square](size) => *size,
+//!     })
+//! }
+//! ```
+//!
+//! # Matching several values
+//!
+//! If the matched expression is a tuple, like `(a, b)`, its values are matched against the same
+//! variant and each of them gets its own payload binding. Together with a fallback for the
+//! mismatched variants, this covers comparisons and merges:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Bound {
+//!     Inclusive(u32),
+//!     Exclusive(u32),
+//! }
+//!
+//! impl Bound {
+//!     pub fn max(&self, other: &Bound) -> Option<u32> {
+//!         all_the_same!(match (self, other) {
+//!             Bound::[Inclusive, Exclusive](a, b) => Some(*a.max(b)),
+//!             _ => None
+//!         })
+//!     }
+//! }
+//!
+//! assert_eq!(Bound::Inclusive(1).max(&Bound::Inclusive(5)), Some(5));
+//! assert_eq!(Bound::Inclusive(1).max(&Bound::Exclusive(5)), None);
+//! ```
+//!
+//! The bindings of the variants with several fields are grouped by the value, the tuple pattern of
+//! the value lists its fields. So a tuple payload of the value is destructured with a nested tuple,
+//! e.g. `((a, b),)`:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Range {
+//!     Open(u32, u32),
+//!     Closed(u32, u32),
+//! }
+//!
+//! impl Range {
+//!     pub fn overlaps(&self, other: &Range) -> Option<bool> {
+//!         all_the_same!(match (self, other) {
+//!             Range::[Open, Closed]((start, end), (other_start, other_end)) => {
+//!                 Some(start < other_end && other_start < end)
+//!             }
+//!             _ => None
+//!         })
+//!     }
+//! }
+//!
+//! assert_eq!(Range::Open(1, 5).overlaps(&Range::Open(4, 8)), Some(true));
+//! assert_eq!(Range::Open(1, 5).overlaps(&Range::Closed(4, 8)), None);
+//! ```
+//!
+//! The values are of the same variant, so the groups have to have the same number of fields:
+//!
+//! ```compile_fail
+//! use all_the_same::all_the_same;
+//!
+//! enum Range {
+//!     Open(u32, u32),
+//!     Closed(u32, u32),
+//! }
+//!
+//! fn starts(a: &Range, b: &Range) -> Option<(u32, u32)> {
+//!     all_the_same!(match (a, b) {
+//!         // error: expected the same number of fields for each of the matched values
+//!         Range::[Open, Closed]((a_start, _), b_start) => Some((*a_start, *b_start)),
+//!         _ => None
+//!     })
+//! }
+//! ```
+//! Values of different enums can be matched with a tuple of the variant lists. The variants at the
+//! same positions of the lists are matched together:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Request {
+//!     Get(String),
+//!     Put(Vec<u8>),
+//! }
+//!
+//! enum Limit {
+//!     Get(u16),
+//!     Put(u32),
+//! }
+//!
+//! fn fits(request: &Request, limit: &Limit) -> bool {
+//!     all_the_same!(match (request, limit) {
+//!         (Request::[Get, Put](req), Limit::[Get, Put](max)) => {
+//!             req.len() as u64 <= u64::from(*max)
+//!         }
+//!         _ => false
+//!     })
+//! }
+//! ```
+//!
+//! For the plain check if two values are the same variant, there is the
+//! [`same_variant!`](same_variant) macro:
+//!
+//! ```
+//! use all_the_same::same_variant;
+//!
+//! enum Token {
+//!     Number(f64),
+//!     Ident(String),
+//!     Comma,
+//! }
+//!
+//! fn is_repeated(prev: &Token, next: &Token) -> bool {
+//!     same_variant!(prev, next, Token::[Number, Ident, Comma])
+//! }
+//!
+//! assert!(is_repeated(&Token::Number(1.0), &Token::Number(2.0)));
+//! ```
+//!
+//! Similarly, [`is_any_of!`](is_any_of) checks if the value is one of the listed variants:
+//!
+//! ```
+//! use all_the_same::is_any_of;
+//!
+//! enum Token {
+//!     Number(f64),
+//!     Ident(String),
+//!     Comma,
+//! }
+//!
+//! assert!(is_any_of!(Token::Ident("x".into()), Token::[Number, Ident]));
+//! ```
+//!
+//! # Fallback
+//!
+//! Variants that are not listed can be handled with the `else` arm, that binds the whole value:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Value {
+//!     Int(i64),
+//!     Float(f64),
+//!     Text(String),
+//! }
+//!
+//! impl Value {
+//!     pub fn kind(&self) -> &'static str {
+//!         match self {
+//!             Value::Int(_) => "int",
+//!             Value::Float(_) => "float",
+//!             Value::Text(_) => "text",
+//!         }
+//!     }
+//!
+//!     pub fn to_f64(&self) -> Result<f64, String> {
+//!         all_the_same!(match self {
+//!             Value::[Int, Float](n) => Ok(*n as f64),
+//!             else other => Err(format!("expected a number, got {}", other.kind()))
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! The `else` keyword can be omitted, so the fallback looks like a regular catch-all arm. This is
+//! handy for the `#[non_exhaustive]` enums from other crates:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//! use std::io::ErrorKind;
+//!
+//! #[non_exhaustive]
+//! pub enum Stream {
+//!     Tcp(tokio::net::TcpStream),
+//!     Unix(tokio::net::UnixStream),
+//! }
+//!
+//! fn check_peer(stream: &Stream) -> Result<(), ErrorKind> {
+//!     all_the_same!(match stream {
+//!         Stream::[Tcp, Unix](s) => s.peer_addr().map(drop).map_err(|e| e.kind()),
+//!         _ => Err(ErrorKind::Unsupported)
+//!     })
+//! }
+//! ```
+//!
+//! If unlisted variants are not expected, the `#[non_exhaustive]` option adds the fallback, that
+//! panics with `unreachable!()`:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! #[non_exhaustive]
+//! pub enum Stream {
+//!     Tcp(tokio::net::TcpStream),
+//!     Unix(tokio::net::UnixStream),
+//! }
+//!
+//! fn is_connected(stream: &Stream) -> bool {
+//!     all_the_same!(#[non_exhaustive] match stream {
+//!         Stream::[Tcp, Unix](s) => s.peer_addr().is_ok()
+//!     })
+//! }
+//! ```
+//!
+//! # Match guards
+//!
+//! Like in a regular `match`, the arm can have a guard. It's replicated into every generated arm,
+//! so the variants that don't pass it are handled by the following arms:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Job {
+//!     Build(Vec<String>),
+//!     Test(Vec<String>),
+//! }
+//!
+//! fn describe(job: &Job) -> String {
+//!     all_the_same!(match job {
+//!         Job::[Build, Test](targets) if targets.is_empty() => "nothing to do".to_string(),
+//!         Job::[Build, Test](targets) => targets.join(", "),
+//!     })
+//! }
+//!
+//! assert_eq!(describe(&Job::Build(vec![])), "nothing to do");
+//! assert_eq!(describe(&Job::Test(vec!["lib".into(), "bin".into()])), "lib, bin");
+//! ```
+//!
+//! # `if let`
+//!
+//! When only some of the variants are of interest, the `if let` form can be used instead of the
+//! `match` with the fallback:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Message {
+//!     Text(String),
+//!     Binary(Vec<u8>),
+//!     Ping,
+//! }
+//!
+//! fn payload_len(msg: &Message) -> usize {
+//!     all_the_same!(if let Message::[Text, Binary](payload) = msg {
+//!         payload.len()
+//!     } else {
+//!         0
+//!     })
+//! }
+//!
+//! assert_eq!(payload_len(&Message::Text("hey".into())), 3);
+//! assert_eq!(payload_len(&Message::Ping), 0);
+//! ```
+//!
+//! # `let ... else`
+//!
+//! Similarly, the `let ... else` form binds the payload of the listed variants, while the rest of
+//! the variants have to diverge:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Endpoint {
+//!     Http(String),
+//!     Https(String),
+//!     Ftp(String),
+//! }
+//!
+//! fn web_host(endpoint: &Endpoint) -> Result<&str, &'static str> {
+//!     all_the_same!(let Endpoint::[Http, Https](host) = endpoint else {
+//!         return Err("not a web endpoint");
+//!     });
+//!
+//!     Ok(host)
+//! }
+//!
+//! assert_eq!(web_host(&Endpoint::Https("example.com".into())), Ok("example.com"));
+//! assert_eq!(web_host(&Endpoint::Ftp("example.com".into())), Err("not a web endpoint"));
+//! ```
+//!
+//! # Returning the payload
+//!
+//! The arm body can be omitted, then the arm just evaluates to the payload. Together with the
+//! unsized coercion of the match arms, that happens when the type is known from the context, this
+//! covers the accessor methods:
+//!
+//! ```
+//! use std::io::Write;
+//! use all_the_same::all_the_same;
+//!
+//! enum Writer {
+//!     Stdout(std::io::Stdout),
+//!     Buffer(Vec<u8>),
+//! }
+//!
+//! impl Writer {
+//!     pub fn as_write_mut(&mut self) -> &mut dyn Write {
+//!         all_the_same!(match self {
+//!             Writer::[Stdout, Buffer](w)
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! # Coercing payloads
+//!
+//! When payloads have different types, the arm body often doesn't compile for all of them. The
+//! `#[coerce(Type)]` option brings the payload to a common type before the arm body: references
+//! are coerced (e.g. to a trait object) and owned payloads are converted with `Into`:
+//!
+//! ```
+//! use std::io::Write;
+//! use std::net::{SocketAddr, SocketAddrV4, SocketAddrV6};
+//! use all_the_same::all_the_same;
+//!
+//! enum Addr {
+//!     V4(SocketAddrV4),
+//!     V6(SocketAddrV6),
+//! }
+//!
+//! impl Addr {
+//!     pub fn to_socket_addr(&self) -> SocketAddr {
+//!         all_the_same!(#[coerce(SocketAddr)] match *self {
+//!             Addr::[V4, V6](addr)
+//!         })
+//!     }
+//! }
+//!
+//! enum Writer {
+//!     Stdout(std::io::Stdout),
+//!     Buffer(Vec<u8>),
+//! }
+//!
+//! impl Writer {
+//!     pub fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+//!         all_the_same!(#[coerce(&mut dyn Write)] match self {
+//!             Writer::[Stdout, Buffer](w) => writeln!(w, "{}", line)
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! # Payload bounds
+//!
+//! When the arm body needs the payload to implement a trait, the error for the variant that doesn't
+//! is reported somewhere inside the body. The bounds can be listed after the payload binding
+//! instead, then they are asserted for every variant and the error points at the variant that
+//! doesn't satisfy them. The bounds are checked for the binding, so it's a reference to the payload
+//! when matching on a reference. They can't use the generic parameters of the enclosing function:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//! use std::fmt::Display;
+//!
+//! enum Value {
+//!     Int(i32),
+//!     Str(String),
+//! }
+//!
+//! fn describe(value: &Value) -> String {
+//!     all_the_same!(match value {
+//!         Value::[Int, Str](v): Display => format!("value: {}", v)
+//!     })
+//! }
+//! ```
+//!
+//! # Shared helpers
+//!
+//! Even with `#[coerce(Type)]`, the arm body is copied for every variant. For large bodies and
+//! enums with many variants, the `#[helper(Type)]` option emits the body only once, in a closure
+//! that takes the payload coerced to the type, and every arm just calls it:
+//!
+//! ```
+//! use std::io::{self, Write};
+//! use all_the_same::all_the_same;
+//!
+//! enum Sink {
+//!     Stdout(io::Stdout),
+//!     Stderr(io::Stderr),
+//!     Buffer(Vec<u8>),
+//!     Custom(Box<dyn Write>),
+//! }
+//!
+//! impl Sink {
+//!     pub fn write_report(&mut self, lines: &[&str]) -> io::Result<usize> {
+//!         all_the_same!(#[helper(&mut dyn Write)] match self {
+//!             Sink::[Stdout, Stderr, Buffer, Custom(&mut **w)](w) => lines
+//!                 .iter()
+//!                 .try_for_each(|line| writeln!(w, "- {}", line))
+//!                 .and_then(|()| w.flush())
+//!                 .map(|()| lines.len())
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! Since the body is in a closure, `return`, `?`, and `break` or `continue` of the loops around
+//! the match would apply to the closure, so they are rejected in the body. Each arm gets its own
+//! closure, so the bodies of different arms can't mutably borrow the same variables, and they can't
+//! depend on the variant, e.g. with `same!(...)`.
+//!
+//! ```compile_fail
+//! use all_the_same::all_the_same;
+//!
+//! enum Value {
+//!     I32(i32),
+//!     I64(i64),
+//! }
+//!
+//! fn to_u8(value: &Value) -> Option<u8> {
+//!     all_the_same!(#[helper(i64)] match value {
+//!         Value::[I32, I64](n) => {
+//!             if n < 0 {
+//!                 return None;
+//!             }
+//!
+//!             u8::try_from(n).ok()
+//!         }
+//!     })
+//! }
+//! ```
+//!
+//! # Converting errors
+//!
+//! When the payloads return different error types, the arm body has to convert them to the common
+//! one. With `=> try expr` the error of every arm is converted with `Into`, the same way as `?`
+//! does it, so the arms converge on the error type of the result:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//! use std::io;
+//! use std::net::TcpStream;
+//! use std::num::ParseIntError;
+//!
+//! enum Error {
+//!     Io(io::Error),
+//!     Parse(ParseIntError),
+//! }
+//!
+//! impl From<io::Error> for Error {
+//!     fn from(err: io::Error) -> Self {
+//!         Error::Io(err)
+//!     }
+//! }
+//!
+//! impl From<ParseIntError> for Error {
+//!     fn from(err: ParseIntError) -> Self {
+//!         Error::Parse(err)
+//!     }
+//! }
+//!
+//! enum Source {
+//!     Stream(TcpStream),
+//!     Text(String),
+//! }
+//!
+//! trait Read {
+//!     type Error;
+//!
+//!     fn read_number(&self) -> Result<u64, Self::Error>;
+//! }
+//!
+//! impl Read for TcpStream {
+//!     type Error = io::Error;
+//!
+//!     fn read_number(&self) -> Result<u64, io::Error> {
+//!         Ok(42)
+//!     }
+//! }
+//!
+//! impl Read for String {
+//!     type Error = ParseIntError;
+//!
+//!     fn read_number(&self) -> Result<u64, ParseIntError> {
+//!         self.parse()
+//!     }
+//! }
+//!
+//! fn read_number(source: &Source) -> Result<u64, Error> {
+//!     all_the_same!(match source {
+//!         Source::[Stream, Text](s) => try s.read_number()
+//!     })
+//! }
+//! ```
+//!
+//! # Result type
+//!
+//! The type of the match can be specified after the matched expression. Every arm is then checked
+//! against this type, which helps the inference when arms would otherwise produce different types,
+//! e.g. with `Into` conversions or unsized coercions:
+//!
+//! ```
+//! use std::fmt::Debug;
+//! use all_the_same::all_the_same;
+//!
+//! enum Number {
+//!     U8(u8),
+//!     U16(u16),
+//! }
+//!
+//! impl Number {
+//!     pub fn describe(&self) -> String {
+//!         let value = all_the_same!(match self -> u64 {
+//!             Number::[U8, U16](n) => (*n).into()
+//!         });
+//!
+//!         let debug = all_the_same!(match self -> &dyn Debug {
+//!             Number::[U8, U16](n)
+//!         });
+//!
+//!         format!("{} ({:?})", value, debug)
+//!     }
+//! }
+//! ```
+//!
+//! # Boxed futures
+//!
+//! The futures returned by the different payloads have different types, so with the `#[async]`
+//! option every arm body is wrapped in `Box::pin(async move { ... })` and the match returns
+//! `Pin<Box<dyn Future<Output = _>>>`. `Box` is referred to through the `alloc` crate, that has to
+//! be declared, as described in the `no_std` section:
+//!
+//! ```
+//! extern crate alloc;
+//!
+//! use std::future::Future;
+//! use std::io;
+//! use std::pin::Pin;
+//! use tokio::net::{TcpStream, UnixStream};
+//! use all_the_same::all_the_same;
+//!
+//! enum Connection {
+//!     Tcp(TcpStream),
+//!     Unix(UnixStream),
+//! }
+//!
+//! fn writable(conn: &Connection) -> Pin<Box<dyn Future<Output = io::Result<()>> + '_>> {
+//!     all_the_same!(#[async] match conn {
+//!         Connection::[Tcp, Unix](s) => s.writable().await
+//!     })
+//! }
+//! ```
+//!
+//! The bodies are moved into the futures, so `?` and `return` apply to the future rather than the
+//! enclosing function. The result type can be annotated to make the future `Send`, e.g.
+//! `match conn -> Pin<Box<dyn Future<Output = T> + Send + '_>> { ... }`.
+//!
+//! # Or-patterns
+//!
+//! When all the listed variants have the same payload type, the `#[or_patterns]` option expands the
+//! arm into a single one with an or-pattern, like `Enum::A(v) | Enum::B(v) => ...`, instead of an
+//! arm per variant, which reduces the amount of the generated code for large enums:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Level {
+//!     Trace(u8),
+//!     Debug(u8),
+//!     Info(u8),
+//!     Warn(u8),
+//!     Error(u8),
+//! }
+//!
+//! fn verbosity(level: &Level) -> u8 {
+//!     all_the_same!(#[or_patterns] match level {
+//!         Level::[Trace, Debug, Info, Warn, Error](v) => *v
+//!     })
+//! }
+//!
+//! assert_eq!(verbosity(&Level::Warn(3)), 3);
+//! ```
+//!
+//! The variants that need their own arm, e.g. with `#[cfg]` attributes, adapters or overrides, as
+//! well as the arms that bind the variant name or use `same!(...)`, are still expanded separately.
+//!
+//! # Table dispatch
+//!
+//! With the `#[table(args, ...)]` option the variants are dispatched through an array of function
+//! pointers, indexed by the position of the variant in the arms, instead of the match. That makes
+//! it easy to benchmark both ways of dispatching. Function pointers can't capture the environment,
+//! so the variables the arm bodies use have to be listed in the option:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Shape {
+//!     Circle(f64),
+//!     Square(f64),
+//!     Point,
+//! }
+//!
+//! fn scaled_size(shape: &Shape, scale: f64) -> f64 {
+//!     all_the_same!(#[table(scale)] match shape {
+//!         Shape::[Circle, Square](size) => size * scale,
+//!         Shape::[Point] => 0.0,
+//!     })
+//! }
+//!
+//! assert_eq!(scaled_size(&Shape::Square(2.0), 1.5), 3.0);
+//! assert_eq!(scaled_size(&Shape::Point, 1.5), 0.0);
+//! ```
+//!
+//! Match guards, regular arms and `#[cfg]` attributes on the variants are not supported in this
+//! mode. The arm bodies are the bodies of the function pointers, so `return`, `?`, `break` and
+//! `continue`, that would leave the function or the loop around the match, are rejected.
+//!
+//! ```compile_fail
+//! use all_the_same::all_the_same;
+//!
+//! enum Value {
+//!     I32(i32),
+//!     I64(i64),
+//! }
+//!
+//! fn checked_add(value: &Value, delta: Option<i64>) -> Option<i64> {
+//!     all_the_same!(#[table(delta)] match value {
+//!         Value::[I32, I64](n) => (*n as i64).checked_add(delta?)
+//!     })
+//! }
+//! ```
+//!
+//! # Const contexts
+//!
+//! The plain expansion is just a `match`, so it works in `const fn`s. The `#[const_context]` option
+//! makes sure it stays this way: the options that call non-`const` functions, like `#[trace]`,
+//! `#[helper(..)]` or `=> try` arms, are rejected, and the `#[debug_assert(..)]` messages are
+//! concatenated at compile time, so they can only be string literals:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Limit {
+//!     Bytes(u64),
+//!     Items(u64),
+//! }
+//!
+//! impl Limit {
+//!     const fn value(&self) -> u64 {
+//!         all_the_same!(#[const_context] #[debug_assert(*n > 0, "empty limit")] match self {
+//!             Limit::[Bytes, Items](n) => *n
+//!         })
+//!     }
+//! }
+//!
+//! const BYTES: u64 = Limit::Bytes(1024).value();
+//!
+//! assert_eq!(BYTES, 1024);
+//! ```
+//!
+//! The accessors can be made `const` as well, see [`Accessors`].
+//!
+//! # Lint attributes
+//!
+//! Lint attributes, like `#[allow(..)]` or `#[expect(..)]`, in front of the `match` are applied to
+//! the generated match, so they cover the lints in every copy of the arm body. The lints that skip
+//! the code generated by macros, like `clippy::match_same_arms`, never fire for the match itself:
+//!
+//! ```
+//! #![deny(unused_mut, unfulfilled_lint_expectations)]
+//!
+//! use all_the_same::all_the_same;
+//!
+//! enum Cache {
+//!     Hot(Vec<u8>),
+//!     Cold(Vec<u8>),
+//! }
+//!
+//! fn take(cache: Cache) -> Vec<u8> {
+//!     all_the_same!(#[expect(unused_mut)] match cache {
+//!         Cache::[Hot, Cold](mut data) => data
+//!     })
+//! }
+//!
+//! assert_eq!(take(Cache::Hot(vec![1])), vec![1]);
+//! ```
+//!
+//! # Per-variant shims
+//!
+//! With the `#[shims(name)]` option each arm body is called through a small non-inlined function
+//! named after the variant and the `name`, e.g. `__dispatch_tcp_poll_write` for the `Tcp` variant
+//! with `#[shims(poll_write)]`, so backtraces and flamegraphs show which variant was active. The
+//! macro can't see the function it's invoked in, so the `name` is usually the name of that
+//! function, and different names tell apart several matches in the same function. The body is
+//! passed to the function as a closure, so `return`, `?`, and `break` or `continue` of the loops
+//! around the match would apply to the closure instead. They are rejected in the body, and `.await`
+//! isn't supported in the closure either:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Number {
+//!     I32(i32),
+//!     I64(i64),
+//! }
+//!
+//! impl Number {
+//!     pub fn is_positive(&self) -> bool {
+//!         all_the_same!(#[shims(is_positive)] match self {
+//!             Number::[I32, I64](n) => *n > 0
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use all_the_same::all_the_same;
+//!
+//! enum Number {
+//!     I32(i32),
+//!     I64(i64),
+//! }
+//!
+//! impl Number {
+//!     pub fn to_u8(&self) -> Result<u8, ()> {
+//!         all_the_same!(#[shims(to_u8)] match self {
+//!             Number::[I32, I64](n) => {
+//!                 if *n < 0 {
+//!                     return Err(());
+//!                 }
+//!
+//!                 u8::try_from(*n).map_err(|_| ())
+//!             }
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! # Variant spans
+//!
+//! The arm body is copied for every variant, so when it doesn't compile for one of them, the error
+//! points at the body without telling which variant it was. With the `#[variant_spans]` option the
+//! errors point at the variant in the list instead:
+//!
+//! ```compile_fail
+//! use all_the_same::all_the_same;
+//!
+//! enum Number {
+//!     Small(u8),
+//!     Medium(u8),
+//!     Large(u16),
+//! }
+//!
+//! fn next(number: &Number) -> u8 {
+//!     // error[E0308]: `match` arms have incompatible types, pointing at `Large`
+//!     all_the_same!(#[variant_spans] match number {
+//!         Number::[Small, Medium, Large](n) => *n + 1
+//!     })
+//! }
+//! ```
+//!
+//! # Debugging the expansion
+//!
+//! The `#[debug]` option prints the code that the macro expands to at compile time, which is
+//! handier than expanding the whole crate to see what's going on with a single invocation. With the
+//! `nightly-diagnostics` feature the expansion is reported as a compiler note:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Number {
+//!     I32(i32),
+//!     I64(i64),
+//! }
+//!
+//! fn is_zero(number: &Number) -> bool {
+//!     all_the_same!(#[debug] match number {
+//!         Number::[I32, I64](n) => *n == 0
+//!     })
+//! }
+//! ```
+//!
+//! # Tracing
+//!
+//! The `#[trace]` option makes every arm emit a [`tracing`](https://docs.rs/tracing) event at the
+//! `TRACE` level, with the `enum_name` and `variant` fields, so it's possible to see which variant
+//! served a call. The crate using the option needs to depend on `tracing` itself:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Number {
+//!     I32(i32),
+//!     I64(i64),
+//! }
+//!
+//! impl Number {
+//!     pub fn is_positive(&self) -> bool {
+//!         all_the_same!(#[trace] match self {
+//!             Number::[I32, I64](n) => *n > 0
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! The events are emitted with `::tracing` by default. The crates that re-export `tracing` can set
+//! the path with `#[trace(tracing = path)]`:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! mod telemetry {
+//!     pub use tracing;
+//! }
+//!
+//! enum Number {
+//!     I32(i32),
+//!     I64(i64),
+//! }
+//!
+//! impl Number {
+//!     pub fn is_zero(&self) -> bool {
+//!         all_the_same!(#[trace(tracing = telemetry::tracing)] match self {
+//!             Number::[I32, I64](n) => *n == 0
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! # Invariants
+//!
+//! The `#[debug_assert(cond, ...)]` option injects `debug_assert!` with the given arguments at the
+//! top of every arm, including the overridden ones. The payload binding can be used in the
+//! assertion and the failure message mentions the variant:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Number {
+//!     I32(i32),
+//!     I64(i64),
+//! }
+//!
+//! impl Number {
+//!     pub fn to_u64(&self) -> u64 {
+//!         all_the_same!(#[debug_assert(*n >= 0, "negative number: {}", n)] match self {
+//!             Number::[I32, I64](n) => *n as u64
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! # Re-exports
+//!
+//! The expansion refers to everything it needs via absolute `::core` paths, so the macro keeps
+//! working when it's re-exported from another crate, wrapped into `macro_rules!` or used under
+//! `#![no_implicit_prelude]`:
+//!
+//! ```
+//! #[no_implicit_prelude]
+//! mod numbers {
+//!     use ::all_the_same::all_the_same;
+//!
+//!     pub enum Number {
+//!         I32(i32),
+//!         I64(i64),
+//!     }
+//!
+//!     impl Number {
+//!         pub fn is_positive(&self) -> bool {
+//!             all_the_same!(#[shims(is_positive)] match self {
+//!                 Number::[I32, I64](n) => *n > 0
+//!             })
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! The macros that generate the invocations of the other macros of the crate, like
+//! [`#[variants]`](variants), refer to the crate as `::all_the_same`. When the crate is only
+//! available through a re-export, they take the path to it with the `crate = path` option, e.g.
+//! `#[variants(crate = ::facade::all_the_same)]`.
+//!
+//! # Invoking from `macro_rules!`
+//!
+//! The enum path, the variant names and the expressions can come from the metavariables of a
+//! declarative macro, including `$expr` ones, that are spliced as a whole, like the compiler does
+//! it for the regular `match`:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Number {
+//!     I32(i32),
+//!     I64(i64),
+//! }
+//!
+//! macro_rules! apply {
+//!     ($value:expr, $enum:path, [$($variant:ident),*], $f:expr) => {
+//!         all_the_same!(match $value {
+//!             $enum::[$($variant),*](n) => $f(*n as i64)
+//!         })
+//!     };
+//! }
+//!
+//! let number = Number::I32(20);
+//!
+//! assert_eq!(apply!(&number, Number, [I32, I64], |n| n + 1), 21);
+//! assert_eq!(apply!(&number, Number, [I32, I64], |n| n * 2) + 1, 41);
+//! ```
+//!
+//! # `no_std`
+//!
+//! The generated code refers to `::core` only, so it works in `#![no_std]` crates. The exceptions
+//! are `Box`, that `#[async]` and `impl_for(Box<Self>)` of the delegated traits use, and the I/O
+//! derives, like `DelegateRead`, that implement the `std::io` traits. `Box` is referred to as
+//! `::alloc::boxed::Box`, that is the same type in the `std` and `no_std` crates alike, so the
+//! crates that use these options declare the `alloc` crate, that isn't in the extern prelude by
+//! default:
+//!
+//! ```
+//! extern crate alloc;
+//! ```
+//!
+//! # `unsafe` code
+//!
+//! The expansion is a plain `match` and never emits `unsafe` on its own, so the macro can be used
+//! in crates that `#![forbid(unsafe_code)]`:
+//!
+//! ```
+//! #![forbid(unsafe_code)]
+//!
+//! use all_the_same::all_the_same;
+//!
+//! enum Number {
+//!     I32(i32),
+//!     I64(i64),
+//! }
+//!
+//! impl Number {
+//!     pub fn is_zero(&self) -> bool {
+//!         all_the_same!(match self {
+//!             Self::[I32, I64](n) => *n == 0
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! If the arm body itself needs `unsafe`, mark it with `unsafe` right after `=>`. The body is then
+//! wrapped into an `unsafe` block in every generated arm:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Bytes {
+//!     Vec(Vec<u8>),
+//!     Boxed(Box<[u8]>),
+//! }
+//!
+//! impl Bytes {
+//!     pub fn first_or_zero(&self) -> u8 {
+//!         all_the_same!(match self {
+//!             Bytes::[Vec, Boxed](b) => unsafe {
+//!                 if b.is_empty() { 0 } else { *b.get_unchecked(0) }
+//!             }
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! `unsafe` blocks nested inside the arm body are accepted too, but they are invisibly duplicated
+//! for every variant, so the marker is preferred: this way all copy-pasted unsafe code can be found
+//! by grepping for `=> unsafe`. With the `nightly-diagnostics` feature, the nested blocks get a
+//! warning suggesting the marker:
+//!
+//! ```
+//! use all_the_same::all_the_same;
+//!
+//! enum Bytes {
+//!     Vec(Vec<u8>),
+//!     Boxed(Box<[u8]>),
+//! }
+//!
+//! impl Bytes {
+//!     pub fn first_plus_one(&self) -> u8 {
+//!         all_the_same!(match self {
+//!             Bytes::[Vec, Boxed](b) => 1 + unsafe { *b.get_unchecked(0) }
+//!         })
+//!     }
+//! }
+//! ```
+//!
+//! The derives don't emit `unsafe` code either, except for the pin projection of
+//! [`PinProject`], and of [`DelegateFuture`] for the payloads that are not `Unpin`. It's opted into
+//! with the `unsafe_project` option of their attributes, and the generated `unsafe` blocks don't
+//! allow the `unsafe_code` lint, so the lint of the crate points at the option.
+//!
+//! # Attribute form
+//!
+//! Bodies of the function-like macros are not formatted by rustfmt and IDEs have a hard time
+//! completing the code in them. The [`#[dispatch(Enum::[A, B])]`](dispatch) attribute on an
+//! impl block or a function rewrites the `delegate!(binding => expr)` shorthands in it into the
+//! matches on `self` with the variant list of the attribute, and the shorthand looks like a
+//! regular expression to the tools.
+//!
+//! # Migrating existing code
+//!
+//! To find the matches that can be rewritten with the macro, temporarily put the
+//! [`#[migrate]`](migrate) attribute on an impl block (or any other item). Every match with
+//! several identical `Enum::Variant(binding) => body` arms is then reported as a compile error,
+//! that points at the arms and suggests the replacement.
+//!
+//! # Derives
+//!
+//! The crate also comes with derives for the boilerplate that usually surrounds such enums:
+//!
+//! * [`Subset`] - conversions between an enum and its subset.
+//! * [`VariantFrom`] - `From` the payload types.
+//! * `EitherFrom` - conversions between two-variant enums and `either::Either`, with the `either`
+//!   feature.
+//! * [`Accessors`] - `is_*`, `as_*` and `into_*` methods for the variants.
+//! * [`AsDyn`] - accessors of the payload as trait objects.
+//! * [`RefView`] - a borrowed view of an enum.
+//! * [`Forward`] - inherent methods forwarding to the payload.
+//! * [`Delegate`] - trait implementations delegating to the payload.
+//! * [`DelegateDebug`] - `Debug` with variant names and redaction.
+//! * [`DelegateDeref`] - `Deref`, `AsRef` and their mutable counterparts to the common target.
+//! * [`DelegateDisplay`], [`DelegateError`] - transparent `Display` and `Error` for the enums of
+//!   errors.
+//! * [`DelegateClone`], [`DelegatePartialEq`], [`DelegatePartialOrd`], [`DelegateHash`] -
+//!   cfg-aware replacements for the std derives.
+//! * `DelegateOps` - operators and indexing applied to the payloads of the same variant, with the
+//!   `ops` feature.
+//! * [`VariantIndex`] - the number of variants and the variant index for per-variant arrays.
+//! * [`DelegateArbitrary`] - `arbitrary::Arbitrary` for fuzzing.
+//! * [`DelegateRead`], [`DelegateWrite`], [`DelegateSeek`], [`DelegateBufRead`] - std I/O traits.
+//! * `DelegateAsyncRead`, `DelegateAsyncWrite`, `DelegateAsyncSeek`, `DelegateAsyncBufRead` -
+//!   `tokio` I/O traits, with the `tokio` feature.
+//! * [`DelegateIterator`] - `Iterator` and the related traits for the enums of iterators.
+//! * [`DelegateFuture`] - `Future` with the pin projection to the payload.
+//! * [`PinProject`] - the pin projection for the other derives and manual impls.
+//! * `DelegateStream`, `DelegateSink` - `futures` streams and sinks, with the `futures` feature.
+//!
+//! The derives support generic enums. The type parameters get the bound of the derived trait, like
+//! with the std derives, and the lifetimes and the `where` clauses are carried over to the
+//! generated impls, so the payloads can borrow the same type the other variants own:
+//!
+//! ```
+//! use all_the_same::{DelegateIterator, DelegateRead};
+//! use std::io::{Cursor, Read};
+//!
+//! #[derive(DelegateRead, DelegateIterator)]
+//! enum Source<'a, T>
+//! where
+//!     T: Read + Iterator<Item = u8>,
+//! {
+//!     Borrowed(&'a mut T),
+//!     Owned(T),
+//! }
+//!
+//! struct Bytes(Cursor<Vec<u8>>);
+//!
+//! impl Read for Bytes {
+//!     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+//!         self.0.read(buf)
+//!     }
+//! }
+//!
+//! impl Iterator for Bytes {
+//!     type Item = u8;
+//!
+//!     fn next(&mut self) -> Option<u8> {
+//!         let mut byte = [0];
+//!
+//!         self.0.read_exact(&mut byte).ok().map(|_| byte[0])
+//!     }
+//! }
+//!
+//! let mut bytes = Bytes(Cursor::new(b"abc".to_vec()));
+//!
+//! assert_eq!(Source::Borrowed(&mut bytes).next(), Some(b'a'));
+//!
+//! let mut rest = String::new();
+//!
+//! Source::Owned(bytes).read_to_string(&mut rest).unwrap();
+//!
+//! assert_eq!(rest, "bc");
+//! ```
+//!
+//! Using deprecated variants in the generated code doesn't trigger deprecation warnings, and the
+//! borrowed variants generated by [`RefView`] and the methods generated by [`Accessors`] inherit
+//! the deprecation. The `From` impls of [`VariantFrom`] can't be deprecated, as `#[deprecated]`
+//! can't be used on trait impls:
+//!
+//! ```
+//! #![deny(deprecated)]
+//!
+//! use all_the_same::{Accessors, DelegateDebug, RefView};
+//!
+//! #[derive(Accessors, DelegateDebug, RefView)]
+//! enum Backend {
+//!     Memory(Vec<u8>),
+//!     #[deprecated(note = "use `Memory` instead")]
+//!     Legacy(String),
+//! }
+//!
+//! let backend = Backend::Memory(Vec::new());
+//!
+//! assert!(backend.is_memory());
+//! ```
+//!
+//! So the uses of the accessors of the deprecated variants are reported:
+//!
+//! ```compile_fail
+//! #![deny(deprecated)]
+//!
+//! use all_the_same::Accessors;
+//!
+//! #[derive(Accessors)]
+//! enum Backend {
+//!     Memory(Vec<u8>),
+//!     #[deprecated(note = "use `Memory` instead")]
+//!     Legacy(String),
+//! }
+//!
+//! let backend = Backend::Memory(Vec::new());
+//!
+//! // error: use of deprecated method `Backend::is_legacy`: use `Memory` instead
+//! assert!(!backend.is_legacy());
+//! ```
+//!
+//! With the `#[derive_coverage(off)]` attribute on the enum, the impls generated by the derives are
+//! marked with `#[coverage(off)]`, so the mechanically generated code doesn't affect coverage
+//! metrics. The attribute needs the `coverage_attribute` nightly feature, so it's usually gated on
+//! the cfg of the coverage builds, e.g. `coverage_nightly` that `cargo llvm-cov` sets:
+//!
+//! ```
+//! #![cfg_attr(coverage_nightly, feature(coverage_attribute))]
+//!
+//! use all_the_same::{DelegateDisplay, VariantFrom};
+//!
+//! #[derive(DelegateDisplay, VariantFrom)]
+//! #[cfg_attr(coverage_nightly, derive_coverage(off))]
+//! enum Number {
+//!     I32(i32),
+//!     I64(i64),
+//! }
+//! ```
+//!
+//! With the `nightly-diagnostics` feature enabled, the errors of [`all_the_same!`] get a note
+//! pointing at the expression they come from, and the macro emits warnings with suggestions, e.g.
+//! for the variants listed without the `#[cfg]` they have in the enum, or for the `unsafe` blocks
+//! nested in the arm bodies.
+
+#![cfg_attr(feature = "nightly-diagnostics", feature(proc_macro_diagnostic))]
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+mod accessors;
+mod arbitrary;
+mod as_dyn;
+mod clone;
+mod debug;
+mod delegate;
+mod deref;
+mod diagnostics;
+mod dispatch;
+#[cfg(feature = "either")]
+mod either;
+mod error;
+mod for_each_variant;
+mod forward;
+mod future;
+mod hash;
+mod io;
+mod is_any_of;
+mod iter;
+mod match_expr;
+mod migrate;
+#[cfg(feature = "ops")]
+mod ops;
+mod partial_eq;
+mod partial_ord;
+mod pin;
+mod ref_view;
+mod same_variant;
+#[cfg(feature = "futures")]
+mod stream;
+mod subset;
+mod sum_type;
+mod utils;
+mod variant_from;
+mod variant_index;
+mod variants;
 
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::parse::{Parse, ParseStream};
-use syn::punctuated::Punctuated;
-use syn::token::Comma;
-use syn::{braced, bracketed, parenthesized, parse_macro_input, Attribute, Expr, Ident, Token};
-
-struct Variant {
-    attrs: Vec<Attribute>,
-    name: Ident,
-}
-
-impl Parse for Variant {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        Ok(Variant {
-            attrs: input.call(Attribute::parse_outer)?,
-            name: input.parse()?,
-        })
+use syn::{parse_macro_input, DeriveInput, Item, ItemEnum, ItemTrait};
+use utils::CratePath;
+
+/// The macro itself.
+#[proc_macro]
+pub fn all_the_same(item: TokenStream) -> TokenStream {
+    let item = proc_macro2::TokenStream::from(item);
+
+    match syn::parse2::<match_expr::Args>(item.clone()) {
+        Ok(args) => match_expr::expand(&args).into(),
+        Err(err) => diagnostics::report(err, &item).into(),
     }
 }
 
-struct Args {
-    expr: Expr,
-    enum_name: Option<Ident>,
-    variants: Punctuated<Variant, Comma>,
-    inner_name: Ident,
-    arm_expr: Expr,
+/// Reports `match` expressions in the item that can be rewritten with [`all_the_same!`].
+///
+/// The attribute is meant to be applied temporarily, while migrating existing code: every match
+/// with several token-identical `Enum::Variant(binding) => body` arms produces a compile error
+/// pointing at the arms, with the suggested replacement:
+///
+/// ```compile_fail
+/// use all_the_same::migrate;
+///
+/// enum Number {
+///     I32(i32),
+///     I64(i64),
+///     U8(u8),
+/// }
+///
+/// #[migrate]
+/// impl Number {
+///     pub fn is_zero(&self) -> bool {
+///         // error: arms are the same for 2 variants, replace them with
+///         // `Number::[I32, I64](n) => ...` in `all_the_same!`
+///         match self {
+///             Number::I32(n) => *n == 0,
+///             Number::I64(n) => *n == 0,
+///             Number::U8(n) => *n < 1,
+///         }
+///     }
+/// }
+/// ```
+///
+/// Items without such matches are left intact:
+///
+/// ```
+/// use all_the_same::{all_the_same, migrate};
+///
+/// enum Number {
+///     I32(i32),
+///     I64(i64),
+/// }
+///
+/// #[migrate]
+/// impl Number {
+///     pub fn is_zero(&self) -> bool {
+///         all_the_same!(match self {
+///             Number::[I32, I64](n) => *n == 0
+///         })
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn migrate(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return syn::Error::new_spanned(
+            proc_macro2::TokenStream::from(attr),
+            "`#[migrate]` doesn't take any options",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let item = parse_macro_input!(item as Item);
+
+    migrate::expand(&item).into()
 }
 
-impl Parse for Args {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        let match_body_content;
+/// Rewrites the `delegate!(binding => expr)` shorthands in the item into [`all_the_same!`]
+/// matches on `self` with the variant list of the attribute.
+///
+/// Unlike the bodies of the function-like macros, the shorthand is formatted by rustfmt and
+/// IDEs can complete the code in it, since it looks like a regular expression:
+///
+/// ```
+/// use all_the_same::dispatch;
+/// use std::io::{self, Write};
+///
+/// enum Sink {
+///     Stdout(io::Stdout),
+///     Buffer(Vec<u8>),
+/// }
+///
+/// #[dispatch(Sink::[Stdout, Buffer])]
+/// impl Write for Sink {
+///     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+///         delegate!(s => s.write(buf))
+///     }
+///
+///     fn flush(&mut self) -> io::Result<()> {
+///         delegate!(s => s.flush())
+///     }
+/// }
+///
+/// let mut sink = Sink::Buffer(Vec::new());
+///
+/// write!(sink, "{}", 42).unwrap();
+///
+/// assert!(matches!(sink, Sink::Buffer(buf) if buf == b"42"));
+/// ```
+///
+/// The other value can be matched with `delegate!(scrutinee, binding => expr)`. The variant list
+/// is copied to the generated arms, so it can be anything that the arms accept, like
+/// `Enum::[*]`. The attribute can be applied to any item, e.g. a function. The generated
+/// matches invoke [`all_the_same!`] through `::all_the_same`, and the path to the re-exported
+/// crate is set with `crate = path` after the list, e.g.
+/// `#[dispatch(Sink::[Stdout, Buffer], crate = ::facade::all_the_same)]`.
+#[proc_macro_attribute]
+pub fn dispatch(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let list = parse_macro_input!(attr with dispatch::VariantList::parse_attr);
 
-        Ok(Args {
-            expr: {
-                input.parse::<Token!(match)>()?;
+    dispatch::expand(&list, item.into())
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
 
-                Expr::parse_without_eager_brace(input)?
-            },
-            enum_name: {
-                braced!(match_body_content in input);
+/// Generates the methods that forward to the same-named methods of the payload, for the listed
+/// variants.
+///
+/// The variant list is followed by the method signatures, and the macro is invoked in an impl
+/// block. It's the function-like counterpart of [`Forward`], that doesn't need to be on the enum
+/// definition and takes any variant list that [`all_the_same!`] arms do:
+///
+/// ```
+/// use all_the_same::delegate_fns;
+/// use std::io;
+/// use std::net::{SocketAddr, TcpListener, TcpStream};
+///
+/// enum Socket {
+///     Stream(TcpStream),
+///     Listener(TcpListener),
+/// }
+///
+/// impl Socket {
+///     delegate_fns! {
+///         Socket::[Stream, Listener];
+///
+///         pub fn local_addr(&self) -> io::Result<SocketAddr>;
+///         pub fn set_ttl(&self, ttl: u32) -> io::Result<()>;
+///         pub fn ttl(&self) -> io::Result<u32>;
+///     }
+/// }
+///
+/// let socket = Socket::Listener(TcpListener::bind("127.0.0.1:0").unwrap());
+///
+/// socket.set_ttl(42).unwrap();
+///
+/// assert_eq!(socket.ttl().unwrap(), 42);
+/// assert!(socket.local_addr().unwrap().ip().is_loopback());
+/// ```
+///
+/// The methods invoke [`all_the_same!`] through `::all_the_same`, and the path to the re-exported
+/// crate is set with `crate = path;` in front of the list, e.g.
+/// `delegate_fns! { crate = ::facade::all_the_same; Socket::[Stream, Listener]; .. }`.
+#[proc_macro]
+pub fn delegate_fns(item: TokenStream) -> TokenStream {
+    let delegate = parse_macro_input!(item as forward::DelegateFns);
 
-                let enum_name = match_body_content.parse::<Option<Ident>>()?;
+    forward::expand_fns(&delegate)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
 
-                if enum_name.is_none() {
-                    match_body_content.parse::<Token!(Self)>()?;
-                }
+/// Registers the variants of the enum, so that [`all_the_same!`] arms can list them with `[*]`:
+///
+/// ```
+/// use all_the_same::{all_the_same, variants};
+///
+/// mod net {
+///     use all_the_same::variants;
+///     use tokio::net::{TcpStream, UnixStream};
+///
+///     #[variants]
+///     pub enum Stream {
+///         Tcp(TcpStream),
+///         Unix(UnixStream),
+///         #[cfg(feature = "unsupported")]
+///         Pipe(std::fs::File),
+///     }
+/// }
+///
+/// use net::Stream;
+///
+/// fn is_readable(stream: &Stream) -> bool {
+///     all_the_same!(match stream {
+///         Stream::[*](s) => s.try_read(&mut []).is_ok()
+///     })
+/// }
+/// ```
+///
+/// The variants are looked up with a hidden macro, that has the same name as the enum and is
+/// imported along with it. This is why `[*]` only works for enums of the current crate and
+/// requires the enum name rather than `Self`. The macro and its module, `__all_the_same_Enum`,
+/// are named after the enum and only list its variants, so the expansion only changes along with
+/// the enum, and nothing outside of the crate depends on it.
+///
+/// The hidden macro invokes [`all_the_same!`] as `::all_the_same::all_the_same!`. If the crate is
+/// only available through a re-export, the path to it is set with `crate = path`:
+///
+/// ```
+/// mod facade {
+///     pub use all_the_same::{all_the_same, variants};
+/// }
+///
+/// use facade::{all_the_same, variants};
+///
+/// #[variants(crate = crate::facade)]
+/// enum Number {
+///     I32(i32),
+///     I64(i64),
+/// }
+///
+/// fn is_zero(number: &Number) -> bool {
+///     all_the_same!(match number {
+///         Number::[*](n) => *n == 0
+///     })
+/// }
+///
+/// fn main() {
+///     assert!(is_zero(&Number::I64(0)));
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn variants(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let krate = if attr.is_empty() {
+        CratePath::default()
+    } else {
+        parse_macro_input!(attr as CratePath)
+    };
 
-                enum_name
-            },
-            variants: {
-                match_body_content.parse::<Token!(::)>()?;
+    let item = parse_macro_input!(item as ItemEnum);
 
-                let variants_list_content;
+    variants::expand(&item, &krate).into()
+}
 
-                bracketed!(variants_list_content in match_body_content);
+/// Defines an enum from the list of its variants, along with the [`VariantFrom`] conversions and
+/// the [`Accessors`], and registers the variants like [`#[variants]`](macro@variants) does:
+///
+/// ```
+/// use all_the_same::{all_the_same, sum_type};
+/// use std::io::{self, Cursor, Sink, Write};
+///
+/// sum_type! {
+///     /// Destination of the output.
+///     pub Output = [Buffer(Cursor<Vec<u8>>), Sink(Sink), Stderr(io::Stderr)];
+/// }
+///
+/// impl Output {
+///     fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+///         all_the_same!(match self {
+///             Output::[*](out) => out.write(bytes)
+///         })
+///     }
+/// }
+///
+/// let mut output = Output::from(Cursor::new(Vec::new()));
+///
+/// output.write(b"foo").unwrap();
+///
+/// assert!(!output.is_sink());
+/// assert_eq!(output.as_buffer().unwrap().get_ref(), b"foo");
+/// ```
+///
+/// Attributes and doc comments before the name are applied to the enum, so it can derive more
+/// traits. The variants can have attributes as well, e.g. `#[cfg]` or `#[variant_from(skip)]`.
+/// The derives and the hidden macro refer to the crate as `::all_the_same`, and the path to the
+/// re-exported crate is set with `crate = path;` in front of the definition, e.g.
+/// `sum_type! { crate = ::facade::all_the_same; pub Output = [..]; }`.
+#[proc_macro]
+pub fn sum_type(item: TokenStream) -> TokenStream {
+    let sum_type = parse_macro_input!(item as sum_type::SumType);
 
-                variants_list_content.parse_terminated(Variant::parse)?
-            },
-            inner_name: {
-                let variant_payload_content;
+    sum_type::expand(&sum_type)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
 
-                parenthesized!(variant_payload_content in match_body_content);
+/// Defines an enum that merges the variants of other enums, like [`sum_type!`] does for a list of
+/// variants, along with `From` the merged enums and `TryFrom` back to them. This is handy to add
+/// variants to an enum of another crate:
+///
+/// ```
+/// use all_the_same::{all_the_same, compose};
+/// use std::io::{self, Cursor, Sink, Write};
+///
+/// mod other_crate {
+///     pub enum Output {
+///         Buffer(std::io::Cursor<Vec<u8>>),
+///         Sink(std::io::Sink),
+///     }
+/// }
+///
+/// compose! {
+///     /// Destination of the output, that can also be the standard error.
+///     pub Output = other_crate::Output::[Buffer(Cursor<Vec<u8>>), Sink(Sink)]
+///         + [Stderr(io::Stderr)];
+/// }
+///
+/// impl Output {
+///     fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+///         all_the_same!(match self {
+///             Output::[*](out) => out.write(bytes)
+///         })
+///     }
+/// }
+///
+/// let mut output = Output::from(other_crate::Output::Sink(io::sink()));
+///
+/// output.write(b"foo").unwrap();
+///
+/// assert!(output.is_sink());
+/// assert!(other_crate::Output::try_from(Output::Stderr(io::stderr())).is_err());
+/// ```
+///
+/// The variants of the merged enums are restated with their payloads, as a procedural macro can't
+/// look into another enum. The list is checked by the exhaustive match of the `From` conversion,
+/// so a missing or misspelled variant is a compile error. Like with [`sum_type!`], the path to the
+/// re-exported crate is set with `crate = path;` in front of the definition.
+#[proc_macro]
+pub fn compose(item: TokenStream) -> TokenStream {
+    let compose = parse_macro_input!(item as sum_type::SumType);
 
-                variant_payload_content.parse()?
-            },
-            arm_expr: {
-                match_body_content.parse::<Token!(=>)>()?;
+    sum_type::expand_compose(&compose)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
 
-                match_body_content.parse()?
-            },
-        })
-    }
+/// Defines a named set of variants, that [`all_the_same!`] arms can refer to with `[..SET]`:
+///
+/// ```
+/// use all_the_same::{all_the_same, variant_set};
+///
+/// pub enum Value {
+///     I32(i32),
+///     I64(i64),
+///     F64(f64),
+///     Text(String),
+/// }
+///
+/// variant_set!(INTS = [I32, I64]);
+///
+/// impl Value {
+///     pub fn as_i64(&self) -> Option<i64> {
+///         all_the_same!(match self {
+///             Self::[..INTS](n) => Some(*n as i64),
+///             else _ => None
+///         })
+///     }
+///
+///     pub fn is_negative(&self) -> bool {
+///         all_the_same!(match self {
+///             Self::[..INTS](n) => *n < 0,
+///             Self::F64(n) => *n < 0.0,
+///             Self::Text(_) => false
+///         })
+///     }
+/// }
+/// ```
+///
+/// The set is a macro with the given name, so it follows the scoping of `macro_rules!` macros
+/// and can be imported from other modules of the crate, e.g. `[..crate::sets::INTS]`. Variants in
+/// the set can have `#[cfg]` attributes, that are copied to the arms. The set is not tied to a
+/// particular enum, so it can be used for the enums with the same variant names, e.g. the ones
+/// derived by [`RefView`]. Like with [`#[variants]`](variants), the path to the re-exported
+/// crate is set with `crate = path;` in front of the set, e.g.
+/// `variant_set!(crate = ::facade::all_the_same; INTS = [I32, I64])`.
+#[proc_macro]
+pub fn variant_set(item: TokenStream) -> TokenStream {
+    let set = parse_macro_input!(item as variants::VariantSet);
+
+    variants::expand_set(&set).into()
 }
 
-/// The macro itself.
+/// Checks if two values are the same variant of the listed ones:
+///
+/// ```
+/// use all_the_same::same_variant;
+///
+/// enum Shape {
+///     Circle(f64),
+///     Rect { w: f64, h: f64 },
+///     #[cfg(feature = "unsupported")]
+///     Polygon(Vec<(f64, f64)>),
+///     Empty,
+/// }
+///
+/// let circle = Shape::Circle(1.0);
+/// let rect = Shape::Rect { w: 1.0, h: 2.0 };
+///
+/// assert!(same_variant!(circle, Shape::Circle(2.0), Shape::[
+///     Circle,
+///     Rect,
+///     #[cfg(feature = "unsupported")]
+///     Polygon,
+/// ]));
+/// assert!(!same_variant!(circle, rect, Shape::[Circle, Rect]));
+///
+/// // Not listed.
+/// assert!(!same_variant!(Shape::Empty, Shape::Empty, Shape::[Circle, Rect]));
+/// ```
+///
+/// Unlike [`core::mem::discriminant`] comparison, only the listed variants are considered the same
+/// and the `#[cfg]` attributes of the variants are respected. The values are taken by reference.
 #[proc_macro]
-pub fn all_the_same(item: TokenStream) -> TokenStream {
-    let args = parse_macro_input!(item as Args);
+pub fn same_variant(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item as same_variant::Args);
+
+    same_variant::expand(&args).into()
+}
+
+/// Checks if the value is one of the listed variants:
+///
+/// ```
+/// use all_the_same::is_any_of;
+///
+/// enum Stream {
+///     Tcp(u16),
+///     Unix(String),
+///     #[cfg(feature = "unsupported")]
+///     Pipe(u32),
+///     Closed,
+/// }
+///
+/// let stream = Stream::Unix("/tmp/app.sock".into());
+///
+/// assert!(is_any_of!(stream, Stream::[
+///     Tcp,
+///     Unix,
+///     #[cfg(feature = "unsupported")]
+///     Pipe,
+/// ]));
+/// assert!(!is_any_of!(Stream::Closed, Stream::[Tcp, Unix]));
+/// ```
+///
+/// Unlike `matches!`, the variants don't need their payloads to be spelled out and the `#[cfg]`
+/// attributes of the variants are respected. The value is taken by reference.
+#[proc_macro]
+pub fn is_any_of(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item as is_any_of::Args);
+
+    is_any_of::expand(&args).into()
+}
+
+/// Matches the value against all the variants of the enum, registered with
+/// [`#[variants]`](macro@variants), with the same closure-like body. It's a shorthand for the
+/// [`all_the_same!`] match with the single `Enum::[*](binding) => body` arm, that reads well in
+/// one-liners:
+///
+/// ```
+/// use all_the_same::{for_each_variant, variants};
+/// use std::io;
+/// use std::pin::Pin;
+/// use std::task::{Context, Poll};
+/// use tokio::io::AsyncWrite;
+/// use tokio::net::{TcpStream, UnixStream};
+///
+/// #[variants]
+/// enum Stream {
+///     Tcp(TcpStream),
+///     Unix(UnixStream),
+/// }
+///
+/// impl Stream {
+///     fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+///         for_each_variant!(self, Stream, |s| Pin::new(s).poll_flush(cx))
+///     }
+/// }
+/// ```
+///
+/// The enum is named explicitly, as the macro only sees the tokens of the value, so it can't tell
+/// the type of `self`. The body of the closure is expanded in the arms rather than called, so
+/// `return`, `?`, `break` and `continue`, that would apply to the function instead of the
+/// closure, are rejected:
+///
+/// ```compile_fail
+/// use all_the_same::{for_each_variant, variants};
+///
+/// #[variants]
+/// enum Number {
+///     I32(i32),
+///     I64(i64),
+/// }
+///
+/// fn to_u8(number: &Number) -> Result<u8, std::num::TryFromIntError> {
+///     // error: `?` would return from the function, not the closure
+///     let n = for_each_variant!(number, Number, |n| u8::try_from(*n)?);
+///
+///     Ok(n)
+/// }
+/// ```
+#[proc_macro]
+pub fn for_each_variant(item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(item as for_each_variant::Args);
+
+    for_each_variant::expand(&args)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives conversions between the enum and a "superset" enum, which has all of the enum's
+/// variants and, possibly, some more.
+///
+/// The superset is specified with the `#[subset(Enum)]` attribute (it can be repeated for
+/// several supersets) and variants are matched by name. A `TryFrom` implementation is generated,
+/// that returns the original value if its variant is not a part of the subset. In the other
+/// direction, an infallible `From` implementation is generated, that converts payloads into the
+/// superset's ones with `Into`. The narrowing moves the payloads as is, so the payload types of
+/// the variants have to match:
+///
+/// ```
+/// use all_the_same::Subset;
+/// use std::convert::TryFrom;
+///
+/// enum Value {
+///     Int(i64),
+///     Float(f64),
+///     Text(String),
+/// }
+///
+/// #[derive(Subset)]
+/// #[subset(Value)]
+/// enum Number {
+///     Int(i64),
+///     Float(f64),
+/// }
+///
+/// assert!(matches!(Number::try_from(Value::Int(42)), Ok(Number::Int(42))));
+/// assert!(matches!(Number::try_from(Value::Text("42".into())), Err(Value::Text(_))));
+/// assert!(matches!(Value::from(Number::Float(4.2)), Value::Float(_)));
+/// ```
+#[proc_macro_derive(Subset, attributes(subset, derive_coverage))]
+pub fn derive_subset(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    subset::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
 
-    let expr = &args.expr;
-    let enum_name = &args.enum_name;
-    let inner_name = &args.inner_name;
-    let arm_expr = &args.arm_expr;
+/// Derives the accessors of the variants' payloads.
+///
+/// For each variant, e.g. `TcpStream`, the `is_tcp_stream()`, `as_tcp_stream()`,
+/// `as_tcp_stream_mut()` and `into_tcp_stream()` methods are generated, with the same visibility
+/// as the enum. The acronyms in the names are kept together, e.g. `HTTPBody` gets `is_http_body()`.
+/// `into_*` methods return the value itself if it's another variant, so it can be checked against
+/// the other variants:
+///
+/// ```
+/// use all_the_same::Accessors;
+/// use std::net::TcpStream;
+/// use std::path::PathBuf;
+///
+/// #[derive(Accessors)]
+/// enum Source {
+///     Path(PathBuf),
+///     TcpStream(TcpStream),
+///     HTTPBody(Vec<u8>),
+/// }
+///
+/// let mut source = Source::Path(PathBuf::from("/tmp"));
+///
+/// assert!(source.is_path());
+/// assert!(source.as_tcp_stream().is_none());
+/// assert!(Source::HTTPBody(vec![]).is_http_body());
+///
+/// source.as_path_mut().unwrap().push("foo");
+///
+/// let source = source.into_tcp_stream().unwrap_err();
+///
+/// assert_eq!(source.into_path().ok(), Some(PathBuf::from("/tmp/foo")));
+/// ```
+///
+/// With `#[accessors(const)]` the methods are `const fn`, except for `into_*`, as the compiler
+/// can't drop the rest of the value in the const contexts:
+///
+/// ```
+/// use all_the_same::Accessors;
+///
+/// #[derive(Accessors)]
+/// #[accessors(const)]
+/// enum Limit {
+///     Bytes(u64),
+///     Items(u32),
+/// }
+///
+/// const IS_BYTES: bool = Limit::Bytes(1024).is_bytes();
+/// const ITEMS: Option<&u32> = Limit::Items(16).as_items();
+///
+/// assert!(IS_BYTES);
+/// assert_eq!(ITEMS, Some(&16));
+/// ```
+///
+/// The methods of the variants gated with `#[cfg(..)]` are gated the same way. With
+/// `#[accessors(doc_cfg)]` they also get `#[cfg_attr(docsrs, doc(cfg(..)))]`, so docs.rs shows
+/// what enables them. The crate has to enable `#![cfg_attr(docsrs, feature(doc_cfg))]` then:
+///
+/// ```
+/// use all_the_same::Accessors;
+/// use std::net::TcpStream;
+///
+/// #[derive(Accessors)]
+/// #[accessors(doc_cfg)]
+/// pub enum Stream {
+///     Tcp(TcpStream),
+///     #[cfg(unix)]
+///     Unix(std::os::unix::net::UnixStream),
+/// }
+/// ```
+#[proc_macro_derive(Accessors, attributes(accessors, derive_coverage))]
+pub fn derive_accessors(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
 
-    let enum_name = match enum_name {
-        Some(name) => quote!(#name),
-        None => quote!(Self),
+    accessors::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives the accessors of the payload as trait objects, for the traits listed in the
+/// `#[as_dyn(..)]` attribute.
+///
+/// For each trait, e.g. `Write`, the `as_write()` and `as_write_mut()` methods are generated,
+/// that return `&dyn Write` and `&mut dyn Write` respectively. The traits wrapped in `pin(..)`
+/// additionally get the `as_*_pin()` method, that takes `Pin<&mut Self>` and returns
+/// `Pin<&mut dyn Trait>`, which requires the payloads to be `Unpin`. This is handy at the API
+/// boundaries, that accept trait objects, where the static delegation isn't needed:
+///
+/// ```
+/// use all_the_same::AsDyn;
+/// use std::fmt::Debug;
+/// use std::future::{self, Future, Pending, Ready};
+/// use std::io::{self, Cursor, Sink, Write};
+/// use std::pin::Pin;
+/// use std::task::{Context, Poll, Waker};
+///
+/// #[derive(AsDyn)]
+/// #[as_dyn(Write, Debug)]
+/// enum Output {
+///     Buffer(Cursor<Vec<u8>>),
+///     Sink(Sink),
+/// }
+///
+/// #[derive(AsDyn)]
+/// #[as_dyn(pin(Future<Output = u32>))]
+/// enum Answer {
+///     Ready(Ready<u32>),
+///     Pending(Pending<u32>),
+/// }
+///
+/// fn log(out: &mut dyn Write, msg: &str) -> io::Result<()> {
+///     writeln!(out, "{}", msg)
+/// }
+///
+/// let mut output = Output::Buffer(Cursor::new(Vec::new()));
+///
+/// log(output.as_write_mut(), "hello").unwrap();
+///
+/// assert_eq!(
+///     format!("{:?}", output.as_debug()),
+///     "Cursor { inner: [104, 101, 108, 108, 111, 10], pos: 6 }",
+/// );
+///
+/// let mut answer = Answer::Ready(future::ready(42));
+/// let mut cx = Context::from_waker(Waker::noop());
+///
+/// assert_eq!(Pin::new(&mut answer).as_future_pin().poll(&mut cx), Poll::Ready(42));
+/// ```
+#[proc_macro_derive(AsDyn, attributes(as_dyn, derive_coverage))]
+pub fn derive_as_dyn(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    as_dyn::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `From` the payload type for each variant.
+///
+/// Variants, which payload types can't be converted unambiguously, e.g. because several variants
+/// have the same payload type, or it's a type parameter of the enum, can be excluded with
+/// `#[variant_from(skip)]`:
+///
+/// ```
+/// use all_the_same::VariantFrom;
+/// use std::io;
+/// use std::num::ParseIntError;
+///
+/// #[derive(VariantFrom)]
+/// enum Error {
+///     Io(io::Error),
+///     Parse(ParseIntError),
+///
+///     #[variant_from(skip)]
+///     Message(String),
+///
+///     #[variant_from(skip)]
+///     Context(String),
+/// }
+///
+/// fn parse(s: &str) -> Result<u32, Error> {
+///     Ok(s.parse::<u32>()?)
+/// }
+///
+/// assert!(matches!(parse("x"), Err(Error::Parse(_))));
+/// assert!(matches!(Error::from(io::Error::other("oops")), Error::Io(_)));
+/// ```
+///
+/// Trait impls can't be `const` on stable Rust, so in the const contexts the variant itself is
+/// used as the constructor, e.g. `Error::Io` instead of `Error::from`.
+///
+/// Like with [`Accessors`], `#[variant_from(doc_cfg)]` on the enum marks the impls for the
+/// variants gated with `#[cfg(..)]` with `#[cfg_attr(docsrs, doc(cfg(..)))]`.
+#[proc_macro_derive(VariantFrom, attributes(variant_from, derive_coverage))]
+pub fn derive_variant_from(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    variant_from::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `From<Either<A, B>>` for the enum of two variants, with the payloads `A` and `B`, and
+/// `From` the enum for `Either<A, B>`, so the enum can be used where the
+/// [`either`](https://docs.rs/either) crate is:
+///
+/// ```
+/// use all_the_same::EitherFrom;
+/// use either::Either;
+/// use std::io::{self, Cursor, Sink};
+///
+/// #[derive(EitherFrom)]
+/// enum Output {
+///     Buffer(Cursor<Vec<u8>>),
+///     Sink(Sink),
+/// }
+///
+/// let output = Output::from(Either::Right(io::sink()));
+///
+/// assert!(matches!(output, Output::Sink(_)));
+/// assert!(Either::from(output).is_right());
+/// ```
+///
+/// The first variant is `Left` and the second one is `Right`.
+#[cfg(feature = "either")]
+#[cfg_attr(docsrs, doc(cfg(feature = "either")))]
+#[proc_macro_derive(EitherFrom, attributes(derive_coverage))]
+pub fn derive_either_from(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    either::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives a borrowed view of the enum.
+///
+/// For an enum `Name` the `NameRef<'_>` enum is generated, that has the same variants, but holds
+/// references to the payloads. It can be obtained with the generated `as_ref()` method. With
+/// the `#[ref_view(to_owned)]` attribute a `to_owned()` method is also generated for the view,
+/// that clones the payload back into the original enum (so all the payloads need to be `Clone`):
+///
+/// ```
+/// use all_the_same::{all_the_same, RefView};
+///
+/// #[derive(RefView)]
+/// #[ref_view(to_owned)]
+/// enum Value {
+///     Int(i64),
+///     Text(String),
+/// }
+///
+/// impl<'a> ValueRef<'a> {
+///     pub fn len(self) -> usize {
+///         all_the_same!(match self {
+///             ValueRef::[Int, Text](v) => v.to_string().len()
+///         })
+///     }
+/// }
+///
+/// let value = Value::Text("foo".into());
+///
+/// assert_eq!(value.as_ref().len(), 3);
+/// assert!(matches!(value.as_ref().to_owned(), Value::Text(s) if s == "foo"));
+/// ```
+///
+/// For the generic enums, `to_owned()` requires the type parameters to be `Clone`:
+///
+/// ```
+/// use all_the_same::RefView;
+///
+/// #[derive(RefView)]
+/// #[ref_view(to_owned)]
+/// enum Slot<T> {
+///     Full(T),
+///     Reserved(u8),
+/// }
+///
+/// let slot = Slot::Full(vec![1, 2]);
+///
+/// assert!(matches!(slot.as_ref().to_owned(), Slot::Full(v) if v == [1, 2]));
+/// ```
+#[proc_macro_derive(RefView, attributes(ref_view, derive_coverage))]
+pub fn derive_ref_view(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    ref_view::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives inherent methods that forward to the same-named methods of the payload.
+///
+/// The methods are listed as signatures in the `#[forward(...)]` attribute. This covers the
+/// methods that are not a part of any trait:
+///
+/// ```
+/// use all_the_same::Forward;
+/// use std::collections::VecDeque;
+///
+/// #[derive(Forward)]
+/// #[forward(
+///     pub fn len(&self) -> usize,
+///     pub fn is_empty(&self) -> bool,
+///     /// Removes all the items.
+///     pub fn clear(&mut self)
+/// )]
+/// enum Items {
+///     Vec(Vec<u8>),
+///     Deque(VecDeque<u8>),
+///     String(String),
+/// }
+///
+/// let mut items = Items::Deque(VecDeque::from(vec![1, 2, 3]));
+///
+/// assert_eq!(items.len(), 3);
+///
+/// items.clear();
+///
+/// assert!(items.is_empty());
+/// ```
+///
+/// The receiver is adapted to the payload automatically: `&self` payload methods can be
+/// forwarded from `&mut self` methods, and `self: Pin<&mut Self>` is projected to the pinned
+/// payload (which requires the payloads to be `Unpin`, unless the enum derives [`PinProject`]):
+///
+/// ```
+/// use all_the_same::Forward;
+/// use std::future::{Future, Pending, Ready};
+/// use std::pin::Pin;
+/// use std::task::{Context, Poll};
+///
+/// #[derive(Forward)]
+/// #[forward(
+///     pub fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<u8>
+/// )]
+/// enum Value {
+///     Ready(Ready<u8>),
+///     Pending(Pending<u8>),
+/// }
+/// ```
+///
+/// No bounds are added for the payload types, so trait object payloads, like the escape hatch
+/// variant in the example at the top of the page, can be mixed with the concrete ones:
+///
+/// ```
+/// use all_the_same::Forward;
+/// use std::io;
+/// use std::pin::Pin;
+/// use std::task::{Context, Poll};
+/// use tokio::io::AsyncWrite;
+/// use tokio::net::TcpStream;
+///
+/// #[derive(Forward)]
+/// #[forward(
+///     pub fn poll_write(
+///         self: Pin<&mut Self>,
+///         cx: &mut Context<'_>,
+///         buf: &[u8],
+///     ) -> Poll<io::Result<usize>>,
+///     pub fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+/// )]
+/// enum Stream {
+///     Tcp(TcpStream),
+///     Custom(Box<dyn AsyncWrite + Unpin + Send>),
+/// }
+/// ```
+///
+/// Derives can't share state between enums, but the same set of forwarded methods can be applied
+/// to a family of enums by wrapping their definitions into a `macro_rules!` macro:
+///
+/// ```
+/// use all_the_same::{DelegateDebug, Forward};
+/// use std::collections::VecDeque;
+///
+/// macro_rules! collection {
+///     ($($item:item)*) => {
+///         $(
+///             #[derive(Forward, DelegateDebug)]
+///             #[forward(
+///                 pub fn len(&self) -> usize,
+///                 pub fn is_empty(&self) -> bool
+///             )]
+///             $item
+///         )*
+///     };
+/// }
+///
+/// collection! {
+///     enum Bytes {
+///         Vec(Vec<u8>),
+///         Deque(VecDeque<u8>),
+///     }
+///
+///     enum Text {
+///         String(String),
+///         Str(&'static str),
+///     }
+/// }
+///
+/// assert_eq!(Bytes::Vec(vec![1, 2]).len(), 2);
+/// assert!(Text::Str("").is_empty());
+/// ```
+#[proc_macro_derive(Forward, attributes(forward, derive_coverage))]
+pub fn derive_forward(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    forward::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Registers the method signatures of the trait, so that [`Delegate`] can implement it for enums.
+///
+/// Every method with `self` is delegated to the payload. The associated types, constants and
+/// methods without `self`, that have no default, are implemented as the options of [`Delegate`]
+/// say:
+///
+/// ```
+/// use all_the_same::{delegatable, Delegate};
+///
+/// #[delegatable]
+/// trait Shape {
+///     fn area(&self) -> f64;
+///     fn scale(&mut self, factor: f64);
+/// }
+///
+/// struct Circle(f64);
+///
+/// impl Shape for Circle {
+///     fn area(&self) -> f64 {
+///         3.14 * self.0 * self.0
+///     }
+///
+///     fn scale(&mut self, factor: f64) {
+///         self.0 *= factor;
+///     }
+/// }
+///
+/// struct Square(f64);
+///
+/// impl Shape for Square {
+///     fn area(&self) -> f64 {
+///         self.0 * self.0
+///     }
+///
+///     fn scale(&mut self, factor: f64) {
+///         self.0 *= factor;
+///     }
+/// }
+///
+/// #[derive(Delegate)]
+/// #[delegate(Shape)]
+/// enum AnyShape {
+///     Circle(Circle),
+///     Square(Square),
+///     #[cfg(feature = "unsupported")]
+///     Boxed(Box<dyn Shape>),
+/// }
+///
+/// let mut shape = AnyShape::Square(Square(2.0));
+///
+/// shape.scale(2.0);
+///
+/// assert_eq!(shape.area(), 16.0);
+/// ```
+///
+/// `async fn`s are delegated by awaiting the method of the payload:
+///
+/// ```
+/// use all_the_same::{delegatable, Delegate};
+/// use futures::executor::block_on;
+///
+/// #[delegatable]
+/// trait Service {
+///     async fn call(&self, request: u32) -> u32;
+/// }
+///
+/// struct Double;
+///
+/// impl Service for Double {
+///     async fn call(&self, request: u32) -> u32 {
+///         request * 2
+///     }
+/// }
+///
+/// struct Echo;
+///
+/// impl Service for Echo {
+///     async fn call(&self, request: u32) -> u32 {
+///         request
+///     }
+/// }
+///
+/// #[derive(Delegate)]
+/// #[delegate(Service)]
+/// enum AnyService {
+///     Double(Double),
+///     Echo(Echo),
+/// }
+///
+/// assert_eq!(block_on(AnyService::Double(Double).call(21)), 42);
+/// ```
+///
+/// The traits with `#[async_trait]` are supported too. If the attribute goes before
+/// `#[delegatable]`, the trait is already desugared to the methods returning boxed futures, which
+/// are delegated as is. If it goes after, the `async-trait` feature is needed, so the attribute is
+/// carried over to the generated impls.
+///
+/// Like with [`#[variants]`](variants), the signatures are looked up with a hidden macro, that
+/// has the same name as the trait and is imported along with it. This is why only the traits of
+/// the current crate can be delegated. The types in the signatures are resolved where the enum
+/// is, so they have to be in scope there. The hidden macro refers to the crate as
+/// `::all_the_same`, and `#[delegatable(crate = path)]` sets the path to the re-exported crate.
+#[proc_macro_attribute]
+pub fn delegatable(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let krate = if attr.is_empty() {
+        CratePath::default()
+    } else {
+        parse_macro_input!(attr as CratePath)
     };
 
-    let arms = args.variants.iter().map(|variant| {
-        let name = &variant.name;
-        let attrs = &variant.attrs;
+    let item = parse_macro_input!(item as ItemTrait);
+
+    delegate::expand_trait(&item, &krate)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
 
-        quote! {
-            #(#attrs)*
-            #enum_name::#name(#inner_name) => #arm_expr
-        }
-    });
+/// Derives the implementations of the traits, registered with [`#[delegatable]`](delegatable),
+/// that delegate every method to the payload.
+///
+/// The traits are listed in the `#[delegate(...)]` attribute. The methods of the payload are
+/// called by the trait path, so they are not shadowed by the inherent methods with the same
+/// name. For the generic enums the trait bound is added to the type parameters, like the std
+/// derives do:
+///
+/// ```
+/// use all_the_same::{delegatable, Delegate};
+///
+/// #[delegatable]
+/// trait Describe {
+///     fn describe(&self) -> String;
+/// }
+///
+/// impl Describe for u8 {
+///     fn describe(&self) -> String {
+///         format!("byte {}", self)
+///     }
+/// }
+///
+/// impl Describe for String {
+///     fn describe(&self) -> String {
+///         format!("string {:?}", self)
+///     }
+/// }
+///
+/// #[derive(Delegate)]
+/// #[delegate(Describe)]
+/// enum Either<T> {
+///     Byte(u8),
+///     Other(T),
+/// }
+///
+/// assert_eq!(Either::<String>::Byte(1).describe(), "byte 1");
+/// assert_eq!(Either::Other(String::from("a")).describe(), "string \"a\"");
+/// ```
+///
+/// The methods listed in `except(...)` after the trait are not delegated, but implemented by the
+/// inherent methods of the enum with the same names and signatures, and it's an error if there
+/// are none. A single variant can use its own function instead of the payload's method with
+/// `#[delegate(Trait::method = function)]`, that takes the payload in place of `self`:
+///
+/// ```
+/// use all_the_same::{delegatable, Delegate};
+///
+/// #[delegatable]
+/// trait Shape {
+///     fn area(&self) -> f64;
+///     fn name(&self) -> String;
+/// }
+///
+/// struct Square(f64);
+///
+/// impl Shape for Square {
+///     fn area(&self) -> f64 {
+///         self.0 * self.0
+///     }
+///
+///     fn name(&self) -> String {
+///         "square".into()
+///     }
+/// }
+///
+/// fn point_area(_: &Square) -> f64 {
+///     0.0
+/// }
+///
+/// #[derive(Delegate)]
+/// #[delegate(Shape, except(name))]
+/// enum AnyShape {
+///     Square(Square),
+///     #[delegate(Shape::area = point_area)]
+///     Point(Square),
+/// }
+///
+/// impl AnyShape {
+///     fn name(&self) -> String {
+///         "any shape".into()
+///     }
+/// }
+///
+/// assert_eq!(AnyShape::Square(Square(2.0)).area(), 4.0);
+/// assert_eq!(AnyShape::Point(Square(2.0)).area(), 0.0);
+/// assert_eq!(Shape::name(&AnyShape::Square(Square(2.0))), "any shape");
+/// ```
+///
+/// The associated types are taken from the payloads, which all have to have the same ones.
+/// Otherwise, the types, constants and methods without `self` are given with
+/// `assoc(Name = value)`, where the value of a method is the function to call, or taken from the
+/// payload of the first variant with `first(name)`. The constructors, that return `Self`, wrap
+/// the payload in the first variant:
+///
+/// ```
+/// use all_the_same::{delegatable, Delegate};
+///
+/// #[delegatable]
+/// trait Decoder {
+///     type Item;
+///     type Error;
+///     const NAME: &'static str;
+///
+///     fn new() -> Self;
+///     fn decode(&mut self, byte: u8) -> Result<Self::Item, Self::Error>;
+/// }
+///
+/// struct Ascii;
+///
+/// impl Decoder for Ascii {
+///     type Item = char;
+///     type Error = u8;
+///     const NAME: &'static str = "ascii";
+///
+///     fn new() -> Self {
+///         Ascii
+///     }
+///
+///     fn decode(&mut self, byte: u8) -> Result<char, u8> {
+///         if byte.is_ascii() {
+///             Ok(byte as char)
+///         } else {
+///             Err(byte)
+///         }
+///     }
+/// }
+///
+/// struct Latin1;
+///
+/// impl Decoder for Latin1 {
+///     type Item = char;
+///     type Error = u8;
+///     const NAME: &'static str = "latin1";
+///
+///     fn new() -> Self {
+///         Latin1
+///     }
+///
+///     fn decode(&mut self, byte: u8) -> Result<char, u8> {
+///         Ok(byte as char)
+///     }
+/// }
+///
+/// #[derive(Delegate)]
+/// #[delegate(Decoder, assoc(NAME = "any"), first(new))]
+/// enum AnyDecoder {
+///     Ascii(Ascii),
+///     Latin1(Latin1),
+/// }
+///
+/// let mut decoder = AnyDecoder::new();
+///
+/// assert_eq!(AnyDecoder::NAME, "any");
+/// assert_eq!(decoder.decode(0xe9), Err(0xe9));
+/// ```
+///
+/// With `impl_for(&Self, &mut Self, Box<Self>)` the trait is also implemented for the listed
+/// pointers to the enum, that call the impl for the enum, as long as the receivers of the methods
+/// can be borrowed or moved out of the pointer. `Box` is referred to through the `alloc` crate,
+/// that has to be declared, as described in the `no_std` section of the [crate docs](crate):
+///
+/// ```
+/// extern crate alloc;
+///
+/// use all_the_same::{delegatable, Delegate};
+///
+/// #[delegatable]
+/// trait Counter {
+///     fn add(&mut self, n: u32);
+///     fn get(&self) -> u32;
+/// }
+///
+/// impl Counter for u32 {
+///     fn add(&mut self, n: u32) {
+///         *self += n;
+///     }
+///
+///     fn get(&self) -> u32 {
+///         *self
+///     }
+/// }
+///
+/// #[derive(Delegate)]
+/// #[delegate(Counter, impl_for(&mut Self, Box<Self>))]
+/// enum AnyCounter {
+///     Plain(u32),
+/// }
+///
+/// fn add_twice(mut counter: impl Counter) -> u32 {
+///     counter.add(1);
+///     counter.add(1);
+///     counter.get()
+/// }
+///
+/// let mut counter = AnyCounter::Plain(0);
+///
+/// assert_eq!(add_twice(&mut counter), 2);
+/// assert_eq!(add_twice(Box::new(counter)), 4);
+/// ```
+///
+/// Implementing an `unsafe trait` asserts that its safety contract is upheld, so such traits are
+/// only delegated with `unsafe` in front of them, e.g. `#[delegate(unsafe Trait)]`, that
+/// generates `unsafe impl`s:
+///
+/// ```
+/// use all_the_same::{delegatable, Delegate};
+///
+/// /// # Safety
+/// ///
+/// /// `id` never returns 0.
+/// #[delegatable]
+/// unsafe trait Unique {
+///     fn id(&self) -> u32;
+/// }
+///
+/// struct Local;
+/// struct Remote;
+///
+/// unsafe impl Unique for Local {
+///     fn id(&self) -> u32 {
+///         1
+///     }
+/// }
+///
+/// unsafe impl Unique for Remote {
+///     fn id(&self) -> u32 {
+///         2
+///     }
+/// }
+///
+/// // SAFETY: the enum returns the ids of the payloads, that are never 0.
+/// #[derive(Delegate)]
+/// #[delegate(unsafe Unique)]
+/// enum Peer {
+///     Local(Local),
+///     Remote(Remote),
+/// }
+///
+/// assert_eq!(Peer::Remote(Remote).id(), 2);
+/// ```
+///
+/// Without `unsafe` in the attribute, the delegation of an `unsafe trait` is rejected:
+///
+/// ```compile_fail
+/// use all_the_same::{delegatable, Delegate};
+///
+/// #[delegatable]
+/// unsafe trait Unique {
+///     fn id(&self) -> u32;
+/// }
+///
+/// unsafe impl Unique for u32 {
+///     fn id(&self) -> u32 {
+///         *self
+///     }
+/// }
+///
+/// #[derive(Delegate)]
+/// #[delegate(Unique)]
+/// enum Peer {
+///     Local(u32),
+/// }
+/// ```
+///
+/// Every delegated method matches on the variants, so the enums that delegate the same traits to
+/// the same payloads get the near-identical matches for every method. With `via(AsDyn)` the
+/// methods are called on the trait object returned by the accessors of [`AsDyn`] instead, e.g.
+/// `as_transport()` for `&self` methods, `as_transport_mut()` for `&mut self` and
+/// `as_transport_pin()` for `Pin<&mut Self>`. The enum then only has the matches of the
+/// accessors, and the code of the payloads' impls is shared through their vtables, at the cost
+/// of the dynamic dispatch. The trait has to be usable as a trait object, and its methods can't
+/// be overridden for the variants:
+///
+/// ```
+/// use all_the_same::{delegatable, AsDyn, Delegate};
+///
+/// #[delegatable]
+/// trait Transport {
+///     fn send(&mut self, data: &[u8]) -> usize;
+///     fn name(&self) -> &'static str;
+/// }
+///
+/// struct Tcp;
+///
+/// impl Transport for Tcp {
+///     fn send(&mut self, data: &[u8]) -> usize {
+///         data.len()
+///     }
+///
+///     fn name(&self) -> &'static str {
+///         "tcp"
+///     }
+/// }
+///
+/// struct Udp;
+///
+/// impl Transport for Udp {
+///     fn send(&mut self, data: &[u8]) -> usize {
+///         data.len().min(512)
+///     }
+///
+///     fn name(&self) -> &'static str {
+///         "udp"
+///     }
+/// }
+///
+/// #[derive(AsDyn, Delegate)]
+/// #[as_dyn(Transport)]
+/// #[delegate(Transport, via(AsDyn))]
+/// enum Client {
+///     Tcp(Tcp),
+///     Udp(Udp),
+/// }
+///
+/// #[derive(AsDyn, Delegate)]
+/// #[as_dyn(Transport)]
+/// #[delegate(Transport, via(AsDyn))]
+/// enum Server {
+///     Tcp(Tcp),
+///     Udp(Udp),
+/// }
+///
+/// let mut client = Client::Udp(Udp);
+///
+/// assert_eq!(client.send(&[0; 1024]), 512);
+/// assert_eq!(Server::Tcp(Tcp).name(), "tcp");
+/// ```
+#[proc_macro_derive(Delegate, attributes(delegate, derive_coverage))]
+pub fn derive_delegate(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
 
-    quote! {
-        match #expr {
-            #(#arms),*
-        }
-    }
-    .into()
+    delegate::expand_derive(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+#[doc(hidden)]
+#[proc_macro]
+pub fn __delegate(item: TokenStream) -> TokenStream {
+    let delegate = parse_macro_input!(item as delegate::DelegateImpl);
+
+    delegate::expand_impl(&delegate)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `Deref` and `AsRef` to the target type specified with the `#[deref(Type)]` attribute.
+///
+/// The payloads are converted to the target with their `AsRef` impls, so the derive works for the
+/// payloads that don't implement `Deref`, like arrays. With `#[deref(Type, mut)]`, `DerefMut` and
+/// `AsMut` are derived as well, using the `AsMut` impls of the payloads:
+///
+/// ```
+/// use all_the_same::DelegateDeref;
+/// use std::borrow::Cow;
+///
+/// #[derive(DelegateDeref)]
+/// #[deref([u8])]
+/// enum Bytes {
+///     Inline([u8; 4]),
+///     Heap(Vec<u8>),
+///     Shared(Cow<'static, [u8]>),
+/// }
+///
+/// #[derive(DelegateDeref)]
+/// #[deref([u8], mut)]
+/// enum Buf {
+///     Inline([u8; 4]),
+///     Heap(Vec<u8>),
+/// }
+///
+/// assert_eq!(Bytes::Inline(*b"abcd").len(), 4);
+/// assert_eq!(&Bytes::Shared(Cow::Borrowed(b"ab"))[..], b"ab");
+///
+/// let mut buf = Buf::Heap(vec![1, 2, 3]);
+///
+/// buf[0] = 42;
+///
+/// assert_eq!(buf.as_ref(), [42, 2, 3]);
+/// ```
+#[proc_macro_derive(DelegateDeref, attributes(deref, derive_coverage))]
+pub fn derive_delegate_deref(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    deref::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `Display` that delegates to the payload, so the enum is displayed the same way as
+/// the payload it holds. See [`DelegateError`] for an example.
+#[proc_macro_derive(DelegateDisplay, attributes(derive_coverage))]
+pub fn derive_delegate_display(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    error::expand_display(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `core::error::Error`, that `std::error::Error` re-exports, that delegates to the
+/// payload, including the deprecated `description` and `cause` methods.
+///
+/// Along with [`DelegateDisplay`], this makes an enum of errors display and report its source the
+/// same way as the error it holds, like `#[error(transparent)]` of `thiserror` does:
+///
+/// ```
+/// use all_the_same::{DelegateDebug, DelegateDisplay, DelegateError};
+/// use std::error::Error;
+/// use std::num::ParseIntError;
+/// use std::str::Utf8Error;
+///
+/// #[derive(DelegateDebug, DelegateDisplay, DelegateError)]
+/// enum ParseError {
+///     Utf8(Utf8Error),
+///     Int(ParseIntError),
+/// }
+///
+/// fn parse(bytes: &[u8]) -> Result<u32, ParseError> {
+///     let s = std::str::from_utf8(bytes).map_err(ParseError::Utf8)?;
+///
+///     s.parse().map_err(ParseError::Int)
+/// }
+///
+/// let err = parse(b"4x2").unwrap_err();
+///
+/// assert_eq!(err.to_string(), "invalid digit found in string");
+/// assert!(err.source().is_none());
+/// ```
+#[proc_macro_derive(DelegateError, attributes(derive_coverage))]
+pub fn derive_delegate_error(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    error::expand_error(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `Debug` that prints the variant's name along with the payload's `Debug` output, e.g.
+/// `Stream::Tcp(TcpStream { .. })`.
+///
+/// With the `#[debug(compact)]` attribute on the enum the enum's name is omitted. Variants marked
+/// with `#[debug(redact)]` print `<redacted>` instead of the payload. The alternate (`{:#?}`)
+/// formatting is supported as well:
+///
+/// ```
+/// use all_the_same::DelegateDebug;
+///
+/// #[derive(DelegateDebug)]
+/// enum Credentials {
+///     Login(String),
+///
+///     #[debug(redact)]
+///     Token(String),
+/// }
+///
+/// #[derive(DelegateDebug)]
+/// #[debug(compact)]
+/// enum Number {
+///     I32(i32),
+///     F64(f64),
+/// }
+///
+/// assert_eq!(format!("{:?}", Credentials::Login("root".into())), r#"Credentials::Login("root")"#);
+/// assert_eq!(
+///     format!("{:?}", Credentials::Token("s3cr3t".into())),
+///     "Credentials::Token(<redacted>)",
+/// );
+/// assert_eq!(format!("{:?}", Number::I32(42)), "I32(42)");
+/// assert_eq!(format!("{:#?}", Number::F64(4.2)), "F64(\n    4.2,\n)");
+/// ```
+#[proc_macro_derive(DelegateDebug, attributes(debug, derive_coverage))]
+pub fn derive_delegate_debug(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    debug::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `Clone` that clones the payload of the active variant.
+///
+/// The arms respect variants' `#[cfg]` attributes, and if some payload is `Clone` only under some
+/// configuration, the derive itself can be gated with `cfg_attr`:
+///
+/// ```
+/// use all_the_same::DelegateClone;
+///
+/// #[derive(DelegateClone)]
+/// enum Source {
+///     Bytes(Vec<u8>),
+///     Text(String),
+///
+///     #[cfg(test)]
+///     Mock(Vec<String>),
+/// }
+///
+/// assert!(matches!(Source::Text("foo".into()).clone(), Source::Text(s) if s == "foo"));
+/// ```
+#[proc_macro_derive(DelegateClone, attributes(derive_coverage))]
+pub fn derive_delegate_clone(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    clone::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `PartialEq` that compares payloads of the same variants, values of different variants
+/// are never equal.
+///
+/// Variants, which payloads can't be compared, can be marked with `#[partial_eq(skip)]`, so
+/// their values are never equal to anything. Combined with `cfg_attr` this handles payloads that
+/// implement `PartialEq` only under some configurations:
+///
+/// ```
+/// use all_the_same::DelegatePartialEq;
+///
+/// #[cfg_attr(feature = "handle-eq", derive(PartialEq))]
+/// struct Handle(u32);
+///
+/// #[derive(DelegatePartialEq)]
+/// enum Source {
+///     Bytes(Vec<u8>),
+///     Text(String),
+///
+///     #[cfg_attr(not(feature = "handle-eq"), partial_eq(skip))]
+///     Handle(Handle),
+/// }
+///
+/// assert!(Source::Text("foo".into()) == Source::Text("foo".into()));
+/// assert!(Source::Text("foo".into()) != Source::Bytes(b"foo".to_vec()));
+/// ```
+///
+/// With the `#[partial_eq(payloads)]` attribute on the enum, the enum can also be compared with
+/// the payload types directly (in both directions). The value is equal to the payload if one of
+/// the variants with that payload type is active. The variants marked with
+/// `#[partial_eq(skip_payload)]` are only compared with the enum:
+///
+/// ```
+/// use all_the_same::DelegatePartialEq;
+///
+/// #[derive(DelegatePartialEq)]
+/// #[partial_eq(payloads)]
+/// enum Source {
+///     Bytes(Vec<u8>),
+///     Text(String),
+///     Path(String),
+///
+///     #[partial_eq(skip_payload)]
+///     Fd(i32),
+/// }
+///
+/// assert!(Source::Text("foo".into()) == "foo".to_string());
+/// assert!(Source::Path("foo".into()) == "foo".to_string());
+/// assert!(b"foo".to_vec() != Source::Text("foo".into()));
+/// assert!(Source::Fd(1) == Source::Fd(1));
+/// ```
+///
+/// The comparisons of the payload types, that mention the type parameters of the enum, e.g.
+/// `&'a T`, with the enum are only generated in one direction, as the orphan rules reject the
+/// other one:
+///
+/// ```
+/// use all_the_same::DelegatePartialEq;
+///
+/// #[derive(DelegatePartialEq)]
+/// #[partial_eq(payloads)]
+/// enum Value<'a, T> {
+///     Borrowed(&'a T),
+///     Owned(T),
+/// }
+///
+/// assert!(Value::Borrowed(&1) == &1);
+/// assert!(Value::Owned(1) == 1);
+/// assert!(Value::Owned(1) != 2);
+/// ```
+#[proc_macro_derive(DelegatePartialEq, attributes(partial_eq, derive_coverage))]
+pub fn derive_delegate_partial_eq(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    partial_eq::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `PartialOrd` that compares payloads of the same variants.
+///
+/// Unlike the std derive, values of different variants are unordered by default, i.e.
+/// `partial_cmp` returns `None`, which is consistent with [`DelegatePartialEq`]. With the
+/// `#[partial_ord(variant_order)]` attribute on the enum, they are ordered by the declaration order
+/// of the variants instead. Variants marked with `#[partial_ord(skip)]` are unordered with
+/// everything, including the values of the same variant:
+///
+/// ```
+/// use all_the_same::{DelegatePartialEq, DelegatePartialOrd};
+///
+/// #[derive(DelegatePartialEq, DelegatePartialOrd)]
+/// enum Version {
+///     Number(u32),
+///     Name(String),
+/// }
+///
+/// #[derive(DelegatePartialEq, DelegatePartialOrd)]
+/// #[partial_ord(variant_order)]
+/// enum Priority {
+///     Low(u8),
+///     High(u8),
+/// }
+///
+/// assert!(Version::Number(1) < Version::Number(2));
+/// assert!(Version::Name("beta".into()) > Version::Name("alpha".into()));
+/// assert_eq!(Version::Number(1).partial_cmp(&Version::Name("alpha".into())), None);
+///
+/// assert!(Priority::Low(10) < Priority::High(1));
+/// ```
+#[proc_macro_derive(DelegatePartialOrd, attributes(partial_ord, derive_coverage))]
+pub fn derive_delegate_partial_ord(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    partial_ord::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `Hash` that hashes the variant's discriminant and the payload.
+///
+/// Similarly to [`DelegatePartialEq`], variants marked with `#[hash(skip)]` only hash the
+/// discriminant:
+///
+/// ```
+/// use all_the_same::{DelegateHash, DelegatePartialEq};
+/// use std::collections::hash_map::RandomState;
+/// use std::hash::BuildHasher;
+/// use std::io::{self, Stdin};
+///
+/// #[derive(DelegateHash, DelegatePartialEq)]
+/// enum Source {
+///     Bytes(Vec<u8>),
+///     Text(String),
+///
+///     #[hash(skip)]
+///     #[partial_eq(skip)]
+///     Stdin(Stdin),
+/// }
+///
+/// let state = RandomState::new();
+///
+/// assert_eq!(
+///     state.hash_one(Source::Text("foo".into())),
+///     state.hash_one(Source::Text("foo".into())),
+/// );
+///
+/// assert_eq!(
+///     state.hash_one(Source::Stdin(io::stdin())),
+///     state.hash_one(Source::Stdin(io::stdin())),
+/// );
+/// ```
+///
+/// The skipped variants never compare equal with `#[partial_eq(skip)]`, so such enums can't
+/// implement `Eq` and be used as the keys of hash maps.
+#[proc_macro_derive(DelegateHash, attributes(hash, derive_coverage))]
+pub fn derive_delegate_hash(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    hash::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives binary operators (`Add`, `Sub`, `Mul`, `Div`, `Rem`, `BitAnd`, `BitOr`, `BitXor`,
+/// `Shl` and `Shr`) and their compound assignment counterparts (`AddAssign`, etc.) that apply the
+/// operator to the payloads of the same variant.
+///
+/// The operators are listed in the `#[ops(...)]` attribute. Operands of different variants cause
+/// a panic:
+///
+/// ```should_panic
+/// use all_the_same::DelegateOps;
+///
+/// #[derive(DelegateOps, Debug, PartialEq)]
+/// #[ops(Add, Sub, AddAssign)]
+/// enum Length {
+///     Meters(f64),
+///     Feet(f64),
+/// }
+///
+/// assert_eq!(Length::Meters(1.0) + Length::Meters(2.0), Length::Meters(3.0));
+/// assert_eq!(Length::Feet(3.0) - Length::Feet(1.0), Length::Feet(2.0));
+///
+/// let mut length = Length::Feet(1.0);
+///
+/// length += Length::Feet(2.0);
+///
+/// assert_eq!(length, Length::Feet(3.0));
+///
+/// // panics: operands of `Length::add` have different variants
+/// let _ = Length::Meters(1.0) + Length::Feet(1.0);
+/// ```
+///
+/// Instead, the result for the operands of different variants can be specified with
+/// `mismatch = expr`, where the operands are available as `lhs` and `rhs`. For the compound
+/// assignment operators `lhs` is a mutable reference and the result is assigned to it:
+///
+/// ```
+/// use all_the_same::DelegateOps;
+///
+/// #[derive(DelegateOps, Debug, PartialEq)]
+/// #[ops(Add, AddAssign, mismatch = Length::Meters(lhs.meters() + rhs.meters()))]
+/// enum Length {
+///     Meters(f64),
+///     Feet(f64),
+/// }
+///
+/// impl Length {
+///     fn meters(&self) -> f64 {
+///         match self {
+///             Length::Meters(m) => *m,
+///             Length::Feet(ft) => ft * 0.3048,
+///         }
+///     }
+/// }
+///
+/// assert_eq!(Length::Feet(1.0) + Length::Feet(2.0), Length::Feet(3.0));
+/// assert_eq!(Length::Meters(1.0) + Length::Feet(10.0), Length::Meters(4.048));
+///
+/// let mut length = Length::Feet(10.0);
+///
+/// length += Length::Meters(1.0);
+///
+/// assert_eq!(length, Length::Meters(4.048));
+/// ```
+///
+/// The unary operators, `Neg` and `Not`, are derived the same way. `Index<Idx, Output = Type>`
+/// and `IndexMut` index the payload, that has to have the same output type for every variant:
+///
+/// ```
+/// use all_the_same::DelegateOps;
+///
+/// #[derive(DelegateOps, Debug, PartialEq)]
+/// #[ops(Index<usize, Output = i32>, IndexMut)]
+/// enum Samples {
+///     Vec(Vec<i32>),
+///     Array([i32; 2]),
+/// }
+///
+/// let mut samples = Samples::Array([1, 2]);
+///
+/// samples[0] = 3;
+///
+/// assert_eq!(samples[0], 3);
+/// assert_eq!(Samples::Vec(vec![1, 2])[1], 2);
+/// ```
+#[cfg(feature = "ops")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ops")))]
+#[proc_macro_derive(DelegateOps, attributes(ops, derive_coverage))]
+pub fn derive_delegate_ops(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    ops::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives the `VARIANT_COUNT` constant and the `const fn index(&self)` method, that returns the
+/// position of the variant.
+///
+/// Indices don't depend on the discriminants and are always less than `VARIANT_COUNT`, so they
+/// can be used for the per-variant arrays. Variants disabled by `#[cfg]` are not counted:
+///
+/// ```
+/// use all_the_same::VariantIndex;
+///
+/// #[derive(VariantIndex)]
+/// #[repr(u8)]
+/// enum Backend {
+///     Memory(Vec<u8>) = 10,
+///     #[cfg(any())]
+///     Disk(std::fs::File),
+///     Network(String),
+/// }
+///
+/// let mut calls = [0; Backend::VARIANT_COUNT];
+///
+/// calls[Backend::Network(String::new()).index()] += 1;
+///
+/// assert_eq!(calls, [0, 1]);
+/// ```
+#[proc_macro_derive(VariantIndex, attributes(derive_coverage))]
+pub fn derive_variant_index(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    variant_index::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives [`arbitrary::Arbitrary`](https://docs.rs/arbitrary) that picks one of the variants
+/// and constructs its payload with the payload's own `Arbitrary` implementation.
+///
+/// Only the variants that are compiled in can be picked, so the delegated trait implementations
+/// can be fuzzed across all the backends enabled in the build:
+///
+/// ```
+/// use all_the_same::DelegateArbitrary;
+/// use arbitrary::{Arbitrary, Unstructured};
+///
+/// #[derive(DelegateArbitrary)]
+/// enum Number {
+///     I32(i32),
+///     F64(f64),
+///
+///     #[cfg(test)]
+///     Mock(u8),
+/// }
+///
+/// let mut u = Unstructured::new(&[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+///
+/// assert!(Number::arbitrary(&mut u).is_ok());
+/// ```
+#[proc_macro_derive(DelegateArbitrary, attributes(derive_coverage))]
+pub fn derive_delegate_arbitrary(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    arbitrary::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `std::io::Read` that delegates to the payload. See [`DelegateWrite`].
+#[proc_macro_derive(DelegateRead, attributes(derive_coverage))]
+pub fn derive_delegate_read(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    io::expand(&input, io::IoTrait::Read)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `std::io::Write` that delegates to the payload.
+///
+/// Along with the required methods, the provided ones, like `write_vectored` and `write_all`,
+/// are delegated too, so the payloads' own implementations of them are used. The derives for the
+/// other std I/O traits work the same way:
+///
+/// ```
+/// use all_the_same::{DelegateRead, DelegateSeek, DelegateWrite};
+/// use std::fs::File;
+/// use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+///
+/// #[derive(DelegateRead, DelegateWrite, DelegateSeek)]
+/// enum Storage {
+///     File(File),
+///     Memory(Cursor<Vec<u8>>),
+///     #[cfg(feature = "unsupported")]
+///     Remote(std::net::TcpStream),
+/// }
+///
+/// let mut storage = Storage::Memory(Cursor::new(Vec::new()));
+///
+/// storage.write_all(b"hello").unwrap();
+/// storage.seek(SeekFrom::Start(1)).unwrap();
+///
+/// let mut text = String::new();
+///
+/// storage.read_to_string(&mut text).unwrap();
+///
+/// assert_eq!(text, "ello");
+/// ```
+#[proc_macro_derive(DelegateWrite, attributes(derive_coverage))]
+pub fn derive_delegate_write(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    io::expand(&input, io::IoTrait::Write)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `std::io::Seek` that delegates to the payload. See [`DelegateWrite`].
+#[proc_macro_derive(DelegateSeek, attributes(derive_coverage))]
+pub fn derive_delegate_seek(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    io::expand(&input, io::IoTrait::Seek)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `std::io::BufRead` that delegates to the payload. See [`DelegateWrite`].
+#[proc_macro_derive(DelegateBufRead, attributes(derive_coverage))]
+pub fn derive_delegate_buf_read(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    io::expand(&input, io::IoTrait::BufRead)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `tokio::io::AsyncRead` that delegates to the payload. See [`DelegateAsyncWrite`].
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+#[proc_macro_derive(DelegateAsyncRead, attributes(derive_coverage))]
+pub fn derive_delegate_async_read(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    io::expand(&input, io::IoTrait::AsyncRead)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `tokio::io::AsyncWrite` that delegates to the payload, including the vectored writes.
+///
+/// The pinned receiver is projected to the pinned payload, so the payloads have to be `Unpin`,
+/// like the boxed trait objects or tokio's own streams, unless the enum derives [`PinProject`]
+/// with the `#[pin_project]` attribute. The derives for the other tokio I/O traits work the same
+/// way and require the `tokio` feature too:
+///
+/// ```
+/// use all_the_same::{DelegateAsyncRead, DelegateAsyncWrite};
+/// use tokio::io::{AsyncRead, AsyncWrite};
+/// use tokio::net::TcpStream;
+///
+/// #[derive(DelegateAsyncRead, DelegateAsyncWrite)]
+/// enum Stream {
+///     Tcp(TcpStream),
+///     #[cfg(unix)]
+///     Unix(tokio::net::UnixStream),
+///     Custom(Box<dyn Io + Unpin + Send>),
+/// }
+///
+/// trait Io: AsyncRead + AsyncWrite {}
+///
+/// impl<T: AsyncRead + AsyncWrite> Io for T {}
+///
+/// fn assert_io<T: AsyncRead + AsyncWrite + Unpin>() {}
+///
+/// assert_io::<Stream>();
+/// ```
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+#[proc_macro_derive(DelegateAsyncWrite, attributes(derive_coverage))]
+pub fn derive_delegate_async_write(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    io::expand(&input, io::IoTrait::AsyncWrite)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `tokio::io::AsyncSeek` that delegates to the payload. See [`DelegateAsyncWrite`].
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+#[proc_macro_derive(DelegateAsyncSeek, attributes(derive_coverage))]
+pub fn derive_delegate_async_seek(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    io::expand(&input, io::IoTrait::AsyncSeek)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `tokio::io::AsyncBufRead` that delegates to the payload. See [`DelegateAsyncWrite`].
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+#[proc_macro_derive(DelegateAsyncBufRead, attributes(derive_coverage))]
+pub fn derive_delegate_async_buf_read(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    io::expand(&input, io::IoTrait::AsyncBufRead)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `Iterator` that delegates to the payload.
+///
+/// The `Item` type is taken from the payload of the first variant without `#[cfg]`, the other
+/// payloads have to yield the same items. Besides `next`, the methods that the payloads can
+/// implement more efficiently, like `size_hint`, `nth` and `fold`, are forwarded as well. This
+/// allows returning one of several iterators without boxing them:
+///
+/// ```
+/// use all_the_same::DelegateIterator;
+/// use std::iter::{Empty, Rev};
+/// use std::ops::Range;
+///
+/// #[derive(DelegateIterator)]
+/// #[iterator(double_ended, exact_size, fused)]
+/// enum Indices {
+///     Forward(Range<usize>),
+///     Backward(Rev<Range<usize>>),
+///     None(Empty<usize>),
+/// }
+///
+/// fn indices(len: usize, reverse: bool) -> Indices {
+///     match (len, reverse) {
+///         (0, _) => Indices::None(std::iter::empty()),
+///         (len, false) => Indices::Forward(0..len),
+///         (len, true) => Indices::Backward((0..len).rev()),
+///     }
+/// }
+///
+/// assert_eq!(indices(3, true).collect::<Vec<_>>(), [2, 1, 0]);
+/// assert_eq!(indices(3, false).rev().collect::<Vec<_>>(), [2, 1, 0]);
+/// assert_eq!(indices(3, false).len(), 3);
+/// assert_eq!(indices(0, false).next(), None);
+/// ```
+///
+/// `DoubleEndedIterator`, `ExactSizeIterator` and `FusedIterator` are implemented with the
+/// `double_ended`, `exact_size` and `fused` options of the `#[iterator(..)]` attribute, and
+/// require all the payloads to implement them.
+#[proc_macro_derive(DelegateIterator, attributes(iterator, derive_coverage))]
+pub fn derive_delegate_iterator(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    iter::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `Future` that polls the payload.
+///
+/// The `Output` type is taken from the payload of the first variant without `#[cfg]`, the other
+/// payloads have to have the same output. Like with the other async derives, the payloads have
+/// to be `Unpin` and are pinned with `Pin::new`, so the impl has no `unsafe` code:
+///
+/// ```
+/// #![forbid(unsafe_code)]
+///
+/// use all_the_same::DelegateFuture;
+/// use std::future::{self, Future, Pending, Ready};
+/// use std::pin::pin;
+/// use std::task::{Context, Poll, Waker};
+///
+/// #[derive(DelegateFuture)]
+/// enum Answer {
+///     Ready(Ready<u32>),
+///     Pending(Pending<u32>),
+/// }
+///
+/// let answer = pin!(Answer::Ready(future::ready(42)));
+/// let mut cx = Context::from_waker(Waker::noop());
+///
+/// assert_eq!(answer.poll(&mut cx), Poll::Ready(42));
+/// ```
+///
+/// To hold the futures that are not `Unpin`, e.g. of `async fn`s, the pinned enum has to be
+/// projected to the pinned payload, like with the `pin-project` crate, which needs `unsafe` code.
+/// It's opted into with `#[future(unsafe_project)]`, or by deriving [`PinProject`] with
+/// `#[pin_project(unsafe_project)]`, so the projection generated by it is used. The enum can't
+/// implement `Drop` then, and is only `Unpin` if all its payloads are:
+///
+/// ```
+/// use all_the_same::DelegateFuture;
+/// use std::future::{self, Future, Ready};
+/// use std::pin::pin;
+/// use std::task::{Context, Poll, Waker};
+///
+/// async fn compute(n: u32) -> u32 {
+///     future::ready(n * 2).await
+/// }
+///
+/// #[derive(DelegateFuture)]
+/// #[future(unsafe_project)]
+/// enum Answer<F> {
+///     Cached(Ready<u32>),
+///     Computed(F),
+/// }
+///
+/// let answer = pin!(Answer::Computed(compute(21)));
+/// let mut cx = Context::from_waker(Waker::noop());
+///
+/// assert_eq!(answer.poll(&mut cx), Poll::Ready(42));
+/// ```
+///
+/// The `unsafe_code` lint of the crate applies to the generated code, and points at the option:
+///
+/// ```compile_fail
+/// #![forbid(unsafe_code)]
+///
+/// use all_the_same::DelegateFuture;
+/// use std::future::Ready;
+///
+/// #[derive(DelegateFuture)]
+/// #[future(unsafe_project)]
+/// enum Answer<F> {
+///     Cached(Ready<u32>),
+///     Computed(F),
+/// }
+/// ```
+#[proc_macro_derive(DelegateFuture, attributes(future, derive_coverage))]
+pub fn derive_delegate_future(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    future::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives the safe projection of the pinned enum to the pinned payload, like the `pin-project`
+/// crate does.
+///
+/// The generated `project` and `project_ref` methods return the `EnumProj` and `EnumProjRef`
+/// enums with the same variants, that hold the pinned payloads. The names can be changed with
+/// `#[pin_project(project = Name, project_ref = Name)]`. The enum can't implement `Drop`, and is
+/// only `Unpin` if all its payloads are.
+///
+/// The projection is `unsafe` code, that has to be opted into with
+/// `#[pin_project(unsafe_project)]`. The generated code doesn't allow the `unsafe_code` lint, so
+/// the derive can't be used in the crates that forbid it. With the `#[pin_project]` attribute, the
+/// other derives use the projection for the pinned
+/// receivers, so [`Forward`], [`Delegate`] and the async derives work with the payloads that are
+/// not `Unpin`, e.g. the futures of `async` blocks:
+///
+/// ```
+/// use all_the_same::{Forward, PinProject};
+/// use std::future::{self, Future, Ready};
+/// use std::pin::{pin, Pin};
+/// use std::task::{Context, Poll, Waker};
+///
+/// #[derive(PinProject, Forward)]
+/// #[pin_project(unsafe_project)]
+/// #[forward(fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<u32>)]
+/// enum Job<F: Future<Output = u32>> {
+///     Ready(Ready<u32>),
+///     Running(F),
+/// }
+///
+/// let mut job = pin!(Job::Running(async { 42 }));
+/// let mut cx = Context::from_waker(Waker::noop());
+///
+/// assert!(matches!(job.as_mut().project(), JobProj::Running(_)));
+/// assert_eq!(job.as_mut().poll(&mut cx), Poll::Ready(42));
+///
+/// let job = pin!(Job::<Ready<u32>>::Ready(future::ready(1)));
+///
+/// assert!(matches!(job.as_ref().project_ref(), JobProjRef::Ready(_)));
+/// ```
+#[proc_macro_derive(PinProject, attributes(pin_project, derive_coverage))]
+pub fn derive_pin_project(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    pin::expand(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `futures_core::Stream` that delegates to the payload.
+///
+/// The `Item` type is taken from the payload of the first variant without `#[cfg]`, the other
+/// payloads have to yield the same items. The pinned receiver is projected to the pinned
+/// payload, so the payloads have to be `Unpin`, unless the enum derives [`PinProject`] with the
+/// `#[pin_project]` attribute. Requires the `futures` feature, and the crate that
+/// uses the derive needs to depend on `futures-core`:
+///
+/// ```
+/// use all_the_same::DelegateStream;
+/// use futures::executor::block_on;
+/// use futures::stream::{self, Iter, StreamExt};
+/// use std::vec::IntoIter;
+///
+/// #[derive(DelegateStream)]
+/// enum Numbers {
+///     Iter(Iter<IntoIter<u32>>),
+///     Boxed(stream::BoxStream<'static, u32>),
+/// }
+///
+/// let numbers = Numbers::Iter(stream::iter(vec![1, 2, 3]));
+///
+/// assert_eq!(block_on(numbers.collect::<Vec<_>>()), [1, 2, 3]);
+/// ```
+#[cfg(feature = "futures")]
+#[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
+#[proc_macro_derive(DelegateStream, attributes(derive_coverage))]
+pub fn derive_delegate_stream(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    stream::expand_stream(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+/// Derives `futures_sink::Sink` that delegates to the payload.
+///
+/// The type of the items is specified with the `#[sink(Type)]` attribute. The `Error` type is
+/// taken from the payload of the first variant without `#[cfg]`, like in [`DelegateStream`].
+/// Requires the `futures` feature, and the crate that uses the derive needs to depend on
+/// `futures-sink`:
+///
+/// ```
+/// use all_the_same::DelegateSink;
+/// use futures::channel::mpsc;
+/// use futures::executor::block_on;
+/// use futures::sink::SinkExt;
+/// use futures::stream::StreamExt;
+///
+/// #[derive(DelegateSink)]
+/// #[sink(u32)]
+/// enum Sender {
+///     Bounded(mpsc::Sender<u32>),
+///     Unbounded(mpsc::UnboundedSender<u32>),
+/// }
+///
+/// let (tx, rx) = mpsc::unbounded();
+/// let mut sender = Sender::Unbounded(tx);
+///
+/// block_on(sender.send(42)).unwrap();
+///
+/// drop(sender);
+///
+/// assert_eq!(block_on(rx.collect::<Vec<_>>()), [42]);
+/// ```
+#[cfg(feature = "futures")]
+#[cfg_attr(docsrs, doc(cfg(feature = "futures")))]
+#[proc_macro_derive(DelegateSink, attributes(sink, derive_coverage))]
+pub fn derive_delegate_sink(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    stream::expand_sink(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
 }
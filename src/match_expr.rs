@@ -0,0 +1,2645 @@
+use crate::diagnostics;
+use crate::utils::{snake_case, tokens_eq};
+use crate::variants::{check_duplicates, parse_variant_list, ListVariant};
+use proc_macro2::{Delimiter, Group, Spacing, Span, TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use std::{iter, mem};
+use syn::buffer::Cursor;
+use syn::parse::{Parse, ParseBuffer, ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::token::{Brace, Bracket, Comma, Paren};
+use syn::visit::{self, Visit};
+use syn::visit_mut::{self, VisitMut};
+use syn::{
+    braced, bracketed, parenthesized, parse_quote, Attribute, Expr, ExprAsync, ExprBlock,
+    ExprBreak, ExprClosure, ExprContinue, ExprForLoop, ExprGroup, ExprLit, ExprLoop, ExprParen,
+    ExprReturn, ExprTry, ExprUnsafe, ExprWhile, Ident, Item, Label, Lifetime, Lit, Meta,
+    NestedMeta, Pat, PatIdent, Path, PathArguments, PathSegment, Token, Type, TypeParamBound,
+};
+
+#[derive(Clone)]
+struct Variant {
+    attrs: Vec<Attribute>,
+    /// The variant is marked with `#[cold]`, so its arm is hinted as unlikely.
+    cold: bool,
+    /// `*Variant` derefs the payload before the shared body, e.g. for `Box<T>` payloads, once
+    /// for every `*`. It's turned into the adapter, once the payload binding is known.
+    derefs: Vec<Token!(*)>,
+    name: Ident,
+    adapter: Option<Expr>,
+    constant: Option<Expr>,
+    arm_expr: Option<Expr>,
+}
+
+impl ListVariant for Variant {
+    fn name(&self) -> &Ident {
+        &self.name
+    }
+
+    fn attrs(&self) -> &[Attribute] {
+        &self.attrs
+    }
+
+    fn add_group_attrs(&mut self, attrs: &[Attribute]) {
+        let mut attrs = attrs.to_vec();
+        let attrs_len = attrs.len();
+
+        attrs.retain(|attr| !is_cold(attr));
+        self.cold |= attrs.len() < attrs_len;
+        self.attrs.splice(0..0, attrs);
+    }
+}
+
+impl Parse for Variant {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut attrs = input.call(Attribute::parse_outer)?;
+        let attrs_len = attrs.len();
+
+        // `#[cold]` isn't allowed on match arms, so it's not copied to them.
+        attrs.retain(|attr| !is_cold(attr));
+
+        Ok(Variant {
+            cold: attrs.len() < attrs_len,
+            attrs,
+            derefs: {
+                let mut derefs = Vec::new();
+
+                while input.peek(Token!(*)) {
+                    derefs.push(input.parse()?);
+                }
+
+                derefs
+            },
+            name: input.parse()?,
+            adapter: {
+                // `Variant(expr)` rebinds the payload before the shared arm body.
+                if input.peek(Paren) {
+                    let adapter_content;
+
+                    parenthesized!(adapter_content in input);
+
+                    Some(adapter_content.parse()?)
+                } else {
+                    None
+                }
+            },
+            constant: {
+                if input.peek(Token!(=)) && !input.peek(Token!(=>)) {
+                    input.parse::<Token!(=)>()?;
+
+                    Some(input.parse()?)
+                } else {
+                    None
+                }
+            },
+            arm_expr: {
+                if input.peek(Token!(=>)) {
+                    input.parse::<Token!(=>)>()?;
+
+                    Some(input.parse()?)
+                } else {
+                    None
+                }
+            },
+        })
+    }
+}
+
+#[derive(Default)]
+struct Options {
+    /// Name of the enclosing function, that the shims of the variants are named after.
+    shims: Option<Ident>,
+    /// Path to the `tracing` crate, that the events of `#[trace]` are emitted with.
+    trace: Option<Path>,
+    /// Every arm body is wrapped in a boxed future, so the arms have the same type.
+    boxed_async: bool,
+    /// The variants share a single arm with an or-pattern, if they don't need their own.
+    or_patterns: bool,
+    /// The arm body is emitted once, in a closure that takes the payload coerced to the type.
+    helper: bool,
+    /// The arm body copies point at the variant they are expanded for, so it's clear which one
+    /// doesn't compile.
+    variant_spans: bool,
+    /// Every variant of the enum has to be in the variant lists, that requires `#[variants]` on
+    /// the enum.
+    strict: bool,
+    /// The match gets a panicking fallback, if it has none, for `#[non_exhaustive]` enums.
+    non_exhaustive: bool,
+    /// Lint attributes, like `#[allow(..)]`, that are applied to the generated match.
+    lint_attrs: Vec<Attribute>,
+    /// The variants are dispatched through a table of function pointers, that take the listed
+    /// variables as arguments.
+    table: Option<Vec<Ident>>,
+    coerce: Option<Type>,
+    debug_assert: Option<Punctuated<Expr, Comma>>,
+    /// The expansion is printed at compile time, to debug the macro invocation.
+    debug: Option<Span>,
+    /// Name of the macro that constructs the matched variant, `same` by default.
+    same: Option<Ident>,
+    /// The expansion is usable in the const contexts, the options that aren't are rejected.
+    const_context: Option<Attribute>,
+}
+
+impl Options {
+    fn same_name(&self) -> Ident {
+        self.same
+            .clone()
+            .unwrap_or_else(|| Ident::new("same", Span::call_site()))
+    }
+}
+
+impl Parse for Options {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut options = Options::default();
+        let mut or_patterns_attr = None;
+        let mut helper = None;
+
+        for attr in input.call(Attribute::parse_outer)? {
+            let is_lint = ["allow", "expect", "warn", "deny", "forbid"]
+                .iter()
+                .any(|lint| attr.path.is_ident(lint));
+
+            if is_lint {
+                options.lint_attrs.push(attr);
+            } else if attr.path.is_ident("shims") {
+                if attr.tokens.is_empty() {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "expected the name of the enclosing function, e.g. `#[shims(poll_write)]`",
+                    ));
+                }
+
+                options.shims = Some(attr.parse_args()?);
+            } else if attr.path.is_ident("trace") {
+                options.trace = Some(if attr.tokens.is_empty() {
+                    parse_quote!(::tracing)
+                } else {
+                    attr.parse_args_with(|input: ParseStream| {
+                        let key = input.parse::<Ident>()?;
+
+                        if key != "tracing" {
+                            return Err(syn::Error::new_spanned(key, "expected `tracing = path`"));
+                        }
+
+                        input.parse::<Token!(=)>()?;
+                        input.call(Path::parse_mod_style)
+                    })?
+                });
+            } else if attr.path.is_ident("const_context") && attr.tokens.is_empty() {
+                options.const_context = Some(attr);
+            } else if attr.path.is_ident("async") && attr.tokens.is_empty() {
+                options.boxed_async = true;
+            } else if attr.path.is_ident("variant_spans") && attr.tokens.is_empty() {
+                options.variant_spans = true;
+            } else if attr.path.is_ident("strict") && attr.tokens.is_empty() {
+                options.strict = true;
+            } else if attr.path.is_ident("debug") && attr.tokens.is_empty() {
+                options.debug = Some(attr.path.get_ident().unwrap().span());
+            } else if attr.path.is_ident("non_exhaustive") && attr.tokens.is_empty() {
+                options.non_exhaustive = true;
+            } else if attr.path.is_ident("or_patterns") && attr.tokens.is_empty() {
+                options.or_patterns = true;
+                or_patterns_attr = Some(attr);
+            } else if attr.path.is_ident("table") {
+                options.table = Some(if attr.tokens.is_empty() {
+                    Vec::new()
+                } else {
+                    attr.parse_args_with(Punctuated::<Ident, Comma>::parse_terminated)?
+                        .into_iter()
+                        .collect()
+                });
+            } else if attr.path.is_ident("helper") {
+                helper = Some((attr.parse_args()?, attr));
+            } else if attr.path.is_ident("coerce") {
+                options.coerce = Some(attr.parse_args()?);
+            } else if attr.path.is_ident("same") {
+                options.same = Some(attr.parse_args()?);
+            } else if attr.path.is_ident("debug_assert") {
+                let args = attr.parse_args_with(Punctuated::parse_terminated)?;
+
+                if args.is_empty() {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "expected an assertion, e.g. `#[debug_assert(cond)]`",
+                    ));
+                }
+
+                options.debug_assert = Some(args);
+            } else {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "unknown option, expected one of: `#[shims(..)]`, `#[trace]`, `#[async]`, \
+                     `#[const_context]`, `#[variant_spans]`, `#[strict]`, `#[non_exhaustive]`, \
+                     `#[or_patterns]`, `#[helper(Type)]`, `#[table(args, ...)]`, \
+                     `#[coerce(Type)]`, `#[same(name)]`, `#[debug_assert(cond, ...)]`, `#[debug]` \
+                     or a lint attribute, like `#[allow(..)]`",
+                ));
+            }
+        }
+
+        // The payload is passed to the helper the same way as it's coerced.
+        if let Some((ty, attr)) = helper {
+            if options.coerce.is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`#[helper(Type)]` already coerces the payload to the type",
+                ));
+            }
+
+            options.helper = true;
+            options.coerce = Some(ty);
+        }
+
+        if let Some(attr) = or_patterns_attr {
+            if options.shims.is_some() || options.trace.is_some() || options.debug_assert.is_some()
+            {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`#[or_patterns]` can't be combined with `#[shims(..)]`, `#[trace]` and \
+                     `#[debug_assert(..)]`, that need an arm for every variant",
+                ));
+            }
+        }
+
+        if let Some(attr) = &options.const_context {
+            options.check_const(attr)?;
+        }
+
+        Ok(options)
+    }
+}
+
+impl Options {
+    /// Rejects the options that call non-`const` functions, with `#[const_context]`.
+    fn check_const(&self, attr: &Attribute) -> syn::Result<()> {
+        let non_const = [
+            (self.shims.is_some(), "`#[shims(..)]`"),
+            (self.trace.is_some(), "`#[trace]`"),
+            (self.boxed_async, "`#[async]`"),
+            (self.table.is_some(), "`#[table(..)]`"),
+            (self.helper, "`#[helper(..)]`"),
+        ];
+
+        if let Some((_, name)) = non_const.iter().find(|(enabled, _)| *enabled) {
+            return Err(syn::Error::new_spanned(
+                attr,
+                format!(
+                    "`#[const_context]` can't be combined with {}, that isn't const",
+                    name
+                ),
+            ));
+        }
+
+        // References are coerced, the rest is converted with `Into`.
+        if let Some(ty) = &self.coerce {
+            if !matches!(ty, Type::Reference(_) | Type::Ptr(_)) {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "`#[const_context]` only supports `#[coerce(..)]` to references, the owned \
+                     payloads are converted with `Into`, that isn't const",
+                ));
+            }
+        }
+
+        if let Some(assertion) = &self.debug_assert {
+            if assertion.len() > 2 || assertion.iter().nth(1).is_some_and(|msg| !is_str_lit(msg)) {
+                return Err(syn::Error::new_spanned(
+                    assertion,
+                    "`#[const_context]` only supports `#[debug_assert(..)]` with a string \
+                     literal message, the formatting isn't const",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) struct Args {
+    options: Options,
+    expr: Expr,
+    ty: Option<Type>,
+    arms: Vec<MatchArm>,
+    fallback: Option<Fallback>,
+    /// Invocation of the macro that provides the variants of `[*]` or `[..SET]`, if they are
+    /// unknown yet.
+    lookup: Option<TokenStream>,
+    /// Bindings of the `let ... else` form, that are assigned the payload returned by the match.
+    let_bindings: Option<TokenStream>,
+}
+
+impl Args {
+    /// Number of the matched values, if the scrutinee is a tuple, e.g. `(a, b)`. Such values are
+    /// matched together, against the same variant.
+    fn zipped(&self) -> Option<usize> {
+        match &self.expr {
+            Expr::Tuple(tuple) => Some(tuple.elems.len()),
+            _ => None,
+        }
+    }
+
+    /// Rejects the parts of the match, that can't be dispatched through the table.
+    fn check_table(&self) -> syn::Result<()> {
+        if self.zipped().is_some() {
+            return Err(syn::Error::new_spanned(
+                &self.expr,
+                "`#[table]` doesn't support matching several values",
+            ));
+        }
+
+        for arm in &self.arms {
+            let arm = match arm {
+                MatchArm::Shared(arm) => arm,
+                MatchArm::Tuple(arm) => {
+                    return Err(syn::Error::new_spanned(
+                        &arm.elems[0].enum_path,
+                        "`#[table]` only supports the variant list arms and the fallback",
+                    ));
+                }
+                MatchArm::Plain(arm) => {
+                    return Err(syn::Error::new_spanned(
+                        &arm.pat,
+                        "`#[table]` only supports the variant list arms and the fallback",
+                    ));
+                }
+            };
+
+            if let Some(guard) = &arm.guard {
+                return Err(syn::Error::new_spanned(
+                    guard,
+                    "`#[table]` doesn't support match guards",
+                ));
+            }
+
+            if let Some(variant) = arm
+                .variants
+                .iter()
+                .find(|variant| !variant.attrs.is_empty())
+            {
+                return Err(syn::Error::new_spanned(
+                    &variant.name,
+                    "`#[table]` doesn't support attributes on the variants",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parenthesizes the expressions spliced from the `macro_rules!` metavariables, see `Ungroup`.
+    fn ungroup(&mut self) {
+        let mut ungroup = Ungroup;
+
+        ungroup.visit_expr_mut(&mut self.expr);
+
+        for expr in self.options.debug_assert.iter_mut().flatten() {
+            ungroup.visit_expr_mut(expr);
+        }
+
+        for arm in &mut self.arms {
+            match arm {
+                MatchArm::Shared(arm) => arm.ungroup(),
+                MatchArm::Tuple(arm) => {
+                    for elem in &mut arm.elems {
+                        elem.ungroup();
+                    }
+
+                    if let Some(guard) = &mut arm.guard {
+                        ungroup.visit_expr_mut(guard);
+                    }
+
+                    ungroup.visit_expr_mut(&mut arm.arm_expr);
+                }
+                MatchArm::Plain(arm) => ungroup.visit_arm_mut(arm),
+            }
+        }
+
+        if let Some(fallback) = &mut self.fallback {
+            ungroup.visit_expr_mut(&mut fallback.arm_expr);
+        }
+    }
+
+    fn shared_arms(&self) -> impl Iterator<Item = &Arm> {
+        self.arms.iter().filter_map(|arm| match arm {
+            MatchArm::Shared(arm) => Some(&**arm),
+            MatchArm::Tuple(_) | MatchArm::Plain(_) => None,
+        })
+    }
+}
+
+/// `Enum::[A, B](inner) => expr` arm, that is copy-pasted for every listed variant.
+#[derive(Clone)]
+struct Arm {
+    whole_name: Option<Ident>,
+    enum_path: Path,
+    glob: Option<Token!(*)>,
+    set: Option<Path>,
+    excluded: Vec<Ident>,
+    variants: Punctuated<Variant, Comma>,
+    field: Option<Ident>,
+    payload: Vec<Pat>,
+    /// Payload bindings marked with `pin`, e.g. `s` in `Enum::[A, B](pin s)`, that are rebound as
+    /// pinned in the arm body.
+    pinned: Vec<PatIdent>,
+    /// Binding of the per-variant constants, e.g. `n` in `Enum::[A = 1, B = 2](v, n)`.
+    constant: Option<Pat>,
+    /// Binding of the variant name, e.g. `name` in `Enum::[A, B](v) as name`.
+    variant_name: Option<Ident>,
+    /// Trait bounds of the payload, e.g. `Display` in `Enum::[A, B](v): Display`, that are
+    /// asserted for every variant.
+    bounds: Option<Punctuated<TypeParamBound, Token!(+)>>,
+    guard: Option<Expr>,
+    /// `=> try expr` converts the error of the body with `Into`.
+    try_token: Option<Token!(try)>,
+    unsafety: Option<Token!(unsafe)>,
+    arm_expr: Option<Expr>,
+}
+
+impl Arm {
+    fn ungroup(&mut self) {
+        let mut ungroup = Ungroup;
+
+        for variant in &mut self.variants {
+            let exprs = [
+                &mut variant.adapter,
+                &mut variant.constant,
+                &mut variant.arm_expr,
+            ];
+
+            for expr in exprs.into_iter().flatten() {
+                ungroup.visit_expr_mut(expr);
+            }
+        }
+
+        for expr in [&mut self.guard, &mut self.arm_expr].into_iter().flatten() {
+            ungroup.visit_expr_mut(expr);
+        }
+    }
+
+    /// Parses the `Enum::[A, B](inner)` part of the arm.
+    fn parse_pattern(input: ParseStream) -> syn::Result<Self> {
+        let mut glob = None;
+        let mut set = None;
+        let mut excluded = Vec::new();
+        let mut payload = Vec::new();
+        let mut pinned = Vec::new();
+
+        let arm = Arm {
+            whole_name: {
+                if input.peek2(Token!(@)) {
+                    let whole_name = input.parse()?;
+
+                    input.parse::<Token!(@)>()?;
+
+                    Some(whole_name)
+                } else {
+                    None
+                }
+            },
+            enum_path: input.call(parse_enum_path)?,
+            variants: {
+                let variants_list_content;
+
+                bracketed!(variants_list_content in input);
+
+                // The variants of `[*]` and `[..SET]` are filled in once they are looked up.
+                if is_glob(&variants_list_content) {
+                    glob = Some(variants_list_content.parse::<Token!(*)>()?);
+                } else if variants_list_content.peek(Token!(..)) {
+                    variants_list_content.parse::<Token!(..)>()?;
+
+                    set = Some(variants_list_content.call(Path::parse_mod_style)?);
+                }
+
+                if glob.is_some() || set.is_some() {
+                    if !variants_list_content.is_empty() {
+                        let except = variants_list_content.fork().parse::<Ident>();
+
+                        if !except.is_ok_and(|except| except == "except") {
+                            return Err(variants_list_content.error("expected `]` or `except`"));
+                        }
+
+                        variants_list_content.parse::<Ident>()?;
+
+                        let names = variants_list_content
+                            .call(Punctuated::<Ident, Comma>::parse_terminated)?;
+
+                        if names.is_empty() {
+                            return Err(variants_list_content.error("expected the variant names"));
+                        }
+
+                        excluded.extend(names);
+                    }
+
+                    Punctuated::new()
+                } else {
+                    variants_list_content.call(parse_variant_list)?
+                }
+            },
+            field: {
+                if input.peek(Brace) {
+                    let variant_field_content;
+
+                    braced!(variant_field_content in input);
+
+                    let field = variant_field_content.parse::<Ident>()?;
+
+                    // `{ field }` binds the field with its own name, like in regular patterns.
+                    payload.push(if variant_field_content.peek(Token!(:)) {
+                        variant_field_content.parse::<Token!(:)>()?;
+
+                        parse_payload_binding(&variant_field_content, &mut pinned)?
+                    } else {
+                        parse_quote!(#field)
+                    });
+
+                    variant_field_content.parse::<Option<Token!(,)>>()?;
+
+                    if !variant_field_content.is_empty() {
+                        return Err(
+                            variant_field_content.error("expected a single field of the variant")
+                        );
+                    }
+
+                    Some(field)
+                } else {
+                    None
+                }
+            },
+            payload: {
+                // Unit variants don't have a payload.
+                if payload.is_empty() && input.peek(Paren) {
+                    let variant_payload_content;
+
+                    parenthesized!(variant_payload_content in input);
+
+                    while !variant_payload_content.is_empty() {
+                        payload.push(parse_payload_binding(
+                            &variant_payload_content,
+                            &mut pinned,
+                        )?);
+
+                        if variant_payload_content.is_empty() {
+                            break;
+                        }
+
+                        variant_payload_content.parse::<Token!(,)>()?;
+                    }
+
+                    if payload.is_empty() {
+                        return Err(variant_payload_content.error("expected the payload binding"));
+                    }
+                }
+
+                payload
+            },
+            pinned,
+            constant: None,
+            variant_name: None,
+            bounds: None,
+            guard: None,
+            try_token: None,
+            unsafety: None,
+            arm_expr: None,
+            glob,
+            set,
+            excluded,
+        };
+
+        if arm.glob.is_some() && arm.enum_path.is_ident("Self") {
+            return Err(syn::Error::new_spanned(
+                &arm.enum_path,
+                "`[*]` requires the enum name instead of `Self`",
+            ));
+        }
+
+        Ok(arm)
+    }
+
+    /// Parses the optional `as name` binding of the variant name.
+    fn parse_variant_name(&mut self, input: ParseStream) -> syn::Result<()> {
+        if input.peek(Token!(as)) {
+            input.parse::<Token!(as)>()?;
+
+            self.variant_name = Some(input.parse()?);
+        }
+
+        Ok(())
+    }
+
+    /// Validates the variant list with the per-variant constants and adapters, and splits off
+    /// the binding of the constants from the payload.
+    fn check_per_variant(&mut self) -> syn::Result<()> {
+        check_duplicates(&self.variants)?;
+
+        if self
+            .variants
+            .iter()
+            .any(|variant| variant.constant.is_some())
+        {
+            let missing = self
+                .variants
+                .iter()
+                .find(|variant| variant.constant.is_none());
+
+            if let Some(variant) = missing {
+                return Err(syn::Error::new_spanned(
+                    &variant.name,
+                    "expected `= value`, other variants in the list have constants",
+                ));
+            }
+
+            if let Some(field) = &self.field {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "variant constants require a parenthesized payload, e.g. `(v, constant)`",
+                ));
+            }
+
+            // The last binding is the one for the constants.
+            self.constant = self.payload.pop();
+
+            if self.constant.is_none() {
+                return Err(syn::Error::new_spanned(
+                    &self.variants[0].constant,
+                    "expected the binding of the variant constants, e.g. `(v, constant)`",
+                ));
+            }
+        }
+
+        let inner_name = match &self.payload[..] {
+            [pat] => binding(pat).cloned(),
+            _ => None,
+        };
+
+        for variant in &self.variants {
+            let deref = match variant.derefs.first() {
+                Some(deref) => deref,
+                None => continue,
+            };
+
+            if variant.adapter.is_some() {
+                return Err(syn::Error::new_spanned(
+                    deref,
+                    "`*` can't be combined with the variant adapter, that can deref the payload \
+                     itself",
+                ));
+            }
+
+            if inner_name.is_none() {
+                return Err(syn::Error::new_spanned(
+                    deref,
+                    "`*` requires a single plain payload binding",
+                ));
+            }
+        }
+
+        let adapter = self
+            .variants
+            .iter()
+            .find_map(|variant| variant.adapter.as_ref());
+
+        if let Some(adapter) = adapter {
+            if inner_name.is_none() {
+                return Err(syn::Error::new_spanned(
+                    adapter,
+                    "variant adapters require a single plain payload binding",
+                ));
+            }
+        }
+
+        if let Some(inner_name) = &inner_name {
+            for variant in &mut self.variants {
+                if !variant.derefs.is_empty() {
+                    variant.adapter = Some(deref_adapter(inner_name, variant.derefs.len()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Like in a regular `match`, the comma after a block arm body is optional.
+    fn needs_comma(&self) -> bool {
+        !matches!(self.arm_expr, Some(Expr::Block(_)))
+    }
+
+    fn payload_pat(&self, payload: &[Pat]) -> TokenStream {
+        match &self.field {
+            Some(field) if is_shorthand(field, &payload[0]) => quote!({ #field, .. }),
+            Some(field) => {
+                let pat = &payload[0];
+
+                quote!({ #field: #pat, .. })
+            }
+            None if payload.is_empty() => quote!(),
+            None => quote!((#(#payload),*)),
+        }
+    }
+
+    /// Path of the macro that provides the variants of `[*]` or `[..SET]`.
+    fn lookup_macro(&self) -> Option<Path> {
+        if self.glob.is_some() {
+            Some(without_generics(&self.enum_path))
+        } else {
+            self.set.clone()
+        }
+    }
+
+    /// Checks if the arm starts with the `Enum::[` prefix, optionally bound with `name @`.
+    fn peek(input: ParseStream) -> bool {
+        let fork = input.fork();
+
+        if fork.peek2(Token!(@))
+            && (fork.parse::<Ident>().is_err() || fork.parse::<Token!(@)>().is_err())
+        {
+            return false;
+        }
+
+        if fork.parse::<Option<Token!(::)>>().is_err() {
+            return false;
+        }
+
+        while fork.parse::<PathSegment>().is_ok() && fork.parse::<Token!(::)>().is_ok() {
+            if fork.peek(Bracket) {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+/// `(Enum::[A, B](x), Other::[A, B](y)) => expr` arm, that matches the values of a tuple against
+/// the variants at the same positions of the lists.
+struct TupleArm {
+    elems: Vec<Arm>,
+    guard: Option<Expr>,
+    try_token: Option<Token!(try)>,
+    unsafety: Option<Token!(unsafe)>,
+    arm_expr: Expr,
+}
+
+impl TupleArm {
+    fn peek(input: ParseStream) -> bool {
+        let fork = input.fork();
+
+        parenthesized_content(&fork).is_ok_and(|content| Arm::peek(&content))
+    }
+}
+
+impl Parse for TupleArm {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let elems_content;
+
+        parenthesized!(elems_content in input);
+
+        let elems = elems_content.parse_terminated::<_, Comma>(Arm::parse_pattern)?;
+
+        let per_variant = elems
+            .iter()
+            .flat_map(|elem| &elem.variants)
+            .find(|variant| {
+                variant.adapter.is_some()
+                    || !variant.derefs.is_empty()
+                    || variant.constant.is_some()
+                    || variant.arm_expr.is_some()
+            });
+
+        if let Some(variant) = per_variant {
+            return Err(syn::Error::new_spanned(
+                &variant.name,
+                "per-variant adapters, constants and overrides are not supported in tuple arms",
+            ));
+        }
+
+        Ok(TupleArm {
+            elems: elems.into_iter().collect(),
+            guard: {
+                if input.peek(Token!(if)) {
+                    input.parse::<Token!(if)>()?;
+
+                    Some(input.parse()?)
+                } else {
+                    None
+                }
+            },
+            try_token: {
+                input.parse::<Token!(=>)>()?;
+
+                input.parse()?
+            },
+            unsafety: input.parse()?,
+            arm_expr: {
+                let arm_expr = input.parse()?;
+
+                NestedUnsafeCheck::check(&arm_expr);
+
+                arm_expr
+            },
+        })
+    }
+}
+
+fn parenthesized_content<'a>(input: ParseStream<'a>) -> syn::Result<ParseBuffer<'a>> {
+    let content;
+
+    parenthesized!(content in input);
+
+    Ok(content)
+}
+
+enum MatchArm {
+    Shared(Box<Arm>),
+    Tuple(Box<TupleArm>),
+    /// Regular `match` arm, that is emitted as is.
+    Plain(syn::Arm),
+}
+
+impl MatchArm {
+    fn needs_comma(&self) -> bool {
+        match self {
+            MatchArm::Shared(arm) => arm.needs_comma(),
+            MatchArm::Tuple(arm) => !matches!(arm.arm_expr, Expr::Block(_)),
+            // The regular arm parser consumes the comma itself.
+            MatchArm::Plain(_) => false,
+        }
+    }
+}
+
+impl Parse for MatchArm {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if Arm::peek(input) {
+            Ok(MatchArm::Shared(input.parse()?))
+        } else if TupleArm::peek(input) {
+            Ok(MatchArm::Tuple(input.parse()?))
+        } else {
+            Ok(MatchArm::Plain(input.parse()?))
+        }
+    }
+}
+
+struct Fallback {
+    pat: Pat,
+    arm_expr: Expr,
+}
+
+impl Fallback {
+    /// Both `else pat => expr` and the plain `_ => expr` or `name => expr` start the fallback arm.
+    fn peek(input: ParseStream) -> bool {
+        input.peek(Token!(else))
+            || input.peek(Token!(_))
+            || (input.peek(Ident) && input.peek2(Token!(=>)))
+    }
+}
+
+impl Parse for Fallback {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Option<Token!(else)>>()?;
+
+        Ok(Fallback {
+            pat: input.parse()?,
+            arm_expr: {
+                input.parse::<Token!(=>)>()?;
+
+                input.parse()?
+            },
+        })
+    }
+}
+
+impl Parse for Arm {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut arm = Arm::parse_pattern(input)?;
+
+        if input.peek(Token!(:)) {
+            let colon = input.parse::<Token!(:)>()?;
+
+            if !matches!(&arm.payload[..], [pat] if binding(pat).is_some()) {
+                return Err(syn::Error::new_spanned(
+                    colon,
+                    "payload bounds require a single payload binding",
+                ));
+            }
+
+            arm.bounds = Some(Punctuated::parse_separated_nonempty(input)?);
+        }
+
+        arm.parse_variant_name(input)?;
+
+        if input.peek(Token!(if)) {
+            input.parse::<Token!(if)>()?;
+
+            arm.guard = Some(input.parse()?);
+        }
+
+        // Without the arm body the arm just evaluates to the payload.
+        if input.peek(Token!(=>)) {
+            input.parse::<Token!(=>)>()?;
+
+            arm.try_token = input.parse()?;
+            arm.unsafety = input.parse()?;
+
+            let arm_expr = input.parse()?;
+
+            NestedUnsafeCheck::check(&arm_expr);
+
+            arm.arm_expr = Some(arm_expr);
+        }
+
+        if arm.arm_expr.is_none() {
+            if arm.payload.is_empty() && arm.variant_name.is_none() {
+                return Err(input.error("expected `=>`, arms for unit variants need a body"));
+            }
+
+            if let Some(pat) = arm.payload.iter().find(|pat| binding(pat).is_none()) {
+                return Err(syn::Error::new_spanned(
+                    pat,
+                    "arms without a body can only have plain bindings of the payload",
+                ));
+            }
+        }
+
+        arm.check_per_variant()?;
+
+        Ok(arm)
+    }
+}
+
+impl Args {
+    fn parse_match(options: Options, input: ParseStream) -> syn::Result<Self> {
+        let match_body_content;
+
+        Ok(Args {
+            options,
+            expr: {
+                input.parse::<Token!(match)>()?;
+
+                match input.step(|cursor| scrutinee_before_ty(*cursor))? {
+                    Some(scrutinee) => Expr::parse_without_eager_brace.parse2(scrutinee)?,
+                    None => Expr::parse_without_eager_brace(input)?,
+                }
+            },
+            ty: {
+                if input.peek(Token!(->)) {
+                    input.parse::<Token!(->)>()?;
+
+                    Some(input.parse()?)
+                } else {
+                    None
+                }
+            },
+            arms: {
+                braced!(match_body_content in input);
+
+                let mut arms = vec![match_body_content.parse::<MatchArm>()?];
+
+                loop {
+                    let comma = match_body_content.parse::<Option<Token!(,)>>()?;
+
+                    if match_body_content.is_empty() {
+                        break;
+                    }
+
+                    if comma.is_none() && arms.last().is_some_and(MatchArm::needs_comma) {
+                        return Err(match_body_content.error("expected `,`"));
+                    }
+
+                    if Fallback::peek(&match_body_content) {
+                        break;
+                    }
+
+                    arms.push(match_body_content.parse()?);
+                }
+
+                arms
+            },
+            fallback: {
+                let fallback = if Fallback::peek(&match_body_content) {
+                    Some(match_body_content.parse()?)
+                } else {
+                    None
+                };
+
+                match_body_content.parse::<Option<Token!(,)>>()?;
+
+                fallback
+            },
+            lookup: None,
+            let_bindings: None,
+        })
+    }
+
+    /// Parses `if let Enum::[A, B](v) = expr { ... } else { ... }` as a match with the fallback.
+    fn parse_if_let(options: Options, input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token!(if)>()?;
+        input.parse::<Token!(let)>()?;
+
+        let mut arm = Arm::parse_pattern(input)?;
+
+        arm.parse_variant_name(input)?;
+        input.parse::<Token!(=)>()?;
+
+        let expr = Expr::parse_without_eager_brace(input)?;
+        let arm_expr = Expr::Block(input.parse()?);
+
+        NestedUnsafeCheck::check(&arm_expr);
+
+        arm.arm_expr = Some(arm_expr);
+        arm.check_per_variant()?;
+
+        let else_expr = if input.peek(Token!(else)) {
+            input.parse::<Token!(else)>()?;
+
+            if input.peek(Token!(if)) {
+                Expr::If(input.parse()?)
+            } else {
+                Expr::Block(input.parse()?)
+            }
+        } else {
+            parse_quote!({})
+        };
+
+        Ok(Args {
+            options,
+            expr,
+            ty: None,
+            arms: vec![MatchArm::Shared(Box::new(arm))],
+            fallback: Some(Fallback {
+                pat: parse_quote!(_),
+                arm_expr: else_expr,
+            }),
+            lookup: None,
+            let_bindings: None,
+        })
+    }
+    /// Parses `let Enum::[A, B](v) = expr else { ... };` as a match that returns the payload or
+    /// diverges in the fallback.
+    fn parse_let_else(options: Options, input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token!(let)>()?;
+
+        let mut arm = Arm::parse_pattern(input)?;
+
+        arm.parse_variant_name(input)?;
+        input.parse::<Token!(=)>()?;
+
+        let expr = Expr::parse_without_eager_brace(input)?;
+
+        input.parse::<Token!(else)>()?;
+
+        let else_expr = Expr::Block(input.parse()?);
+
+        input.parse::<Option<Token!(;)>>()?;
+        arm.check_per_variant()?;
+
+        // The payload is moved out of the match arm, so `mut` goes to the `let` binding instead.
+        let mut bindings = Vec::new();
+
+        for pat in arm.payload.iter_mut().chain(&mut arm.constant) {
+            if let Pat::Ident(pat) = pat {
+                if pat.subpat.is_none() {
+                    let mutability = pat.by_ref.is_none().then(|| pat.mutability.take());
+                    let ident = &pat.ident;
+
+                    bindings.push(quote!(#mutability #ident));
+                }
+            }
+        }
+
+        bindings.extend(arm.variant_name.iter().map(|name| quote!(#name)));
+
+        let let_bindings = match &bindings[..] {
+            [binding] => binding.clone(),
+            bindings => quote!((#(#bindings),*)),
+        };
+
+        Ok(Args {
+            options,
+            expr,
+            ty: None,
+            arms: vec![MatchArm::Shared(Box::new(arm))],
+            fallback: Some(Fallback {
+                pat: parse_quote!(_),
+                arm_expr: else_expr,
+            }),
+            lookup: None,
+            let_bindings: Some(let_bindings),
+        })
+    }
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let tokens = input.fork().parse::<TokenStream>()?;
+        let mut registered = Vec::<Punctuated<Variant, Comma>>::new();
+
+        while input.peek(Token!(@)) {
+            let variants_list_content;
+
+            input.parse::<Token!(@)>()?;
+            bracketed!(variants_list_content in input);
+
+            registered.push(variants_list_content.call(parse_variant_list)?);
+        }
+
+        let options = input.parse()?;
+
+        let mut args = if input.peek(Token!(if)) {
+            Args::parse_if_let(options, input)?
+        } else if input.peek(Token!(let)) {
+            Args::parse_let_else(options, input)?
+        } else {
+            Args::parse_match(options, input)?
+        };
+
+        if args.options.non_exhaustive && args.fallback.is_none() {
+            args.fallback = Some(Fallback {
+                pat: parse_quote!(_),
+                arm_expr: parse_quote! {
+                    ::core::unreachable!("unexpected variant of the non-exhaustive enum")
+                },
+            });
+        }
+
+        // With `#[strict]` all the variants of the enum are looked up after the globs.
+        let strict_enum = if args.options.strict {
+            let arm = args
+                .shared_arms()
+                .next()
+                .ok_or_else(|| input.error("`#[strict]` requires an arm with the variant list"))?;
+
+            if arm.enum_path.is_ident("Self") {
+                return Err(syn::Error::new_spanned(
+                    &arm.enum_path,
+                    "`#[strict]` requires the enum name instead of `Self`",
+                ));
+            }
+
+            Some(arm.enum_path.clone())
+        } else {
+            None
+        };
+
+        let strict_lookup = strict_enum.as_ref().map(without_generics);
+
+        let mut globs = args
+            .arms
+            .iter_mut()
+            .flat_map(|arm| match arm {
+                MatchArm::Shared(arm) => vec![&mut **arm],
+                MatchArm::Tuple(arm) => arm.elems.iter_mut().collect(),
+                MatchArm::Plain(_) => vec![],
+            })
+            .filter_map(|arm| Some((arm.lookup_macro()?, arm)))
+            .collect::<Vec<_>>();
+
+        let lookups = globs
+            .iter()
+            .map(|(macro_path, _)| macro_path.clone())
+            .chain(strict_lookup)
+            .collect::<Vec<_>>();
+
+        let mut all_variants = None;
+
+        // The variants of the enums looked up for `[*]` and `#[strict]`, by the lookup macro path.
+        let mut enums = Vec::new();
+
+        // The macros generated by `#[variants]` and `variant_set!` invoke `all_the_same!` back,
+        // with the variant list prepended to the input. The lookups start from the last one, so
+        // that the lists end up in the order of the arms.
+        match lookups.len().checked_sub(registered.len()) {
+            Some(0) => {
+                if strict_enum.is_some() {
+                    all_variants = registered.pop();
+                }
+
+                for ((macro_path, arm), variants) in globs.iter_mut().zip(registered) {
+                    let unknown = arm
+                        .excluded
+                        .iter()
+                        .find(|name| !variants.iter().any(|variant| variant.name == **name));
+
+                    if let Some(name) = unknown {
+                        return Err(syn::Error::new_spanned(
+                            name,
+                            format!("no variant `{}` to exclude", name),
+                        ));
+                    }
+
+                    if arm.glob.is_some() {
+                        enums.push((macro_path.clone(), variants.clone()));
+                    }
+
+                    arm.variants = variants
+                        .into_iter()
+                        .filter(|variant| !arm.excluded.contains(&variant.name))
+                        .collect();
+                }
+            }
+            Some(unresolved) => {
+                let macro_path = &lookups[unresolved - 1];
+
+                args.lookup = Some(quote!(#macro_path! { #tokens }));
+
+                // The rest is checked once the variants are known.
+                return Ok(args);
+            }
+            None => return Err(input.error("unexpected variant list")),
+        }
+
+        if let (Some(enum_path), Some(all_variants)) = (&strict_enum, all_variants) {
+            let enum_name = quote!(#enum_path).to_string();
+
+            let missing = all_variants
+                .iter()
+                .filter(|variant| {
+                    !args.shared_arms().any(|arm| {
+                        let arm_enum = &arm.enum_path;
+
+                        quote!(#arm_enum).to_string() == enum_name
+                            && arm
+                                .variants
+                                .iter()
+                                .any(|listed| listed.name == variant.name)
+                    })
+                })
+                .map(|variant| format!("`{}`", variant.name))
+                .collect::<Vec<_>>();
+
+            if !missing.is_empty() {
+                return Err(syn::Error::new_spanned(
+                    enum_path,
+                    format!("missing variants in the lists: {}", missing.join(", ")),
+                ));
+            }
+
+            enums.push((without_generics(enum_path), all_variants));
+        }
+
+        // The variants gated in the enum don't compile in the configurations without the gate, if
+        // they are listed without it, so the gates are checked against the looked up enums.
+        for (macro_path, enum_variants) in &enums {
+            let listed = args
+                .shared_arms()
+                .filter(|arm| {
+                    arm.glob.is_none() && tokens_eq(&without_generics(&arm.enum_path), macro_path)
+                })
+                .flat_map(|arm| &arm.variants);
+
+            for listed in listed {
+                let listed_cfgs = cfg_predicates(&listed.attrs);
+
+                let missing = enum_variants
+                    .iter()
+                    .filter(|variant| variant.name == listed.name)
+                    .flat_map(|variant| cfg_predicates(&variant.attrs))
+                    .filter(|predicate| !listed_cfgs.contains(predicate))
+                    .map(|predicate| format!("#[cfg({})]", predicate))
+                    .collect::<Vec<_>>();
+
+                if missing.is_empty() {
+                    continue;
+                }
+
+                let missing = missing.join(" ");
+                let message = format!(
+                    "variant `{}` is gated with `{}` in the enum",
+                    listed.name, missing
+                );
+                let help = format!("add `{}` in front of the variant in the list", missing);
+
+                // `#[strict]` is for the lists that have to match the enum.
+                if args.options.strict {
+                    return Err(syn::Error::new(
+                        listed.name.span(),
+                        format!("{}, {}", message, help),
+                    ));
+                }
+
+                diagnostics::warn(listed.name.span(), &message, &help);
+            }
+        }
+
+        for arm in &args.arms {
+            if let MatchArm::Tuple(arm) = arm {
+                let len = arm.elems[0].variants.len();
+
+                if let Some(elem) = arm.elems.iter().find(|elem| elem.variants.len() != len) {
+                    return Err(syn::Error::new_spanned(
+                        &elem.enum_path,
+                        format!(
+                            "expected {} variants, like in the first list of the tuple",
+                            len
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if let Some(zipped) = args.zipped() {
+            for arm in args.shared_arms() {
+                if !arm.payload.is_empty() && arm.payload.len() != zipped {
+                    return Err(syn::Error::new_spanned(
+                        &arm.payload[0],
+                        format!(
+                            "expected {} payload bindings, one for each of the matched values",
+                            zipped
+                        ),
+                    ));
+                }
+
+                // The values are of the same variant, so they have the same number of fields.
+                let field_counts = arm.payload.iter().map(|pat| match pat {
+                    Pat::Tuple(fields) if arm.field.is_none() => fields.elems.len(),
+                    _ => 1,
+                });
+
+                if let Some((pat, _)) = arm
+                    .payload
+                    .iter()
+                    .zip(field_counts.clone())
+                    .find(|&(_, count)| Some(count) != field_counts.clone().next())
+                {
+                    return Err(syn::Error::new_spanned(
+                        pat,
+                        "expected the same number of fields for each of the matched values, \
+                         the bindings of several fields are grouped, e.g. `Enum::[A, B]((x0, x1), (y0, y1))`",
+                    ));
+                }
+            }
+        }
+
+        args.ungroup();
+
+        if args.options.const_context.is_some() {
+            let try_token = args.arms.iter().find_map(|arm| match arm {
+                MatchArm::Shared(arm) => arm.try_token.as_ref(),
+                MatchArm::Tuple(arm) => arm.try_token.as_ref(),
+                MatchArm::Plain(_) => None,
+            });
+
+            if let Some(try_token) = try_token {
+                return Err(syn::Error::new_spanned(
+                    try_token,
+                    "`#[const_context]` doesn't support `=> try` arms, the errors are converted \
+                     with `Into`, that isn't const",
+                ));
+            }
+        }
+
+        if args.options.table.is_some() {
+            args.check_table()?;
+        }
+
+        // Only the shared bodies are called through the shims and the helpers. The entries of the
+        // table are closures too, including the one of the fallback.
+        let closure_option = if args.options.shims.is_some() {
+            Some("`#[shims(..)]`")
+        } else if args.options.helper {
+            Some("`#[helper(..)]`")
+        } else if args.options.table.is_some() {
+            Some("`#[table]`")
+        } else {
+            None
+        };
+
+        if let Some(option) = closure_option {
+            let fallback = args
+                .fallback
+                .as_ref()
+                .filter(|_| args.options.table.is_some())
+                .map(|fallback| &fallback.arm_expr);
+
+            for arm_expr in args
+                .shared_arms()
+                .filter_map(|arm| arm.arm_expr.as_ref())
+                .chain(fallback)
+            {
+                EscapingControlFlowCheck::check(arm_expr, ClosureBody::Wrapped(option))?;
+            }
+        }
+
+        if args.options.helper {
+            let per_variant = args.shared_arms().find(|arm| {
+                arm.variant_name.is_some()
+                    || arm.constant.is_some()
+                    || arm.arm_expr.as_ref().is_some_and(|arm_expr| {
+                        contains_same(&quote!(#arm_expr), &args.options.same_name())
+                    })
+            });
+
+            if let Some(arm) = per_variant {
+                return Err(syn::Error::new_spanned(
+                    &arm.enum_path,
+                    "`#[helper]` shares the arm body between the variants, so it can't bind the \
+                     variant name or constants, or use `same!(...)`",
+                ));
+            }
+        }
+
+        if args.options.coerce.is_some() {
+            for arm in args.shared_arms() {
+                match &arm.payload[..] {
+                    [pat] if binding(pat).is_none() => {
+                        return Err(syn::Error::new_spanned(
+                            pat,
+                            "`#[coerce]` requires a plain payload binding",
+                        ));
+                    }
+                    [_, pat, ..] => {
+                        return Err(syn::Error::new_spanned(
+                            pat,
+                            "`#[coerce]` requires a single payload binding",
+                        ));
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+/// Path of the macro generated by `#[variants]` for the enum, e.g. `Enum` for `Enum<T>`.
+fn without_generics(enum_path: &Path) -> Path {
+    let mut macro_path = enum_path.clone();
+
+    for segment in &mut macro_path.segments {
+        segment.arguments = PathArguments::None;
+    }
+
+    macro_path
+}
+
+/// Predicates of the `#[cfg]` attributes as they are written, with `all(..)` flattened.
+fn cfg_predicates(attrs: &[Attribute]) -> Vec<String> {
+    fn flatten(meta: NestedMeta, predicates: &mut Vec<String>) {
+        match meta {
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("all") => {
+                for nested in list.nested {
+                    flatten(nested, predicates);
+                }
+            }
+            meta => predicates.push(meta.to_token_stream().to_string()),
+        }
+    }
+
+    let mut predicates = Vec::new();
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("cfg")) {
+        match attr.parse_args::<NestedMeta>() {
+            Ok(meta) => flatten(meta, &mut predicates),
+            Err(_) => predicates.push(attr.tokens.to_string()),
+        }
+    }
+
+    predicates
+}
+
+/// Parses the enum path in front of the variant list, e.g. `Enum::` in `Enum::[A, B]`.
+pub(crate) fn parse_enum_path(input: ParseStream) -> syn::Result<Path> {
+    let mut enum_path = Path {
+        leading_colon: input.parse()?,
+        segments: Punctuated::new(),
+    };
+
+    loop {
+        let mut segment = input.parse::<PathSegment>()?;
+
+        // `Enum<T>::[..]` reads better without the turbofish, that is required in the patterns.
+        if let PathArguments::AngleBracketed(args) = &mut segment.arguments {
+            args.colon2_token.get_or_insert_with(Default::default);
+        }
+
+        enum_path.segments.push_value(segment);
+
+        let colon2 = input.parse::<Token!(::)>()?;
+
+        if input.peek(Bracket) {
+            break;
+        }
+
+        enum_path.segments.push_punct(colon2);
+    }
+
+    Ok(enum_path)
+}
+
+/// Collects the scrutinee tokens if they are followed by the `-> Type` annotation: the expression
+/// parser would otherwise take `->` for a subtraction.
+fn scrutinee_before_ty(cursor: Cursor<'_>) -> syn::Result<(Option<TokenStream>, Cursor<'_>)> {
+    let mut scrutinee = TokenStream::new();
+    let mut rest = cursor;
+
+    while let Some((tt, next)) = rest.token_tree() {
+        if let Some((minus, after_minus)) = rest.punct() {
+            let is_arrow = minus.as_char() == '-'
+                && minus.spacing() == Spacing::Joint
+                && after_minus
+                    .punct()
+                    .is_some_and(|(gt, _)| gt.as_char() == '>');
+
+            if is_arrow {
+                return Ok((Some(scrutinee), rest));
+            }
+        }
+
+        scrutinee.extend(iter::once(tt));
+        rest = next;
+    }
+
+    Ok((None, cursor))
+}
+
+/// Warns about `unsafe` blocks nested in the arm body: they are silently copy-pasted for every
+/// variant, so marking the body with `=> unsafe` instead is suggested. They compiled before the
+/// marker was added, so they are still accepted.
+struct NestedUnsafeCheck;
+
+impl NestedUnsafeCheck {
+    fn check(arm_expr: &Expr) {
+        NestedUnsafeCheck.visit_expr(arm_expr);
+    }
+}
+
+impl<'ast> Visit<'ast> for NestedUnsafeCheck {
+    fn visit_expr_unsafe(&mut self, expr: &'ast ExprUnsafe) {
+        diagnostics::warn(
+            expr.unsafe_token.span,
+            "`unsafe` blocks in the arm body are duplicated for every variant",
+            "mark the arm body with `=> unsafe` instead",
+        );
+
+        visit::visit_expr_unsafe(self, expr);
+    }
+}
+
+/// How the code, that looks like the body of a closure, or the body, that is called through one,
+/// ends up in the expansion.
+#[derive(Clone, Copy)]
+pub(crate) enum ClosureBody {
+    /// The options, e.g. `#[shims(..)]`, call the arm body through a closure.
+    Wrapped(&'static str),
+    /// The macro, e.g. `for_each_variant!`, expands the body of the closure it takes in the arms.
+    Inlined(&'static str),
+}
+
+/// Rejects `return`, `?`, `break` and `continue` that leave the body, if only one of the arm
+/// body and the closure has it: they would silently apply to the closure, rather than to the
+/// function or the loop around the match, or the other way around.
+pub(crate) struct EscapingControlFlowCheck {
+    body: ClosureBody,
+    loops: usize,
+    labels: Vec<Lifetime>,
+    error: Option<syn::Error>,
+}
+
+impl EscapingControlFlowCheck {
+    pub(crate) fn check(body_expr: &Expr, body: ClosureBody) -> syn::Result<()> {
+        let mut check = EscapingControlFlowCheck {
+            body,
+            loops: 0,
+            labels: Vec::new(),
+            error: None,
+        };
+
+        check.visit_expr(body_expr);
+
+        check.error.map_or(Ok(()), Err)
+    }
+
+    fn reject(&mut self, tokens: impl ToTokens, what: &str, returns: bool) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let message = match (self.body, returns) {
+            (ClosureBody::Wrapped(option), true) => format!(
+                "{} would return from the closure, not the function, since {} calls the arm \
+                 body through a closure",
+                what, option
+            ),
+            (ClosureBody::Wrapped(option), false) => format!(
+                "{} can't leave the closure, since {} calls the arm body through a closure",
+                what, option
+            ),
+            (ClosureBody::Inlined(option), true) => format!(
+                "{} would return from the function, not the closure, since {} expands the \
+                 closure body in the match arms",
+                what, option
+            ),
+            (ClosureBody::Inlined(option), false) => format!(
+                "{} would leave the loop around {}, not the closure, since it expands the \
+                 closure body in the match arms",
+                what, option
+            ),
+        };
+
+        self.error = Some(syn::Error::new_spanned(tokens, message));
+    }
+
+    fn leaves_body(&self, label: Option<&Lifetime>) -> bool {
+        match label {
+            Some(label) => !self.labels.contains(label),
+            None => self.loops == 0,
+        }
+    }
+
+    fn visit_loop(&mut self, label: Option<&Label>, visit: impl FnOnce(&mut Self)) {
+        self.loops += 1;
+        self.labels.extend(label.map(|label| label.name.clone()));
+        visit(self);
+        self.labels
+            .truncate(self.labels.len() - usize::from(label.is_some()));
+        self.loops -= 1;
+    }
+}
+
+impl<'ast> Visit<'ast> for EscapingControlFlowCheck {
+    fn visit_expr_return(&mut self, expr: &'ast ExprReturn) {
+        self.reject(expr.return_token, "`return`", true);
+        visit::visit_expr_return(self, expr);
+    }
+
+    fn visit_expr_try(&mut self, expr: &'ast ExprTry) {
+        self.reject(expr.question_token, "`?`", true);
+        visit::visit_expr_try(self, expr);
+    }
+
+    fn visit_expr_break(&mut self, expr: &'ast ExprBreak) {
+        if self.leaves_body(expr.label.as_ref()) {
+            self.reject(expr.break_token, "`break`", false);
+        }
+
+        visit::visit_expr_break(self, expr);
+    }
+
+    fn visit_expr_continue(&mut self, expr: &'ast ExprContinue) {
+        if self.leaves_body(expr.label.as_ref()) {
+            self.reject(expr.continue_token, "`continue`", false);
+        }
+
+        visit::visit_expr_continue(self, expr);
+    }
+
+    fn visit_expr_loop(&mut self, expr: &'ast ExprLoop) {
+        self.visit_loop(expr.label.as_ref(), |check| {
+            visit::visit_expr_loop(check, expr)
+        });
+    }
+
+    fn visit_expr_while(&mut self, expr: &'ast ExprWhile) {
+        self.visit_loop(expr.label.as_ref(), |check| {
+            visit::visit_expr_while(check, expr)
+        });
+    }
+
+    fn visit_expr_for_loop(&mut self, expr: &'ast ExprForLoop) {
+        self.visit_loop(expr.label.as_ref(), |check| {
+            visit::visit_expr_for_loop(check, expr)
+        });
+    }
+
+    fn visit_expr_block(&mut self, expr: &'ast ExprBlock) {
+        // Labeled blocks can only be left with labeled `break`s.
+        let label = expr.label.as_ref().map(|label| label.name.clone());
+        let labeled = label.is_some();
+
+        self.labels.extend(label);
+        visit::visit_expr_block(self, expr);
+        self.labels
+            .truncate(self.labels.len() - usize::from(labeled));
+    }
+
+    // The control flow of the nested closures, `async` blocks and items is their own.
+    fn visit_expr_closure(&mut self, _: &'ast ExprClosure) {}
+
+    fn visit_expr_async(&mut self, _: &'ast ExprAsync) {}
+
+    fn visit_item(&mut self, _: &'ast Item) {}
+}
+
+/// Replaces the invisible groups of the expressions with the parentheses. The expressions passed
+/// as `$expr` metavariables of `macro_rules!` come wrapped in such groups, that the compiler
+/// ignores in the expansion of a procedural macro, so `$f(x)` with `|x| x + 1` passed as `$f`
+/// would end up as `|x| x + 1(x)` otherwise.
+struct Ungroup;
+
+impl VisitMut for Ungroup {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if let Expr::Group(ExprGroup {
+            attrs,
+            group_token,
+            expr: inner,
+        }) = expr
+        {
+            *expr = Expr::Paren(ExprParen {
+                attrs: mem::take(attrs),
+                paren_token: Paren(group_token.span),
+                expr: mem::replace(inner, Box::new(Expr::Verbatim(TokenStream::new()))),
+            });
+        }
+
+        visit_mut::visit_expr_mut(self, expr);
+    }
+}
+
+pub(crate) fn expand(args: &Args) -> TokenStream {
+    if let Some(lookup) = &args.lookup {
+        return lookup.clone();
+    }
+
+    let match_expr = match &args.options.table {
+        Some(table_args) => expand_table(args, table_args),
+        None => expand_match(args),
+    };
+
+    // Passing the match to a function with the explicit type makes it a coercion site, so every
+    // arm is checked against the annotated type. The boxed futures are coerced to the trait
+    // object, unless the type is annotated, e.g. to make the future `Send`.
+    let ty = match &args.ty {
+        Some(ty) => Some(quote!(#ty)),
+        None if args.options.boxed_async => Some(quote! {
+            ::core::pin::Pin<::alloc::boxed::Box<dyn ::core::future::Future<Output = _> + '_>>
+        }),
+        None => None,
+    };
+
+    let match_expr = match ty {
+        Some(ty) => quote!(::core::convert::identity::<#ty>(#match_expr)),
+        None => match_expr,
+    };
+
+    // Attributes on expressions are unstable, except for the tail expressions of the blocks and
+    // the statements, so the lint attributes go on the match in a block, or on the `let`.
+    let lint_attrs = &args.options.lint_attrs;
+
+    let expanded = match &args.let_bindings {
+        Some(bindings) => quote!(#(#lint_attrs)* let #bindings = #match_expr;),
+        None if lint_attrs.is_empty() => match_expr,
+        None => quote! {{
+            #(#lint_attrs)*
+            #match_expr
+        }},
+    };
+
+    if let Some(span) = args.options.debug {
+        diagnostics::note_expansion(span, &expanded);
+    }
+
+    expanded
+}
+
+fn expand_match(args: &Args) -> TokenStream {
+    let expr = &args.expr;
+    let mut arms = TokenStream::new();
+    let mut helpers = Vec::new();
+
+    for arm in &args.arms {
+        match arm {
+            MatchArm::Shared(arm) => {
+                expand_arm(&args.options, args.zipped(), arm, &mut arms, &mut helpers)
+            }
+            MatchArm::Tuple(arm) => expand_tuple_arm(&args.options, arm, &mut arms),
+            MatchArm::Plain(arm) => {
+                let mut arm = arm.clone();
+
+                if args.options.boxed_async {
+                    let body = &arm.body;
+
+                    *arm.body = parse_quote!(::alloc::boxed::Box::pin(async move { #body }));
+                }
+
+                // The last arm might not have a comma, but it's followed by the fallback.
+                let comma = arm.comma.is_none().then(|| quote!(,));
+
+                arms.extend(quote!(#arm #comma));
+            }
+        }
+    }
+
+    if let Some(Fallback { pat, arm_expr }) = &args.fallback {
+        let arm_expr = box_future(&args.options, quote!(#arm_expr));
+
+        arms.extend(quote! {
+            #[allow(unreachable_patterns)]
+            #pat => #arm_expr
+        });
+    }
+
+    let match_expr = quote! {
+        match #expr {
+            #arms
+        }
+    };
+
+    if helpers.is_empty() {
+        match_expr
+    } else {
+        quote! {{
+            #(#helpers)*
+
+            #match_expr
+        }}
+    }
+}
+
+/// Dispatches the variants through an array of function pointers, indexed by the position of the
+/// variant in the arms. The function pointers can't capture the environment, so the variables
+/// the arm bodies use are passed as arguments.
+fn expand_table(args: &Args, table_args: &[Ident]) -> TokenStream {
+    let expr = &args.expr;
+    let this = hygienic("this");
+    let table_args_tuple = hygienic("args");
+    let index = hygienic("index");
+    let entries_array = hygienic("entries");
+    let mut index_arms = TokenStream::new();
+    let mut entries = Vec::new();
+
+    let table_entry = |arms: TokenStream| {
+        quote! {
+            |#this, #table_args_tuple| {
+                #[allow(unused_variables)]
+                let (#(#table_args,)*) = #table_args_tuple;
+
+                match #this {
+                    #arms
+
+                    #[allow(unreachable_patterns)]
+                    _ => ::core::unreachable!(),
+                }
+            }
+        }
+    };
+
+    for arm in args.shared_arms() {
+        // Every entry gets the arm with just its variant. The arm is cloned once, rather than for
+        // every variant, as cloning the list of the variants each time is quadratic.
+        let mut variant_arm = arm.clone();
+
+        for variant in &arm.variants {
+            let enum_path = &arm.enum_path;
+            let name = &variant.name;
+            let index = entries.len();
+
+            index_arms.extend(quote!(#enum_path::#name { .. } => #index,));
+
+            let mut arms = TokenStream::new();
+
+            variant_arm.variants = iter::once(variant.clone()).collect();
+            expand_arm(
+                &args.options,
+                None,
+                &variant_arm,
+                &mut arms,
+                &mut Vec::new(),
+            );
+            entries.push(table_entry(arms));
+        }
+    }
+
+    if let Some(Fallback { pat, arm_expr }) = &args.fallback {
+        let index = entries.len();
+        let arm_expr = box_future(&args.options, quote!(#arm_expr));
+
+        index_arms.extend(quote! {
+            #[allow(unreachable_patterns)]
+            _ => #index,
+        });
+
+        entries.push(table_entry(quote!(#pat => #arm_expr,)));
+    }
+
+    // The types of the scrutinee and the arguments are inferred before the entries, since
+    // closure arguments are type checked last, so the entries don't need the annotations.
+    quote! {{
+        fn __all_the_same_table<S, A, R, const N: usize>(
+            _: &S,
+            _: &A,
+            table: [fn(S, A) -> R; N],
+        ) -> [fn(S, A) -> R; N] {
+            table
+        }
+
+        let #this = #expr;
+        let #table_args_tuple = (#(#table_args,)*);
+
+        let #index = match &#this {
+            #index_arms
+        };
+
+        let #entries_array = __all_the_same_table(&#this, &#table_args_tuple, [#(#entries),*]);
+
+        (#entries_array[#index])(#this, #table_args_tuple)
+    }}
+}
+
+fn expand_arm(
+    options: &Options,
+    zipped: Option<usize>,
+    arm: &Arm,
+    arms: &mut TokenStream,
+    helpers: &mut Vec<TokenStream>,
+) {
+    let whole_binding = arm.whole_name.as_ref().map(|name| quote!(#name @));
+    let mut payload = arm.payload.clone();
+
+    // The coerced payload is rebound, so the new binding gets `mut` instead.
+    let coerced_mut = match (&options.coerce, &mut payload[..]) {
+        (Some(_), [Pat::Ident(pat)]) => pat.mutability.take(),
+        _ => None,
+    };
+
+    let bindings = payload
+        .iter()
+        .chain(&arm.constant)
+        .filter_map(binding)
+        .chain(&arm.variant_name)
+        .collect::<Vec<_>>();
+
+    let inner_name = match &payload[..] {
+        [pat] => binding(pat),
+        _ => None,
+    };
+
+    // The guard is replicated into every generated arm.
+    let guard = arm.guard.as_ref().map(|guard| quote!(if #guard));
+
+    let arm_expr = match &arm.arm_expr {
+        Some(arm_expr) => arm_body(arm.try_token.as_ref(), arm.unsafety.as_ref(), arm_expr),
+        None => match &bindings[..] {
+            [binding] => quote!(#binding),
+            bindings => quote!((#(#bindings),*)),
+        },
+    };
+
+    let arm_expr = box_future(options, arm_expr);
+
+    // Pinned after the coercion, so that the coerced payload is pinned.
+    let arm_expr = pin_bindings(&arm.pinned, arm_expr);
+
+    // The helper is called by every arm with the coerced payload, that it takes by the same name.
+    // It's only needed if the body is shared by several variants.
+    let shared_by = arm
+        .variants
+        .iter()
+        .filter(|variant| variant.arm_expr.is_none())
+        .count();
+
+    let (arm_expr, coerced_mut) = if options.helper && shared_by > 1 {
+        let helper = hygienic(&format!("helper_{}", helpers.len()));
+
+        let param = inner_name.map(|inner_name| {
+            let ty = &options.coerce;
+
+            quote!(#coerced_mut #inner_name: #ty)
+        });
+
+        helpers.push(quote! {
+            #[allow(unused_mut)]
+            let mut #helper = |#param| { #arm_expr };
+        });
+
+        (quote!(#helper(#inner_name)), None)
+    } else {
+        (arm_expr, coerced_mut)
+    };
+
+    // `Into` can't do unsized coercions, like `&mut T` to `&mut dyn Trait`, so references are
+    // coerced and only owned payloads are converted.
+    let arm_expr = match (&options.coerce, inner_name) {
+        (Some(ty @ (Type::Reference(_) | Type::Ptr(_))), Some(inner_name)) => quote! {{
+            let #coerced_mut #inner_name: #ty = #inner_name;
+
+            #arm_expr
+        }},
+        (Some(ty), Some(inner_name)) => quote! {{
+            let #coerced_mut #inner_name: #ty = ::core::convert::Into::into(#inner_name);
+
+            #arm_expr
+        }},
+        _ => arm_expr,
+    };
+
+    // Unless it's coerced, the adapted payload is rebound as mutable as the original binding.
+    let adapter_mut = match &payload[..] {
+        [Pat::Ident(pat)] => pat.mutability,
+        _ => None,
+    };
+
+    let enum_path = &arm.enum_path;
+
+    // The zipped values get a payload binding each, e.g. `(Enum::A(x), Enum::A(y))`. The
+    // bindings of the variants with several fields are grouped, e.g. `(x0, x1)`.
+    let variant_pat = |name: &Ident| match zipped {
+        Some(zipped) => {
+            let pats = (0..zipped).map(|i| {
+                let payload_pat = match payload.get(i) {
+                    Some(Pat::Tuple(fields)) if arm.field.is_none() => {
+                        arm.payload_pat(&fields.elems.iter().cloned().collect::<Vec<_>>())
+                    }
+                    _ => arm.payload_pat(payload.get(i..=i).unwrap_or_default()),
+                };
+
+                quote!(#enum_path::#name #payload_pat)
+            });
+
+            quote!(#whole_binding (#(#pats,)*))
+        }
+        None => {
+            let payload_pat = arm.payload_pat(&payload);
+
+            quote!(#whole_binding #enum_path::#name #payload_pat)
+        }
+    };
+
+    let traced_enum_name = match enum_path.segments.last() {
+        Some(segment) if segment.ident != "Self" => {
+            let name = segment.ident.to_string();
+
+            quote!(#name)
+        }
+        _ => quote!(::core::any::type_name::<Self>()),
+    };
+
+    // Variants that don't need their own arm, e.g. for `#[cfg]` or an adapter, share one.
+    let same_name = options.same_name();
+    let shared_body = arm.variant_name.is_none() && !contains_same(&arm_expr, &same_name);
+    let mut merged = Vec::new();
+
+    // The cold variants go last, they are distinct from the rest, so the order doesn't matter
+    // otherwise.
+    let variants = arm
+        .variants
+        .iter()
+        .filter(|variant| !variant.cold)
+        .chain(arm.variants.iter().filter(|variant| variant.cold));
+
+    for variant in variants {
+        let name = &variant.name;
+        let attrs = &variant.attrs;
+        let pat = variant_pat(name);
+
+        let mergeable = options.or_patterns
+            && shared_body
+            && attrs.is_empty()
+            && !variant.cold
+            && variant.adapter.is_none()
+            && variant.constant.is_none()
+            && variant.arm_expr.is_none()
+            && arm.bounds.is_none();
+
+        if mergeable {
+            merged.push(pat);
+
+            continue;
+        }
+
+        let trace = options.trace.as_ref().map(|tracing| {
+            let variant_name = name.to_string();
+            let variant_name = variant_name.trim_start_matches("r#");
+
+            quote! {
+                #tracing::trace!(
+                    enum_name = #traced_enum_name,
+                    variant = #variant_name,
+                    "dispatching to the variant",
+                );
+            }
+        });
+
+        // The variant is added to the message, so the assertion can be shared by all the arms.
+        let assertion = options.debug_assert.as_ref().map(|assertion| {
+            let variant_name = name.to_string();
+            let variant_name = variant_name.trim_start_matches("r#");
+            let cond = &assertion[0];
+            let msg = assertion.iter().skip(1);
+
+            // The formatting isn't const, so the message is concatenated at compile time.
+            if options.const_context.is_some() {
+                let msg = match assertion.iter().nth(1) {
+                    Some(msg) => quote!(#msg),
+                    None => quote!("assertion failed: ", ::core::stringify!(#cond)),
+                };
+                let arm_note = format!(" (in the `{}` arm)", variant_name);
+
+                quote! {
+                    ::core::debug_assert!(#cond, ::core::concat!(#msg, #arm_note));
+                }
+            } else if assertion.len() > 1 {
+                quote! {
+                    ::core::debug_assert!(
+                        #cond,
+                        "{} (in the `{}` arm)",
+                        ::core::format_args!(#(#msg),*),
+                        #variant_name,
+                    );
+                }
+            } else {
+                quote! {
+                    ::core::debug_assert!(
+                        #cond,
+                        "assertion failed: {} (in the `{}` arm)",
+                        ::core::stringify!(#cond),
+                        #variant_name,
+                    );
+                }
+            }
+        });
+
+        let cold = variant.cold.then(cold_path);
+
+        let prelude = (trace.is_some() || assertion.is_some() || cold.is_some())
+            .then(|| quote!(#cold #trace #assertion));
+
+        let with_prelude = |arm_expr: TokenStream| match &prelude {
+            Some(prelude) => quote!({ #prelude #arm_expr }),
+            None => arm_expr,
+        };
+
+        let constant = match (&arm.constant, &variant.constant) {
+            (Some(pat), Some(value)) => Some(quote!(let #pat = #value;)),
+            _ => None,
+        };
+
+        let variant_name = arm.variant_name.as_ref().map(|binding| {
+            let name = name.to_string();
+            let name = name.trim_start_matches("r#");
+
+            quote!(let #binding: &'static str = #name;)
+        });
+
+        let with_bindings = |arm_expr: TokenStream| {
+            if constant.is_none() && variant_name.is_none() {
+                return arm_expr;
+            }
+
+            quote! {{
+                #constant
+                #variant_name
+
+                #arm_expr
+            }}
+        };
+
+        let with_same =
+            |arm_expr| replace_same(arm_expr, &same_name, enum_path, name, arm.field.as_ref());
+
+        let arm_expr = with_variant_span(options, with_same(arm_expr.clone()), name);
+
+        // The bounds are checked by a call at the variant, so the error points at it instead of
+        // the body, that would fail to compile for the same reason.
+        let arm_expr = match (&arm.bounds, inner_name) {
+            (Some(bounds), Some(inner_name)) => {
+                let assertion = respan(
+                    quote!(__all_the_same_assert_bounds(&#inner_name);),
+                    name.span(),
+                );
+
+                quote! {{
+                    fn __all_the_same_assert_bounds<T: ?::core::marker::Sized + #bounds>(_: &T) {}
+
+                    #assertion
+
+                    #arm_expr
+                }}
+            }
+            _ => arm_expr,
+        };
+
+        let arm_expr = with_bindings(match (&variant.adapter, inner_name) {
+            (Some(adapter), Some(inner_name)) => quote! {{
+                let #adapter_mut #inner_name = #adapter;
+
+                #arm_expr
+            }},
+            _ => arm_expr,
+        });
+
+        arms.extend(match &variant.arm_expr {
+            // Overrides often don't use the payload, e.g. when they just diverge.
+            Some(arm_expr) => {
+                let arm_expr = box_future(options, quote!(#arm_expr));
+                let arm_expr = with_prelude(with_bindings(with_same(arm_expr)));
+
+                quote! {
+                    #(#attrs)*
+                    #[allow(unused_variables)]
+                    #pat #guard => #arm_expr,
+                }
+            }
+            None if options.shims.is_some() => {
+                let shim_name = format_ident!(
+                    "__dispatch_{}_{}",
+                    snake_case(name),
+                    options.shims.as_ref().unwrap()
+                );
+
+                quote! {
+                    #(#attrs)*
+                    #pat #guard => {
+                        #prelude
+
+                        #[inline(never)]
+                        fn #shim_name<R>(f: impl ::core::ops::FnOnce() -> R) -> R {
+                            f()
+                        }
+
+                        #shim_name(|| #arm_expr)
+                    }
+                }
+            }
+            None => {
+                let arm_expr = with_prelude(arm_expr);
+
+                quote! {
+                    #(#attrs)*
+                    #pat #guard => #arm_expr,
+                }
+            }
+        });
+    }
+
+    if !merged.is_empty() {
+        arms.extend(quote! {
+            #(#merged)|* #guard => #arm_expr,
+        });
+    }
+}
+
+fn expand_tuple_arm(options: &Options, arm: &TupleArm, arms: &mut TokenStream) {
+    let guard = arm.guard.as_ref().map(|guard| quote!(if #guard));
+    let arm_expr = pin_bindings(
+        arm.elems.iter().flat_map(|elem| &elem.pinned),
+        box_future(
+            options,
+            arm_body(arm.try_token.as_ref(), arm.unsafety.as_ref(), &arm.arm_expr),
+        ),
+    );
+
+    for i in 0..arm.elems[0].variants.len() {
+        let attrs = arm.elems.iter().flat_map(|elem| &elem.variants[i].attrs);
+
+        let arm_expr = if arm.elems.iter().any(|elem| elem.variants[i].cold) {
+            let cold_path = cold_path();
+
+            quote!({ #cold_path #arm_expr })
+        } else {
+            arm_expr.clone()
+        };
+
+        let arm_expr = with_variant_span(options, arm_expr, &arm.elems[0].variants[i].name);
+
+        let pats = arm.elems.iter().map(|elem| {
+            let whole_binding = elem.whole_name.as_ref().map(|name| quote!(#name @));
+            let enum_path = &elem.enum_path;
+            let name = &elem.variants[i].name;
+            let payload_pat = elem.payload_pat(&elem.payload);
+
+            quote!(#whole_binding #enum_path::#name #payload_pat)
+        });
+
+        arms.extend(quote! {
+            #(#attrs)*
+            (#(#pats,)*) #guard => #arm_expr,
+        });
+    }
+}
+
+/// Parses the payload binding, that can be marked with `pin`, e.g. `pin s`.
+fn parse_payload_binding(input: ParseStream, pinned: &mut Vec<PatIdent>) -> syn::Result<Pat> {
+    let is_pinned = input
+        .fork()
+        .parse::<Ident>()
+        .is_ok_and(|ident| ident == "pin")
+        && (input.peek2(Ident) || input.peek2(Token!(mut)) || input.peek2(Token!(ref)));
+
+    if !is_pinned {
+        return input.parse();
+    }
+
+    let pin = input.parse::<Ident>()?;
+
+    match input.parse()? {
+        Pat::Ident(mut pat) if pat.subpat.is_none() => {
+            // The binding is only moved into `Pin::new`, so `mut` goes to the pinned one.
+            let mutability = match pat.by_ref {
+                Some(_) => None,
+                None => pat.mutability.take(),
+            };
+
+            pinned.push(PatIdent {
+                attrs: Vec::new(),
+                by_ref: None,
+                mutability,
+                ident: pat.ident.clone(),
+                subpat: None,
+            });
+
+            Ok(Pat::Ident(pat))
+        }
+        _ => Err(syn::Error::new_spanned(
+            pin,
+            "`pin` requires a plain binding, e.g. `pin s`",
+        )),
+    }
+}
+
+/// Rebinds the payload bindings marked with `pin` with `Pin::new`, before the arm body.
+fn pin_bindings<'a>(
+    pinned: impl IntoIterator<Item = &'a PatIdent>,
+    arm_expr: TokenStream,
+) -> TokenStream {
+    let rebindings = pinned
+        .into_iter()
+        .map(|pat| {
+            let ident = &pat.ident;
+
+            quote!(let #pat = ::core::pin::Pin::new(#ident);)
+        })
+        .collect::<Vec<_>>();
+
+    if rebindings.is_empty() {
+        return arm_expr;
+    }
+
+    quote! {{
+        #(#rebindings)*
+
+        #arm_expr
+    }}
+}
+
+/// Wraps the arm body in `Box::pin(async move { ... })` with the `#[async]` option.
+fn box_future(options: &Options, arm_expr: TokenStream) -> TokenStream {
+    if !options.boxed_async {
+        return arm_expr;
+    }
+
+    quote!(::alloc::boxed::Box::pin(async move { #arm_expr }))
+}
+
+fn is_cold(attr: &Attribute) -> bool {
+    attr.path.is_ident("cold") && attr.tokens.is_empty()
+}
+
+/// Moves the tokens of the arm body copy to the variant it's expanded for, with the
+/// `#[variant_spans]` option.
+fn with_variant_span(options: &Options, tokens: TokenStream, name: &Ident) -> TokenStream {
+    if options.variant_spans {
+        respan(tokens, name.span())
+    } else {
+        tokens
+    }
+}
+
+/// Changes the location of the tokens, keeping their hygiene.
+fn respan(tokens: TokenStream, span: Span) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|mut token| {
+            if let TokenTree::Group(group) = &token {
+                let mut respanned = Group::new(group.delimiter(), respan(group.stream(), span));
+
+                respanned.set_span(group.span().located_at(span));
+                token = TokenTree::Group(respanned);
+            } else {
+                token.set_span(token.span().located_at(span));
+            }
+
+            token
+        })
+        .collect()
+}
+
+/// Name of the local variable of the expansion, that isn't visible to the arm bodies, so nested
+/// invocations and the user's bindings don't clash with it.
+fn hygienic(name: &str) -> Ident {
+    Ident::new(&format!("__all_the_same_{}", name), Span::mixed_site())
+}
+
+/// Hints that the arm is unlikely, the same way as `core::hint::cold_path`. The function is
+/// `const`, so the hint works in the const contexts too.
+fn cold_path() -> TokenStream {
+    quote! {
+        #[cold]
+        #[inline(never)]
+        const fn cold_path() {}
+
+        cold_path();
+    }
+}
+
+/// Marks the arm body with `unsafe`, if the arm is marked with `=> unsafe`.
+fn arm_body(
+    try_token: Option<&Token!(try)>,
+    unsafety: Option<&Token!(unsafe)>,
+    arm_expr: &Expr,
+) -> TokenStream {
+    let body = match (unsafety, arm_expr) {
+        (Some(unsafety), Expr::Block(expr)) if expr.attrs.is_empty() && expr.label.is_none() => {
+            let block = &expr.block;
+
+            quote!(#unsafety #block)
+        }
+        (Some(unsafety), arm_expr) => quote!(#unsafety { #arm_expr }),
+        (None, arm_expr) => quote!(#arm_expr),
+    };
+
+    // The error of every copy is converted on its own, so the payloads can return different
+    // error types.
+    match try_token {
+        Some(_) => quote!(::core::result::Result::map_err(#body, ::core::convert::Into::into)),
+        None => body,
+    }
+}
+
+fn is_str_lit(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(_),
+            ..
+        })
+    )
+}
+
+/// Whether the variant list is the `[*]` glob, maybe with `except`, rather than a list that starts
+/// with the `*Variant` deref.
+fn is_glob(input: ParseStream) -> bool {
+    let fork = input.fork();
+
+    fork.parse::<Token!(*)>().is_ok()
+        && (fork.is_empty()
+            || fork.parse::<Ident>().is_ok_and(|ident| ident == "except") && fork.peek(Ident))
+}
+
+/// Adapter of `*Variant`, that derefs the payload the number of times. The payload is bound by
+/// reference, so the helper trait derefs `&T` with `Deref` and `&mut T` with `DerefMut`, keeping
+/// the mutability of the binding.
+fn deref_adapter(inner_name: &Ident, depth: usize) -> Expr {
+    let derefs = (0..depth).fold(
+        quote!(#inner_name),
+        |expr, _| quote!(PayloadDeref::deref(#expr)),
+    );
+
+    parse_quote! {{
+        trait PayloadDeref {
+            type Target;
+
+            fn deref(self) -> Self::Target;
+        }
+
+        impl<'a, T: ::core::ops::Deref + ?::core::marker::Sized> PayloadDeref for &'a T {
+            type Target = &'a T::Target;
+
+            fn deref(self) -> Self::Target {
+                &**self
+            }
+        }
+
+        impl<'a, T: ::core::ops::DerefMut + ?::core::marker::Sized> PayloadDeref for &'a mut T {
+            type Target = &'a mut T::Target;
+
+            fn deref(self) -> Self::Target {
+                &mut **self
+            }
+        }
+
+        #derefs
+    }}
+}
+
+/// Returns the name bound by the plain binding pattern, like `v` or `ref mut v`.
+fn binding(pat: &Pat) -> Option<&Ident> {
+    match pat {
+        Pat::Ident(pat) if pat.subpat.is_none() => Some(&pat.ident),
+        _ => None,
+    }
+}
+
+fn is_shorthand(field: &Ident, pat: &Pat) -> bool {
+    match pat {
+        Pat::Ident(pat) => {
+            pat.ident == *field
+                && pat.by_ref.is_none()
+                && pat.mutability.is_none()
+                && pat.subpat.is_none()
+        }
+        _ => false,
+    }
+}
+
+/// Replaces `same!(...)` in the arm body with the constructor of the matched variant, so that the
+/// variant can be rebuilt with a new payload. `Enum::same!(...)` constructs the variant with the
+/// same name of another enum.
+fn replace_same(
+    tokens: TokenStream,
+    same_name: &Ident,
+    enum_path: &Path,
+    name: &Ident,
+    field: Option<&Ident>,
+) -> TokenStream {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let mut replaced = TokenStream::new();
+    let mut rest = &tokens[..];
+
+    loop {
+        let preceding = &tokens[..tokens.len() - rest.len()];
+
+        rest = match rest {
+            [TokenTree::Ident(ident), bang, TokenTree::Group(args), rest @ ..]
+                if is_same_invocation(ident, bang, args, same_name) =>
+            {
+                let args = replace_same(args.stream(), same_name, enum_path, name, field);
+
+                let constructor = match preceding {
+                    [.., TokenTree::Punct(colon1), TokenTree::Punct(colon2)]
+                        if colon1.as_char() == ':'
+                            && colon1.spacing() == Spacing::Joint
+                            && colon2.as_char() == ':' =>
+                    {
+                        quote!(#name)
+                    }
+                    _ => quote!(#enum_path::#name),
+                };
+
+                replaced.extend(match field {
+                    Some(field) => quote!(#constructor { #field: #args }),
+                    None => quote!(#constructor(#args)),
+                });
+
+                rest
+            }
+            // The nested invocation replaces `same!(...)` with its own variants.
+            [nested @ TokenTree::Ident(ident), bang @ TokenTree::Punct(_), invocation @ TokenTree::Group(_), rest @ ..]
+                if is_nested_invocation(ident, bang, same_name) =>
+            {
+                replaced.extend([nested.clone(), bang.clone(), invocation.clone()]);
+
+                rest
+            }
+            [TokenTree::Group(group), rest @ ..] => {
+                let stream = replace_same(group.stream(), same_name, enum_path, name, field);
+                let mut replaced_group = Group::new(group.delimiter(), stream);
+
+                replaced_group.set_span(group.span());
+                replaced.extend(iter::once(TokenTree::Group(replaced_group)));
+
+                rest
+            }
+            [token, rest @ ..] => {
+                replaced.extend(iter::once(token.clone()));
+
+                rest
+            }
+            [] => return replaced,
+        };
+    }
+}
+
+/// Checks if the arm body has `same!(...)`, that is replaced differently for every variant.
+fn contains_same(tokens: &TokenStream, same_name: &Ident) -> bool {
+    let tokens = tokens.clone().into_iter().collect::<Vec<_>>();
+    let mut rest = &tokens[..];
+
+    loop {
+        rest = match rest {
+            [TokenTree::Ident(ident), bang, TokenTree::Group(args), ..]
+                if is_same_invocation(ident, bang, args, same_name) =>
+            {
+                return true
+            }
+            [TokenTree::Ident(ident), bang @ TokenTree::Punct(_), TokenTree::Group(_), rest @ ..]
+                if is_nested_invocation(ident, bang, same_name) =>
+            {
+                rest
+            }
+            [TokenTree::Group(group), ..] if contains_same(&group.stream(), same_name) => {
+                return true
+            }
+            [_, rest @ ..] => rest,
+            [] => return false,
+        };
+    }
+}
+
+/// Whether the tokens are the `same!(...)` invocation. The bare `same` identifier, e.g. a local
+/// variable, isn't the marker, even in `same != other`.
+fn is_same_invocation(ident: &Ident, bang: &TokenTree, args: &Group, same_name: &Ident) -> bool {
+    ident == same_name
+        && matches!(
+            bang,
+            TokenTree::Punct(bang) if bang.as_char() == '!' && bang.spacing() == Spacing::Alone
+        )
+        && args.delimiter() == Delimiter::Parenthesis
+}
+
+/// Whether the tokens start the nested `all_the_same!(...)`, that owns the `same!(...)` in its
+/// arms. Only the renamed constructor, e.g. `#[same(outer)]`, is replaced in it.
+fn is_nested_invocation(ident: &Ident, bang: &TokenTree, same_name: &Ident) -> bool {
+    ident == "all_the_same"
+        && same_name == "same"
+        && matches!(bang, TokenTree::Punct(bang) if bang.as_char() == '!')
+}
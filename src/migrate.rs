@@ -0,0 +1,136 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::visit::{self, Visit};
+use syn::{Arm, ExprMatch, Ident, Item, Pat, Path};
+
+pub(crate) fn expand(item: &Item) -> TokenStream {
+    let mut finder = SameArmsFinder { errors: Vec::new() };
+
+    finder.visit_item(item);
+
+    let errors = finder.errors.iter().map(syn::Error::to_compile_error);
+
+    quote! {
+        #item
+        #(#errors)*
+    }
+}
+
+/// An arm of the `Enum::Variant(binding) => body` shape.
+struct NewtypeArm<'a> {
+    arm: &'a Arm,
+    enum_path: String,
+    variant_name: &'a Ident,
+    binding: &'a Ident,
+    body: String,
+}
+
+impl<'a> NewtypeArm<'a> {
+    fn new(arm: &'a Arm) -> Option<Self> {
+        if arm.guard.is_some() {
+            return None;
+        }
+
+        let pat = match &arm.pat {
+            Pat::TupleStruct(pat) if pat.pat.elems.len() == 1 => pat,
+            _ => return None,
+        };
+
+        let binding = match &pat.pat.elems[0] {
+            Pat::Ident(binding) if binding.subpat.is_none() => &binding.ident,
+            _ => return None,
+        };
+
+        let segments = &pat.path.segments;
+
+        if segments.len() < 2 {
+            return None;
+        }
+
+        let variant_name = &segments.last()?.ident;
+
+        let enum_path = Path {
+            leading_colon: pat.path.leading_colon,
+            segments: segments.iter().take(segments.len() - 1).cloned().collect(),
+        };
+
+        Some(NewtypeArm {
+            arm,
+            enum_path: enum_path.to_token_stream().to_string(),
+            variant_name,
+            binding,
+            body: arm.body.to_token_stream().to_string(),
+        })
+    }
+
+    fn is_same(&self, other: &NewtypeArm) -> bool {
+        self.enum_path == other.enum_path
+            && self.binding == other.binding
+            && self.body == other.body
+    }
+}
+
+/// Reports matches that have several token-identical arms for different variants.
+struct SameArmsFinder {
+    errors: Vec<syn::Error>,
+}
+
+impl SameArmsFinder {
+    fn check(&mut self, expr: &ExprMatch) {
+        let arms = expr
+            .arms
+            .iter()
+            .filter_map(NewtypeArm::new)
+            .collect::<Vec<_>>();
+
+        let mut grouped = vec![false; arms.len()];
+
+        for i in 0..arms.len() {
+            if grouped[i] {
+                continue;
+            }
+
+            let group = (i..arms.len())
+                .filter(|&j| !grouped[j] && arms[i].is_same(&arms[j]))
+                .collect::<Vec<_>>();
+
+            if group.len() < 2 {
+                continue;
+            }
+
+            let variants = group
+                .iter()
+                .map(|&j| {
+                    grouped[j] = true;
+
+                    let attrs = &arms[j].arm.attrs;
+                    let variant_name = arms[j].variant_name;
+
+                    quote!(#(#attrs)* #variant_name).to_string()
+                })
+                .collect::<Vec<_>>();
+
+            let first = &arms[i];
+
+            self.errors.push(syn::Error::new_spanned(
+                &first.arm.pat,
+                format!(
+                    "arms are the same for {} variants, replace them with \
+                     `{}::[{}]({}) => ...` in `all_the_same!`",
+                    group.len(),
+                    first.enum_path,
+                    variants.join(", "),
+                    first.binding,
+                ),
+            ));
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for SameArmsFinder {
+    fn visit_expr_match(&mut self, expr: &'ast ExprMatch) {
+        self.check(expr);
+
+        visit::visit_expr_match(self, expr);
+    }
+}
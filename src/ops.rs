@@ -0,0 +1,361 @@
+use crate::utils::{coverage_off, newtype_variants, with_bound, NewtypeVariant};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use syn::{
+    parse_quote, DeriveInput, Expr, GenericParam, Generics, Ident, Token, Type, TypeParamBound,
+};
+
+const OPS: &[&str] = &[
+    "Add", "Sub", "Mul", "Div", "Rem", "BitAnd", "BitOr", "BitXor", "Shl", "Shr",
+];
+
+const UNARY_OPS: &[&str] = &["Neg", "Not"];
+
+/// Binary operator or its compound assignment counterpart, e.g. `Add` or `AddAssign`.
+struct Op<'a> {
+    name: &'a Ident,
+    method: Ident,
+    is_assign: bool,
+}
+
+impl<'a> Op<'a> {
+    fn new(name: &'a Ident) -> Self {
+        let name_str = name.to_string();
+        let base = name_str.trim_end_matches("Assign");
+        let is_assign = base.len() < name_str.len();
+
+        let method = if is_assign {
+            format_ident!("{}_assign", base.to_lowercase())
+        } else {
+            format_ident!("{}", base.to_lowercase())
+        };
+
+        Op {
+            name,
+            method,
+            is_assign,
+        }
+    }
+}
+
+enum OpsItem {
+    Op(Ident),
+    Unary(Ident),
+    /// `Index<Idx, Output = Type>`, the output has to be the same for all the payloads.
+    Index(Box<Type>, Box<Type>),
+    IndexMut(Ident),
+    Mismatch(Box<Expr>),
+}
+
+impl Parse for OpsItem {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident = input.parse::<Ident>()?;
+
+        if ident == "mismatch" {
+            input.parse::<Token!(=)>()?;
+
+            return Ok(OpsItem::Mismatch(Box::new(input.parse()?)));
+        }
+
+        if ident == "Index" {
+            input.parse::<Token!(<)>()?;
+
+            let idx = input.parse()?;
+
+            input.parse::<Token!(,)>()?;
+
+            let output = input.parse::<Ident>()?;
+
+            if output != "Output" {
+                return Err(syn::Error::new_spanned(output, "expected `Output = Type`"));
+            }
+
+            input.parse::<Token!(=)>()?;
+
+            let output = input.parse()?;
+
+            input.parse::<Token!(>)>()?;
+
+            return Ok(OpsItem::Index(Box::new(idx), Box::new(output)));
+        }
+
+        if ident == "IndexMut" {
+            return Ok(OpsItem::IndexMut(ident));
+        }
+
+        if UNARY_OPS.iter().any(|op| ident == op) {
+            return Ok(OpsItem::Unary(ident));
+        }
+
+        let is_known = OPS
+            .iter()
+            .any(|op| ident == op || ident == format!("{}Assign", op));
+
+        if !is_known {
+            return Err(syn::Error::new_spanned(
+                ident,
+                format!(
+                    "unknown `ops` option, expected one of: `{}`, their `*Assign` counterparts, \
+                     `{}`, `Index<Idx, Output = Type>`, `IndexMut`, `mismatch = expr`",
+                    OPS.join("`, `"),
+                    UNARY_OPS.join("`, `")
+                ),
+            ));
+        }
+
+        Ok(OpsItem::Op(ident))
+    }
+}
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "DelegateOps")?;
+
+    let mut ops = Vec::new();
+    let mut unary_ops = Vec::new();
+    let mut index = None;
+    let mut index_mut = None;
+    let mut mismatch = None;
+
+    for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("ops")) {
+        for item in attr.parse_args_with(Punctuated::<OpsItem, Comma>::parse_terminated)? {
+            match item {
+                OpsItem::Op(op) => ops.push(op),
+                OpsItem::Unary(op) => unary_ops.push(op),
+                OpsItem::Index(idx, output) => index = Some((idx, output)),
+                OpsItem::IndexMut(ident) => index_mut = Some(ident),
+                OpsItem::Mismatch(expr) => mismatch = Some(expr),
+            }
+        }
+    }
+
+    if ops.is_empty() && unary_ops.is_empty() && index.is_none() && index_mut.is_none() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "expected `#[ops(...)]` attribute listing the operators to delegate",
+        ));
+    }
+
+    let name = &input.ident;
+
+    let impls = ops.iter().map(|op| {
+        let Op {
+            name: op,
+            method,
+            is_assign,
+        } = Op::new(op);
+
+        // Payloads of the same variant produce a value of the same type, hence the `Output`.
+        let generics = with_param_bound(&input.generics, |ident| {
+            if is_assign {
+                parse_quote!(::core::ops::#op)
+            } else {
+                parse_quote!(::core::ops::#op<Output = #ident>)
+            }
+        });
+
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+        let arms = variants.iter().map(|variant| {
+            let variant_name = variant.name;
+            let cfg_attrs = variant.cfg_attrs();
+            let allow_deprecated = variant.allow_deprecated();
+
+            let result = if is_assign {
+                quote!(::core::ops::#op::#method(lhs, rhs))
+            } else {
+                quote!(Self::#variant_name(::core::ops::#op::#method(lhs, rhs)))
+            };
+
+            quote! {
+                #(#cfg_attrs)*
+                #allow_deprecated
+                (Self::#variant_name(lhs), Self::#variant_name(rhs)) => #result
+            }
+        });
+
+        let (track_caller, mismatch_arm) = match &mismatch {
+            // For the compound assignment `lhs` is `&mut Self` and the result is assigned to it.
+            Some(expr) if is_assign => (
+                None,
+                quote! {
+                    #[allow(unreachable_patterns)]
+                    (lhs, rhs) => *lhs = #expr,
+                },
+            ),
+            Some(expr) => (
+                None,
+                quote! {
+                    #[allow(unreachable_patterns, unused_variables)]
+                    (lhs, rhs) => #expr,
+                },
+            ),
+            None => {
+                let msg = format!("operands of `{}::{}` have different variants", name, method);
+
+                (
+                    Some(quote!(#[track_caller])),
+                    quote! {
+                        #[allow(unreachable_patterns)]
+                        _ => ::core::panic!(#msg),
+                    },
+                )
+            }
+        };
+
+        let (output, signature) = if is_assign {
+            (None, quote!(fn #method(&mut self, rhs: Self)))
+        } else {
+            (
+                Some(quote!(
+                    type Output = Self;
+                )),
+                quote!(fn #method(self, rhs: Self) -> Self),
+            )
+        };
+
+        quote! {
+            #coverage_off
+            impl #impl_generics ::core::ops::#op for #name #ty_generics #where_clause {
+                #output
+
+                #track_caller
+                #signature {
+                    match (self, rhs) {
+                        #(#arms,)*
+                        #mismatch_arm
+                    }
+                }
+            }
+        }
+    });
+
+    let unary_impls = unary_ops.iter().map(|op| {
+        let method = format_ident!("{}", op.to_string().to_lowercase());
+        let generics = with_param_bound(
+            &input.generics,
+            |ident| parse_quote!(::core::ops::#op<Output = #ident>),
+        );
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+        let arms = variants.iter().map(|variant| {
+            let variant_name = variant.name;
+            let cfg_attrs = variant.cfg_attrs();
+            let allow_deprecated = variant.allow_deprecated();
+
+            quote! {
+                #(#cfg_attrs)*
+                #allow_deprecated
+                Self::#variant_name(payload) => {
+                    Self::#variant_name(::core::ops::#op::#method(payload))
+                }
+            }
+        });
+
+        quote! {
+            #coverage_off
+            impl #impl_generics ::core::ops::#op for #name #ty_generics #where_clause {
+                type Output = Self;
+
+                fn #method(self) -> Self {
+                    match self {
+                        #(#arms)*
+                    }
+                }
+            }
+        }
+    });
+
+    let index_impls = match (index, index_mut) {
+        (None, Some(index_mut)) => {
+            return Err(syn::Error::new_spanned(
+                index_mut,
+                "`IndexMut` requires `Index<Idx, Output = Type>`, that specifies the index type",
+            ));
+        }
+        (None, None) => None,
+        (Some((idx, output)), index_mut) => {
+            let arms = index_arms(&variants, quote!(::core::ops::Index::index));
+            let generics = with_bound(
+                &input.generics,
+                parse_quote!(::core::ops::Index<#idx, Output = #output>),
+            );
+            let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+            let index_mut_impl = index_mut.map(|_| {
+                let arms = index_arms(&variants, quote!(::core::ops::IndexMut::index_mut));
+                let generics = with_bound(&generics, parse_quote!(::core::ops::IndexMut<#idx>));
+                let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+                quote! {
+                    #coverage_off
+                    impl #impl_generics ::core::ops::IndexMut<#idx> for #name #ty_generics
+                        #where_clause
+                    {
+                        fn index_mut(&mut self, index: #idx) -> &mut #output {
+                            match self {
+                                #(#arms)*
+                            }
+                        }
+                    }
+                }
+            });
+
+            Some(quote! {
+                #coverage_off
+                impl #impl_generics ::core::ops::Index<#idx> for #name #ty_generics #where_clause {
+                    type Output = #output;
+
+                    fn index(&self, index: #idx) -> &#output {
+                        match self {
+                            #(#arms)*
+                        }
+                    }
+                }
+
+                #index_mut_impl
+            })
+        }
+    };
+
+    Ok(quote! {
+        #(#impls)*
+        #(#unary_impls)*
+        #index_impls
+    })
+}
+
+/// Adds the bound, that can refer to the parameter itself, to every type parameter.
+fn with_param_bound(generics: &Generics, bound: impl Fn(&Ident) -> TypeParamBound) -> Generics {
+    let mut generics = generics.clone();
+
+    for param in &mut generics.params {
+        if let GenericParam::Type(param) = param {
+            let bound = bound(&param.ident);
+
+            param.bounds.push(bound);
+        }
+    }
+
+    generics
+}
+
+fn index_arms(variants: &[NewtypeVariant], method: TokenStream) -> Vec<TokenStream> {
+    variants
+        .iter()
+        .map(|variant| {
+            let variant_name = variant.name;
+            let cfg_attrs = variant.cfg_attrs();
+            let allow_deprecated = variant.allow_deprecated();
+
+            quote! {
+                #(#cfg_attrs)*
+                #allow_deprecated
+                Self::#variant_name(payload) => #method(payload, index),
+            }
+        })
+        .collect()
+}
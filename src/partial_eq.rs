@@ -0,0 +1,161 @@
+use crate::utils::{
+    coverage_off, mentions_type_param, newtype_variants, tokens_eq, with_bound, Flags,
+    NewtypeVariant,
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, DeriveInput, Generics};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "DelegatePartialEq")?;
+    let payloads = Flags::parse(&input.attrs, "partial_eq", &["payloads"])?.has("payloads");
+
+    let variant_flags = variants
+        .iter()
+        .map(|variant| Flags::parse(variant.attrs, "partial_eq", &["skip", "skip_payload"]))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let skipped = variant_flags
+        .iter()
+        .map(|flags| flags.has("skip"))
+        .collect::<Vec<_>>();
+
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, parse_quote!(::core::cmp::PartialEq));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let arms = variants.iter().zip(&skipped).map(|(variant, &skip)| {
+        let variant_name = variant.name;
+        let cfg_attrs = variant.cfg_attrs();
+        let allow_deprecated = variant.allow_deprecated();
+
+        if skip {
+            quote! {
+                #(#cfg_attrs)*
+                #allow_deprecated
+                (Self::#variant_name(_), Self::#variant_name(_)) => false
+            }
+        } else {
+            quote! {
+                #(#cfg_attrs)*
+                #allow_deprecated
+                (Self::#variant_name(payload), Self::#variant_name(other_payload)) => {
+                    payload == other_payload
+                }
+            }
+        }
+    });
+
+    // The variants with the same payload type share the impls, that would conflict otherwise.
+    let mut payload_groups: Vec<Vec<&NewtypeVariant>> = Vec::new();
+
+    for (variant, flags) in variants.iter().zip(&variant_flags) {
+        if !payloads || flags.has("skip") || flags.has("skip_payload") {
+            continue;
+        }
+
+        match payload_groups
+            .iter_mut()
+            .find(|group| tokens_eq(group[0].ty, variant.ty))
+        {
+            Some(group) => group.push(variant),
+            None => payload_groups.push(vec![variant]),
+        }
+    }
+
+    let payload_impls = payload_groups
+        .iter()
+        .map(|group| payload_impls(input, &generics, group, &coverage_off));
+
+    Ok(quote! {
+        #coverage_off
+        impl #impl_generics ::core::cmp::PartialEq for #name #ty_generics #where_clause {
+            fn eq(&self, other: &Self) -> bool {
+                match (self, other) {
+                    #(#arms,)*
+                    #[allow(unreachable_patterns)]
+                    _ => false,
+                }
+            }
+        }
+
+        #(#payload_impls)*
+    })
+}
+
+fn payload_impls(
+    input: &DeriveInput,
+    generics: &Generics,
+    variants: &[&NewtypeVariant],
+    coverage_off: &Option<TokenStream>,
+) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let ty = variants[0].ty;
+    let cfg_attr = group_cfg_attr(variants);
+
+    let arms = variants.iter().map(|variant| {
+        let variant_name = variant.name;
+        let cfg_attrs = variant.cfg_attrs();
+        let allow_deprecated = variant.allow_deprecated();
+
+        quote! {
+            #(#cfg_attrs)*
+            #allow_deprecated
+            Self::#variant_name(payload) => payload == other
+        }
+    });
+
+    // `impl<T> PartialEq<Enum<T>> for T` is rejected by the orphan rules, and so are `&T` and
+    // `Box<T>`, so the reverse impl is only generated for the payloads without type parameters.
+    let reverse_impl = (!mentions_type_param(generics, ty)).then(|| {
+        quote! {
+            #cfg_attr
+            #coverage_off
+            impl #impl_generics ::core::cmp::PartialEq<#name #ty_generics> for #ty #where_clause {
+                fn eq(&self, other: &#name #ty_generics) -> bool {
+                    other == self
+                }
+            }
+        }
+    });
+
+    quote! {
+        #cfg_attr
+        #coverage_off
+        impl #impl_generics ::core::cmp::PartialEq<#ty> for #name #ty_generics #where_clause {
+            fn eq(&self, other: &#ty) -> bool {
+                match self {
+                    #(#arms,)*
+                    #[allow(unreachable_patterns)]
+                    _ => false,
+                }
+            }
+        }
+
+        #reverse_impl
+    }
+}
+
+/// The impls shared by the variants exist if any of the variants does.
+fn group_cfg_attr(variants: &[&NewtypeVariant]) -> Option<TokenStream> {
+    if variants
+        .iter()
+        .any(|variant| variant.cfg_attrs().next().is_none())
+    {
+        return None;
+    }
+
+    let predicates = variants.iter().map(|variant| {
+        let predicates = variant.cfg_attrs().map(|attr| {
+            let predicate = &attr.tokens;
+
+            quote!(all #predicate)
+        });
+
+        quote!(all(#(#predicates),*))
+    });
+
+    Some(quote!(#[cfg(any(#(#predicates),*))]))
+}
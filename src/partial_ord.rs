@@ -0,0 +1,81 @@
+use crate::utils::{coverage_off, newtype_variants, with_bound, Flags};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, DeriveInput};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "DelegatePartialOrd")?;
+    let variant_order =
+        Flags::parse(&input.attrs, "partial_ord", &["variant_order"])?.has("variant_order");
+
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, parse_quote!(::core::cmp::PartialOrd));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let arms = variants
+        .iter()
+        .map(|variant| {
+            let variant_name = variant.name;
+            let cfg_attrs = variant.cfg_attrs();
+            let allow_deprecated = variant.allow_deprecated();
+
+            Ok(
+                if Flags::parse(variant.attrs, "partial_ord", &["skip"])?.has("skip") {
+                    quote! {
+                        #(#cfg_attrs)*
+                        #allow_deprecated
+                        (Self::#variant_name(_), Self::#variant_name(_)) => ::core::option::Option::None
+                    }
+                } else {
+                    quote! {
+                        #(#cfg_attrs)*
+                        #allow_deprecated
+                        (Self::#variant_name(payload), Self::#variant_name(other_payload)) => {
+                            ::core::cmp::PartialOrd::partial_cmp(payload, other_payload)
+                        }
+                    }
+                },
+            )
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    // The declaration order is used for the different variants, like in the std derive. Indices
+    // of the variants disabled with `#[cfg]` are skipped, which doesn't affect the order.
+    let fallback = if variant_order {
+        let index_arms = variants.iter().enumerate().map(|(index, variant)| {
+            let variant_name = variant.name;
+            let cfg_attrs = variant.cfg_attrs();
+            let allow_deprecated = variant.allow_deprecated();
+
+            quote! {
+                #(#cfg_attrs)*
+                #allow_deprecated
+                Self::#variant_name(_) => #index
+            }
+        });
+
+        quote! {{
+            let index = |value: &Self| match value {
+                #(#index_arms,)*
+            };
+
+            ::core::cmp::PartialOrd::partial_cmp(&index(self), &index(other))
+        }}
+    } else {
+        quote!(::core::option::Option::None)
+    };
+
+    Ok(quote! {
+        #coverage_off
+        impl #impl_generics ::core::cmp::PartialOrd for #name #ty_generics #where_clause {
+            fn partial_cmp(&self, other: &Self) -> ::core::option::Option<::core::cmp::Ordering> {
+                match (self, other) {
+                    #(#arms,)*
+                    #[allow(unreachable_patterns)]
+                    _ => #fallback,
+                }
+            }
+        }
+    })
+}
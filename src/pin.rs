@@ -0,0 +1,288 @@
+use crate::utils::{coverage_off, newtype_variants, NewtypeVariant};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use syn::{parse_quote, DeriveInput, GenericParam, Ident, Token};
+
+/// Options of the `#[pin_project(unsafe_project, project = Proj, project_ref = ProjRef)]`
+/// attribute.
+struct Projection {
+    unsafe_project: Option<Ident>,
+    project: Ident,
+    project_ref: Ident,
+}
+
+enum ProjectionOption {
+    UnsafeProject(Ident),
+    Name { key: Ident, name: Ident },
+}
+
+impl Parse for ProjectionOption {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key = input.parse::<Ident>()?;
+
+        if key == "unsafe_project" {
+            return Ok(ProjectionOption::UnsafeProject(key));
+        }
+
+        if key != "project" && key != "project_ref" {
+            return Err(syn::Error::new_spanned(
+                key,
+                "unknown `pin_project` option, expected one of: `unsafe_project`, `project`, \
+                 `project_ref`",
+            ));
+        }
+
+        input.parse::<Token!(=)>()?;
+
+        Ok(ProjectionOption::Name {
+            key,
+            name: input.parse()?,
+        })
+    }
+}
+
+/// Name of the projection enum if the enum has the `#[pin_project]` attribute of `PinProject`.
+/// The other derives project the pinned receivers with the generated `project` method then, so
+/// the payloads don't have to be `Unpin`.
+pub(crate) fn projection(input: &DeriveInput) -> syn::Result<Option<Ident>> {
+    if input
+        .attrs
+        .iter()
+        .any(|attr| attr.path.is_ident("pin_project"))
+    {
+        Ok(Some(projection_options(input)?.project))
+    } else {
+        Ok(None)
+    }
+}
+
+fn projection_options(input: &DeriveInput) -> syn::Result<Projection> {
+    let mut projection = Projection {
+        unsafe_project: None,
+        project: format_ident!("{}Proj", input.ident),
+        project_ref: format_ident!("{}ProjRef", input.ident),
+    };
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("pin_project") && !attr.tokens.is_empty())
+    {
+        let options =
+            attr.parse_args_with(Punctuated::<ProjectionOption, Comma>::parse_terminated)?;
+
+        for option in options {
+            match option {
+                ProjectionOption::UnsafeProject(ident) => projection.unsafe_project = Some(ident),
+                ProjectionOption::Name { key, name } if key == "project" => {
+                    projection.project = name
+                }
+                ProjectionOption::Name { name, .. } => projection.project_ref = name,
+            }
+        }
+    }
+
+    Ok(projection)
+}
+
+/// Items that make the projection of the pinned enum to the pinned payloads sound. The enum must
+/// only be `Unpin` if all its payloads are, and it must not implement `Drop`, that could move the
+/// payloads. The same way as `pin-project` does it, the former is enforced by the `Unpin` impl,
+/// that conflicts with a manual one, and the latter by the trait, that conflicts with the blanket
+/// impl for the `Drop` types.
+pub(crate) fn guards(input: &DeriveInput, variants: &[NewtypeVariant]) -> TokenStream {
+    let name = &input.ident;
+
+    let unpin_fields = variants.iter().map(|variant| {
+        let cfg_attrs = variant.cfg_attrs();
+        let field_name = format_ident!("__{}", variant.name);
+        let ty = variant.ty;
+
+        quote!(#(#cfg_attrs)* #field_name: #ty)
+    });
+
+    // The unused parameters are wrapped in `fn() -> T`, that is always `Unpin`.
+    let phantom_params = input.generics.params.iter().map(|param| match param {
+        GenericParam::Type(param) => {
+            let ident = &param.ident;
+
+            quote!(fn() -> #ident)
+        }
+        GenericParam::Lifetime(param) => {
+            let lifetime = &param.lifetime;
+
+            quote!(&#lifetime ())
+        }
+        GenericParam::Const(_) => quote!(()),
+    });
+
+    let mut origin_generics = input.generics.clone();
+
+    origin_generics.params.insert(0, parse_quote!('__pin));
+
+    let (origin_impl_generics, origin_ty_generics, _) = origin_generics.split_for_impl();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let unpin_where = match where_clause {
+        Some(where_clause) => {
+            let predicates = &where_clause.predicates;
+
+            quote!(where __Origin #origin_ty_generics: ::core::marker::Unpin, #predicates)
+        }
+        None => quote!(where __Origin #origin_ty_generics: ::core::marker::Unpin),
+    };
+
+    quote! {
+        #[allow(dead_code, non_snake_case)]
+        struct __Origin #origin_impl_generics #where_clause {
+            __pin: ::core::marker::PhantomData<(&'__pin (), #(#phantom_params,)*)>,
+            #(#unpin_fields,)*
+        }
+
+        // The lifetime makes the bound non-trivial, so it's not an error if it doesn't hold.
+        #[allow(private_bounds)]
+        impl #origin_impl_generics ::core::marker::Unpin for #name #ty_generics
+            #unpin_where
+        {
+        }
+
+        trait MustNotImplDrop {}
+
+        #[allow(drop_bounds)]
+        impl<T: ::core::ops::Drop> MustNotImplDrop for T {}
+
+        impl #impl_generics MustNotImplDrop for #name #ty_generics #where_clause {}
+    }
+}
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "PinProject")?;
+    let guards = guards(input, &variants);
+
+    let Projection {
+        unsafe_project,
+        project,
+        project_ref,
+    } = projection_options(input)?;
+
+    // The projection is unsafe code, so it has to be opted into. The `unsafe` blocks are spanned
+    // to the option, so `unsafe_code` lints of the crate point at it.
+    let span = match unsafe_project {
+        Some(unsafe_project) => unsafe_project.span(),
+        None => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "`PinProject` generates `unsafe` code, opt into it with \
+                 `#[pin_project(unsafe_project)]`",
+            ))
+        }
+    };
+
+    let get_mut = quote_spanned!(span=> unsafe { ::core::pin::Pin::get_unchecked_mut(self) });
+
+    let name = &input.ident;
+    let vis = &input.vis;
+
+    let mut proj_generics = input.generics.clone();
+
+    proj_generics.params.insert(0, parse_quote!('__pin));
+
+    let (proj_impl_generics, proj_ty_generics, _) = proj_generics.split_for_impl();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let proj_variants = |mutability| {
+        variants
+            .iter()
+            .map(move |variant| {
+                let variant_name = variant.name;
+                let cfg_attrs = variant.cfg_attrs();
+                let ty = variant.ty;
+
+                quote! {
+                    #(#cfg_attrs)*
+                    #variant_name(::core::pin::Pin<&'__pin #mutability #ty>)
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let pin_payload = quote_spanned!(span=> unsafe { ::core::pin::Pin::new_unchecked(payload) });
+
+    let proj_arms = |proj_name: &Ident| {
+        variants
+            .iter()
+            .map(|variant| {
+                let variant_name = variant.name;
+                let cfg_attrs = variant.cfg_attrs();
+                let allow_deprecated = variant.allow_deprecated();
+
+                quote! {
+                    #(#cfg_attrs)*
+                    #allow_deprecated
+                    Self::#variant_name(payload) => {
+                        // SAFETY: the payload is pinned along with the enum, see the guards.
+                        #proj_name::#variant_name(#pin_payload)
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let proj_mut_variants = proj_variants(Some(<Token!(mut)>::default()));
+    let proj_ref_variants = proj_variants(None);
+    let project_arms = proj_arms(&project);
+    let project_ref_arms = proj_arms(&project_ref);
+
+    let project_doc = format!(
+        "Projection of the pinned [`{}`] to the pinned payload, returned by [`{}::project`].",
+        name, name
+    );
+    let project_ref_doc = format!(
+        "Projection of the pinned [`{}`] to the pinned payload, returned by [`{}::project_ref`].",
+        name, name
+    );
+
+    Ok(quote! {
+        #[doc = #project_doc]
+        #[allow(dead_code)]
+        #vis enum #project #proj_impl_generics #where_clause {
+            #(#proj_mut_variants,)*
+        }
+
+        #[doc = #project_ref_doc]
+        #[allow(dead_code)]
+        #vis enum #project_ref #proj_impl_generics #where_clause {
+            #(#proj_ref_variants,)*
+        }
+
+        const _: () = {
+            #guards
+
+            #coverage_off
+            impl #impl_generics #name #ty_generics #where_clause {
+                /// Projects the pinned enum to the pinned payload.
+                #vis fn project<'__pin>(
+                    self: ::core::pin::Pin<&'__pin mut Self>,
+                ) -> #project #proj_ty_generics {
+                    // SAFETY: the payloads are never moved out of the reference.
+                    match #get_mut {
+                        #(#project_arms,)*
+                    }
+                }
+
+                /// Projects the pinned enum to the pinned payload by shared reference.
+                #vis fn project_ref<'__pin>(
+                    self: ::core::pin::Pin<&'__pin Self>,
+                ) -> #project_ref #proj_ty_generics {
+                    match ::core::pin::Pin::get_ref(self) {
+                        #(#project_ref_arms,)*
+                    }
+                }
+            }
+        };
+    })
+}
@@ -0,0 +1,109 @@
+use crate::utils::{coverage_off, newtype_variants, with_bound, Flags};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_quote, DeriveInput};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "RefView")?;
+    let to_owned = Flags::parse(&input.attrs, "ref_view", &["to_owned"])?.has("to_owned");
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let ref_name = format_ident!("{}Ref", name);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut ref_generics = input.generics.clone();
+
+    ref_generics.params.insert(0, parse_quote!('__ref));
+
+    let (ref_impl_generics, ref_ty_generics, _) = ref_generics.split_for_impl();
+
+    let ref_variants = variants.iter().map(|variant| {
+        let variant_name = variant.name;
+        let ty = variant.ty;
+        let cfg_attrs = variant.cfg_attrs();
+        let deprecated_attrs = variant.deprecated_attrs();
+        let doc = format!("Borrowed [`{}::{}`] variant.", name, variant_name);
+
+        quote! {
+            #(#cfg_attrs)*
+            #(#deprecated_attrs)*
+            #[doc = #doc]
+            #variant_name(&'__ref #ty)
+        }
+    });
+
+    let as_ref_arms = variants.iter().map(|variant| {
+        let variant_name = variant.name;
+        let cfg_attrs = variant.cfg_attrs();
+        let allow_deprecated = variant.allow_deprecated();
+
+        quote! {
+            #(#cfg_attrs)*
+            #allow_deprecated
+            Self::#variant_name(value) => #ref_name::#variant_name(value)
+        }
+    });
+
+    let enum_doc = format!("Borrowed view of [`{}`].", name);
+    let as_ref_doc = format!("Returns a borrowed view of the [`{}`].", name);
+
+    let to_owned = to_owned.then(|| {
+        let to_owned_arms = variants.iter().map(|variant| {
+            let variant_name = variant.name;
+            let cfg_attrs = variant.cfg_attrs();
+            let allow_deprecated = variant.allow_deprecated();
+
+            quote! {
+                #(#cfg_attrs)*
+                #allow_deprecated
+                Self::#variant_name(value) => #name::#variant_name(::core::clone::Clone::clone(value))
+            }
+        });
+
+        let to_owned_doc = format!("Clones the borrowed payload into an owned [`{}`].", name);
+        let owned_generics = with_bound(&ref_generics, parse_quote!(::core::clone::Clone));
+        let (owned_impl_generics, _, owned_where_clause) = owned_generics.split_for_impl();
+
+        quote! {
+            #coverage_off
+            impl #owned_impl_generics #ref_name #ref_ty_generics #owned_where_clause {
+                #[doc = #to_owned_doc]
+                #vis fn to_owned(self) -> #name #ty_generics {
+                    match self {
+                        #(#to_owned_arms,)*
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        #[doc = #enum_doc]
+        #vis enum #ref_name #ref_impl_generics #where_clause {
+            #(#ref_variants,)*
+        }
+
+        #coverage_off
+        impl #ref_impl_generics ::core::clone::Clone for #ref_name #ref_ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl #ref_impl_generics ::core::marker::Copy for #ref_name #ref_ty_generics #where_clause {}
+
+        #coverage_off
+        impl #impl_generics #name #ty_generics #where_clause {
+            #[doc = #as_ref_doc]
+            #vis fn as_ref<'__ref>(&'__ref self) -> #ref_name #ref_ty_generics {
+                match self {
+                    #(#as_ref_arms,)*
+                }
+            }
+        }
+
+        #to_owned
+    })
+}
@@ -0,0 +1,77 @@
+use crate::match_expr::parse_enum_path;
+use crate::variants::{check_duplicates, parse_variant_list, SetVariant};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use syn::{bracketed, Expr, Path, Token};
+
+/// `lhs, rhs, Enum::[A, B]` arguments.
+pub(crate) struct Args {
+    lhs: Expr,
+    rhs: Expr,
+    enum_path: Path,
+    variants: Punctuated<SetVariant, Comma>,
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let variants_list_content;
+
+        let args = Args {
+            lhs: input.parse()?,
+            rhs: {
+                input.parse::<Token!(,)>()?;
+
+                input.parse()?
+            },
+            enum_path: {
+                input.parse::<Token!(,)>()?;
+
+                input.call(parse_enum_path)?
+            },
+            variants: {
+                bracketed!(variants_list_content in input);
+
+                let variants = variants_list_content.call(parse_variant_list)?;
+
+                check_duplicates(&variants)?;
+
+                variants
+            },
+        };
+
+        input.parse::<Option<Token!(,)>>()?;
+
+        Ok(args)
+    }
+}
+
+pub(crate) fn expand(args: &Args) -> TokenStream {
+    let Args {
+        lhs,
+        rhs,
+        enum_path,
+        variants,
+    } = args;
+
+    // `{ .. }` matches the variants of any kind, so the payloads don't need to be known.
+    let arms = variants.iter().map(|variant| {
+        let attrs = &variant.attrs;
+        let name = &variant.name;
+
+        quote! {
+            #(#attrs)*
+            (#enum_path::#name { .. }, #enum_path::#name { .. }) => true,
+        }
+    });
+
+    quote! {
+        match (&#lhs, &#rhs) {
+            #(#arms)*
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+}
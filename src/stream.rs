@@ -0,0 +1,140 @@
+use crate::forward::forward_trait_fns;
+use crate::pin::projection;
+use crate::utils::{
+    coverage_off, first_payload_ty, newtype_variants, turbofish, with_bound, with_same_assoc,
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, DeriveInput, Path, Type};
+
+pub(crate) fn expand_stream(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "DelegateStream")?;
+    let trait_path: Path = parse_quote!(::futures_core::Stream);
+    let first_ty = first_payload_ty(input, &variants, "Item")?;
+
+    let name = &input.ident;
+
+    let projection = projection(input)?;
+
+    // The pinned receiver is projected to the pinned payload, which requires it to be `Unpin`,
+    // unless the enum derives `PinProject`.
+    let mut generics = with_bound(&input.generics, parse_quote!(#trait_path));
+
+    if projection.is_none() {
+        generics = with_bound(&generics, parse_quote!(::core::marker::Unpin));
+    }
+
+    let generics = with_same_assoc(
+        &generics,
+        &variants,
+        first_ty,
+        parse_quote!(#trait_path<Item = <#first_ty as #trait_path>::Item>),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let fns = forward_trait_fns(
+        &variants,
+        &trait_path,
+        vec![
+            parse_quote! {
+                fn poll_next(
+                    self: ::core::pin::Pin<&mut Self>,
+                    cx: &mut ::core::task::Context<'_>,
+                ) -> ::core::task::Poll<::core::option::Option<Self::Item>>
+            },
+            parse_quote! {
+                fn size_hint(&self) -> (usize, ::core::option::Option<usize>)
+            },
+        ],
+        projection.as_ref(),
+    )?;
+
+    Ok(quote! {
+        #coverage_off
+        impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+            type Item = <#first_ty as #trait_path>::Item;
+
+            #(#fns)*
+        }
+    })
+}
+
+pub(crate) fn expand_sink(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "DelegateSink")?;
+    let first_ty = first_payload_ty(input, &variants, "Error")?;
+
+    let item = match input.attrs.iter().find(|attr| attr.path.is_ident("sink")) {
+        Some(attr) => attr.parse_args::<Type>()?,
+        None => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "expected `#[sink(Type)]` attribute with the type of the sink items",
+            ))
+        }
+    };
+
+    let trait_path: Path = parse_quote!(::futures_sink::Sink<#item>);
+    let name = &input.ident;
+
+    let projection = projection(input)?;
+    let mut generics = with_bound(&input.generics, parse_quote!(#trait_path));
+
+    if projection.is_none() {
+        generics = with_bound(&generics, parse_quote!(::core::marker::Unpin));
+    }
+
+    let generics = with_same_assoc(
+        &generics,
+        &variants,
+        first_ty,
+        parse_quote!(::futures_sink::Sink<#item, Error = <#first_ty as #trait_path>::Error>),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let poll: Type = parse_quote! {
+        ::core::task::Poll<::core::result::Result<(), Self::Error>>
+    };
+
+    let fns = forward_trait_fns(
+        &variants,
+        &turbofish(&trait_path),
+        vec![
+            parse_quote! {
+                fn poll_ready(
+                    self: ::core::pin::Pin<&mut Self>,
+                    cx: &mut ::core::task::Context<'_>,
+                ) -> #poll
+            },
+            parse_quote! {
+                fn start_send(
+                    self: ::core::pin::Pin<&mut Self>,
+                    item: #item,
+                ) -> ::core::result::Result<(), Self::Error>
+            },
+            parse_quote! {
+                fn poll_flush(
+                    self: ::core::pin::Pin<&mut Self>,
+                    cx: &mut ::core::task::Context<'_>,
+                ) -> #poll
+            },
+            parse_quote! {
+                fn poll_close(
+                    self: ::core::pin::Pin<&mut Self>,
+                    cx: &mut ::core::task::Context<'_>,
+                ) -> #poll
+            },
+        ],
+        projection.as_ref(),
+    )?;
+
+    Ok(quote! {
+        #coverage_off
+        impl #impl_generics #trait_path for #name #ty_generics #where_clause {
+            type Error = <#first_ty as #trait_path>::Error;
+
+            #(#fns)*
+        }
+    })
+}
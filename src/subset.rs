@@ -0,0 +1,85 @@
+use crate::utils::{coverage_off, newtype_variants, turbofish};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, Path};
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "Subset")?;
+    let supersets = supersets(input)?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let impls = supersets.iter().map(|superset| {
+        let superset_expr = turbofish(superset);
+
+        let narrowing_arms = variants.iter().map(|variant| {
+            let variant_name = variant.name;
+            let cfg_attrs = variant.cfg_attrs();
+            let allow_deprecated = variant.allow_deprecated();
+
+            quote! {
+                #(#cfg_attrs)*
+                #allow_deprecated
+                #superset_expr::#variant_name(value) => ::core::result::Result::Ok(Self::#variant_name(value))
+            }
+        });
+
+        let widening_arms = variants.iter().map(|variant| {
+            let variant_name = variant.name;
+            let cfg_attrs = variant.cfg_attrs();
+            let allow_deprecated = variant.allow_deprecated();
+
+            quote! {
+                #(#cfg_attrs)*
+                #allow_deprecated
+                #name::#variant_name(value) => #superset_expr::#variant_name(::core::convert::Into::into(value))
+            }
+        });
+
+        quote! {
+            #coverage_off
+            impl #impl_generics ::core::convert::TryFrom<#superset> for #name #ty_generics #where_clause {
+                type Error = #superset;
+
+                fn try_from(value: #superset) -> ::core::result::Result<Self, Self::Error> {
+                    match value {
+                        #(#narrowing_arms,)*
+                        #[allow(unreachable_patterns)]
+                        other => ::core::result::Result::Err(other),
+                    }
+                }
+            }
+
+            #coverage_off
+            impl #impl_generics ::core::convert::From<#name #ty_generics> for #superset #where_clause {
+                fn from(value: #name #ty_generics) -> Self {
+                    match value {
+                        #(#widening_arms,)*
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(quote!(#(#impls)*))
+}
+
+fn supersets(input: &DeriveInput) -> syn::Result<Vec<Path>> {
+    let supersets = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("subset"))
+        .map(|attr| attr.parse_args())
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    if supersets.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "expected `#[subset(Enum)]` attribute naming the enum this one is a subset of",
+        ));
+    }
+
+    Ok(supersets)
+}
@@ -0,0 +1,218 @@
+use crate::match_expr::parse_enum_path;
+use crate::utils::{coverage_off, CratePath};
+use crate::variants;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::token::{Bracket, Comma};
+use syn::{
+    bracketed, parse_quote, Attribute, Generics, Ident, ItemEnum, Path, Token, Variant, Visibility,
+};
+
+/// `#[attrs] pub Name<T> = [A(T), B(u8)]` input of `sum_type!`, or
+/// `#[attrs] pub Name<T> = Source::[A(T), B(u8)] + [C(u16)]` input of `compose!`. Both can be
+/// preceded by `crate = path;`.
+pub(crate) struct SumType {
+    krate: CratePath,
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    generics: Generics,
+    parts: Vec<Part>,
+}
+
+/// Part of the variant list, that is either new or restates the variants of the source enum, e.g.
+/// `Source::[A(T), B(u8)]`.
+struct Part {
+    source: Option<Path>,
+    variants: Punctuated<Variant, Comma>,
+}
+
+impl Parse for Part {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let source = if input.peek(Bracket) {
+            None
+        } else {
+            Some(input.call(parse_enum_path)?)
+        };
+
+        let content;
+
+        bracketed!(content in input);
+
+        Ok(Part {
+            source,
+            variants: content.parse_terminated(Variant::parse)?,
+        })
+    }
+}
+
+impl Parse for SumType {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let krate = if CratePath::peek(input) {
+            let krate = input.parse()?;
+
+            input.parse::<Token!(;)>()?;
+
+            krate
+        } else {
+            CratePath::default()
+        };
+
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        let name = input.parse()?;
+        let mut generics: Generics = input.parse()?;
+
+        generics.where_clause = input.parse()?;
+
+        input.parse::<Token!(=)>()?;
+
+        let mut parts = vec![input.parse()?];
+
+        while input.peek(Token!(+)) {
+            input.parse::<Token!(+)>()?;
+            parts.push(input.parse()?);
+        }
+
+        input.parse::<Option<Token!(;)>>()?;
+
+        Ok(SumType {
+            krate,
+            attrs,
+            vis,
+            name,
+            generics,
+            parts,
+        })
+    }
+}
+
+pub(crate) fn expand(sum_type: &SumType) -> syn::Result<TokenStream> {
+    if sum_type.parts.len() > 1 || sum_type.parts[0].source.is_some() {
+        return Err(syn::Error::new_spanned(
+            &sum_type.name,
+            "`sum_type!` takes a single list of the variants, the enums are merged with `compose!`",
+        ));
+    }
+
+    Ok(expand_enum(sum_type))
+}
+
+/// Expands `compose!`: the enum, along with the conversions from the source enums and back.
+pub(crate) fn expand_compose(sum_type: &SumType) -> syn::Result<TokenStream> {
+    if sum_type.parts.iter().all(|part| part.source.is_none()) {
+        return Err(syn::Error::new_spanned(
+            &sum_type.name,
+            "expected the variants of an enum to merge, e.g. `Enum::[A(T), B(u8)]`, the enums \
+             are defined from a list of the variants with `sum_type!`",
+        ));
+    }
+
+    let coverage_off = coverage_off(&sum_type.attrs)?;
+    let name = &sum_type.name;
+    let (impl_generics, ty_generics, where_clause) = sum_type.generics.split_for_impl();
+
+    let mut conversions = TokenStream::new();
+
+    for part in &sum_type.parts {
+        let source = match &part.source {
+            Some(source) => source,
+            None => continue,
+        };
+
+        let variants = part
+            .variants
+            .iter()
+            .map(|variant| {
+                let cfg_attrs = variant
+                    .attrs
+                    .iter()
+                    .filter(|attr| attr.path.is_ident("cfg"))
+                    .collect::<Vec<_>>();
+
+                (&variant.ident, cfg_attrs)
+            })
+            .collect::<Vec<_>>();
+
+        let from_arms = variants.iter().map(|(variant_name, cfg_attrs)| {
+            quote! {
+                #(#cfg_attrs)*
+                #source::#variant_name(payload) => Self::#variant_name(payload)
+            }
+        });
+
+        let try_from_arms = variants.iter().map(|(variant_name, cfg_attrs)| {
+            quote! {
+                #(#cfg_attrs)*
+                #name::#variant_name(payload) => {
+                    ::core::result::Result::Ok(#source::#variant_name(payload))
+                }
+            }
+        });
+
+        // The list is checked against the source enum by the exhaustive match of `From`.
+        conversions.extend(quote! {
+            #coverage_off
+            impl #impl_generics ::core::convert::From<#source> for #name #ty_generics
+                #where_clause
+            {
+                fn from(value: #source) -> Self {
+                    match value {
+                        #(#from_arms,)*
+                    }
+                }
+            }
+
+            #coverage_off
+            impl #impl_generics ::core::convert::TryFrom<#name #ty_generics> for #source
+                #where_clause
+            {
+                type Error = #name #ty_generics;
+
+                fn try_from(
+                    value: #name #ty_generics,
+                ) -> ::core::result::Result<Self, Self::Error> {
+                    match value {
+                        #(#try_from_arms,)*
+                        #[allow(unreachable_patterns)]
+                        other => ::core::result::Result::Err(other),
+                    }
+                }
+            }
+        });
+    }
+
+    let item = expand_enum(sum_type);
+
+    Ok(quote! {
+        #item
+        #conversions
+    })
+}
+
+fn expand_enum(sum_type: &SumType) -> TokenStream {
+    let SumType {
+        krate,
+        attrs,
+        vis,
+        name,
+        generics,
+        parts,
+    } = sum_type;
+
+    let variants = parts.iter().flat_map(|part| &part.variants);
+
+    // The conversions and the accessors are generated by the derives, which also check the
+    // variants, and the enum is registered the same way as with `#[variants]`.
+    let item: ItemEnum = parse_quote! {
+        #[derive(#krate::VariantFrom, #krate::Accessors)]
+        #(#attrs)*
+        #vis enum #name #generics {
+            #(#variants,)*
+        }
+    };
+
+    variants::expand(&item, krate)
+}
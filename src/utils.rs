@@ -0,0 +1,331 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned, ToTokens};
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use syn::visit::{self, Visit};
+use syn::{
+    parse_quote, Attribute, Data, DeriveInput, Fields, GenericParam, Generics, Ident, Path,
+    PathArguments, Token, Type, TypeParamBound,
+};
+
+pub(crate) struct NewtypeVariant<'a> {
+    pub(crate) attrs: &'a [Attribute],
+    pub(crate) name: &'a Ident,
+    pub(crate) ty: &'a Type,
+}
+
+impl NewtypeVariant<'_> {
+    pub(crate) fn cfg_attrs(&self) -> impl Iterator<Item = &Attribute> {
+        self.attrs.iter().filter(|attr| attr.path.is_ident("cfg"))
+    }
+
+    /// `#[cfg_attr(docsrs, doc(cfg(..)))]` for each `#[cfg(..)]` of the variant, so docs.rs shows
+    /// the requirements of the generated public items, that are gated the same way.
+    pub(crate) fn doc_cfg_attrs(&self) -> impl Iterator<Item = TokenStream> + '_ {
+        self.cfg_attrs().map(|attr| {
+            let predicate = &attr.tokens;
+
+            quote!(#[cfg_attr(docsrs, doc(cfg #predicate))])
+        })
+    }
+
+    pub(crate) fn deprecated_attrs(&self) -> impl Iterator<Item = &Attribute> {
+        self.attrs
+            .iter()
+            .filter(|attr| attr.path.is_ident("deprecated"))
+    }
+
+    /// Uses of deprecated variants in the generated code are reported at the variant declaration,
+    /// where users can't silence them, so the arms that mention such variants allow them.
+    pub(crate) fn allow_deprecated(&self) -> Option<TokenStream> {
+        self.deprecated_attrs()
+            .next()
+            .map(|_| quote!(#[allow(deprecated)]))
+    }
+}
+
+pub(crate) fn newtype_variants<'a>(
+    input: &'a DeriveInput,
+    derive_name: &str,
+) -> syn::Result<Vec<NewtypeVariant<'a>>> {
+    let data = match &input.data {
+        Data::Enum(data) => data,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                format!("`{}` can only be derived for enums", derive_name),
+            ))
+        }
+    };
+
+    data.variants
+        .iter()
+        .map(|variant| match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => Ok(NewtypeVariant {
+                attrs: &variant.attrs,
+                name: &variant.ident,
+                ty: &fields.unnamed[0].ty,
+            }),
+            _ => Err(syn::Error::new_spanned(
+                variant,
+                format!(
+                    "`{}` only supports variants with a single unnamed field",
+                    derive_name
+                ),
+            )),
+        })
+        .collect()
+}
+
+/// The associated type of the trait is taken from the payload of the first variant, that is
+/// always compiled in. The other payloads have to have the same type. The payloads that are type
+/// parameters of the enum are preferred, so the bounds of the other payloads, e.g. `&'a mut T`,
+/// refer to `<T as Trait>::Assoc`, rather than `T` being bound to the type derived from itself.
+pub(crate) fn first_payload_ty<'a>(
+    input: &DeriveInput,
+    variants: &[NewtypeVariant<'a>],
+    assoc_name: &str,
+) -> syn::Result<&'a Type> {
+    let mut payload_tys = variants
+        .iter()
+        .filter(|variant| variant.cfg_attrs().next().is_none())
+        .map(|variant| variant.ty);
+
+    payload_tys
+        .clone()
+        .find(|ty| is_type_param(&input.generics, ty))
+        .or_else(|| payload_tys.next())
+        .ok_or_else(|| {
+            syn::Error::new_spanned(
+                &input.ident,
+                format!(
+                    "expected a variant without `#[cfg]`, that the `{}` type is taken from",
+                    assoc_name
+                ),
+            )
+        })
+}
+
+/// Adds the bound to every type parameter, like std derives do.
+pub(crate) fn with_bound(generics: &Generics, bound: TypeParamBound) -> Generics {
+    let mut generics = generics.clone();
+
+    for param in &mut generics.params {
+        if let GenericParam::Type(param) = param {
+            param.bounds.push(bound.clone());
+        }
+    }
+
+    generics
+}
+
+/// Adds the bound, that fixes the associated type to the one of the [`first_payload_ty`], to the
+/// types of the other payloads. It can't be added to the type parameters, like with
+/// [`with_bound`], as `T: Trait<Assoc = <T as Trait>::Assoc>` can't be resolved.
+pub(crate) fn with_same_assoc(
+    generics: &Generics,
+    variants: &[NewtypeVariant],
+    first_ty: &Type,
+    bound: TypeParamBound,
+) -> Generics {
+    let mut generics = generics.clone();
+    let where_clause = generics.make_where_clause();
+
+    for variant in variants {
+        let ty = variant.ty;
+
+        if variant.cfg_attrs().next().is_none() && !tokens_eq(ty, first_ty) {
+            where_clause.predicates.push(parse_quote!(#ty: #bound));
+        }
+    }
+
+    generics
+}
+
+/// Turns `Enum<T>` into `Enum::<T>`, so the path can be used in patterns and expressions.
+pub(crate) fn turbofish(path: &Path) -> Path {
+    let mut path = path.clone();
+
+    for segment in &mut path.segments {
+        if let PathArguments::AngleBracketed(args) = &mut segment.arguments {
+            args.colon2_token.get_or_insert_with(<Token!(::)>::default);
+        }
+    }
+
+    path
+}
+
+/// Converts `CamelCase` variant names to `snake_case`, e.g. for the names of generated methods.
+/// Runs of uppercase letters are kept together, so `HTTPServer` becomes `http_server`.
+pub(crate) fn snake_case(ident: &Ident) -> String {
+    let name = ident.to_string();
+    let chars = name.trim_start_matches("r#").chars().collect::<Vec<_>>();
+    let mut snake_case = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+
+            // A word starts after a lowercase letter or a digit, or at the last letter of an
+            // acronym, that is followed by a lowercase one, e.g. `S` in `HTTPServer`.
+            if prev != '_' && (!prev.is_uppercase() || next_is_lower) {
+                snake_case.push('_');
+            }
+        }
+
+        snake_case.extend(c.to_lowercase());
+    }
+
+    snake_case
+}
+
+/// Flag options specified in the derive's helper attributes, e.g. `#[ref_view(to_owned)]`.
+pub(crate) struct Flags(Vec<Ident>);
+
+impl Flags {
+    pub(crate) fn parse(attrs: &[Attribute], attr_name: &str, known: &[&str]) -> syn::Result<Self> {
+        let mut flags = Vec::new();
+
+        for attr in attrs.iter().filter(|attr| attr.path.is_ident(attr_name)) {
+            // Flags can be keywords, like `const`.
+            let parser = |input: ParseStream| {
+                Punctuated::<Ident, Comma>::parse_terminated_with(input, Ident::parse_any)
+            };
+
+            for flag in attr.parse_args_with(parser)? {
+                if !known.iter().any(|known| flag == known) {
+                    return Err(syn::Error::new_spanned(
+                        flag,
+                        format!(
+                            "unknown `{}` option, expected one of: `{}`",
+                            attr_name,
+                            known.join("`, `")
+                        ),
+                    ));
+                }
+
+                flags.push(flag);
+            }
+        }
+
+        Ok(Flags(flags))
+    }
+
+    pub(crate) fn has(&self, flag: &str) -> bool {
+        self.get(flag).is_some()
+    }
+
+    /// The flag as specified, e.g. to point the errors at it.
+    pub(crate) fn get(&self, flag: &str) -> Option<&Ident> {
+        self.0.iter().find(|f| *f == flag)
+    }
+}
+
+/// `#[coverage(off)]` for the generated impls if the enum has the `#[derive_coverage(off)]`
+/// attribute, so mechanically generated code doesn't skew coverage metrics. It's an attribute of
+/// the enum rather than a feature, so enabling it in one crate doesn't affect the derives of the
+/// others, that may be built on stable.
+pub(crate) fn coverage_off(attrs: &[Attribute]) -> syn::Result<Option<TokenStream>> {
+    let mut coverage_off = None;
+
+    for attr in attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("derive_coverage"))
+    {
+        let off = attr.parse_args::<Ident>()?;
+
+        if off != "off" {
+            return Err(syn::Error::new_spanned(off, "expected `off`"));
+        }
+
+        coverage_off = Some(quote_spanned!(off.span()=> #[coverage(off)]));
+    }
+
+    Ok(coverage_off)
+}
+
+/// `crate = path` option of the macros, that generate the invocations of the other macros of the
+/// crate. It's for the crates that re-export the macros, so the generated code refers to the
+/// re-export, e.g. `crate = ::facade::all_the_same`.
+pub(crate) struct CratePath(Path);
+
+impl CratePath {
+    pub(crate) fn peek(input: ParseStream) -> bool {
+        input.peek(Token!(crate)) && input.peek2(Token!(=))
+    }
+}
+
+impl Default for CratePath {
+    fn default() -> Self {
+        CratePath(parse_quote!(::all_the_same))
+    }
+}
+
+impl Parse for CratePath {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token!(crate)>()?;
+        input.parse::<Token!(=)>()?;
+
+        Ok(CratePath(input.call(Path::parse_mod_style)?))
+    }
+}
+
+impl ToTokens for CratePath {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        self.0.to_tokens(tokens);
+    }
+}
+
+/// Whether the nodes are spelled the same way. syn only implements `PartialEq` with the
+/// `extra-traits` feature, that isn't enabled to keep the build fast.
+pub(crate) fn tokens_eq(a: &impl ToTokens, b: &impl ToTokens) -> bool {
+    a.to_token_stream().to_string() == b.to_token_stream().to_string()
+}
+
+/// Whether the type mentions any type parameter at any depth, e.g. `&'a T` or `Box<T::Item>`.
+pub(crate) fn mentions_type_param(generics: &Generics, ty: &Type) -> bool {
+    struct Finder<'a> {
+        generics: &'a Generics,
+        found: bool,
+    }
+
+    impl<'ast> Visit<'ast> for Finder<'_> {
+        fn visit_path(&mut self, path: &'ast Path) {
+            // Covers both `T` and `T::Assoc`.
+            self.found |= path.leading_colon.is_none()
+                && self.generics.params.iter().any(|param| match param {
+                    GenericParam::Type(param) => path.segments[0].ident == param.ident,
+                    _ => false,
+                });
+
+            visit::visit_path(self, path);
+        }
+    }
+
+    let mut finder = Finder {
+        generics,
+        found: false,
+    };
+
+    finder.visit_type(ty);
+    finder.found
+}
+
+/// Whether the type is a bare type parameter, e.g. `T`.
+pub(crate) fn is_type_param(generics: &Generics, ty: &Type) -> bool {
+    let ident = match ty {
+        Type::Path(ty) if ty.qself.is_none() => match ty.path.get_ident() {
+            Some(ident) => ident,
+            None => return false,
+        },
+        _ => return false,
+    };
+
+    generics.params.iter().any(|param| match param {
+        GenericParam::Type(param) => param.ident == *ident,
+        _ => false,
+    })
+}
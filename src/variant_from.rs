@@ -0,0 +1,41 @@
+use crate::utils::{coverage_off, newtype_variants, Flags};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "VariantFrom")?;
+    let doc_cfg = Flags::parse(&input.attrs, "variant_from", &["doc_cfg"])?.has("doc_cfg");
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut impls = TokenStream::new();
+
+    for variant in &variants {
+        if Flags::parse(variant.attrs, "variant_from", &["skip"])?.has("skip") {
+            continue;
+        }
+
+        let variant_name = variant.name;
+        let ty = variant.ty;
+        let cfg_attrs = variant.cfg_attrs();
+        let doc_cfg_attrs = variant.doc_cfg_attrs().filter(|_| doc_cfg);
+        let allow_deprecated = variant.allow_deprecated();
+
+        impls.extend(quote! {
+            #(#cfg_attrs)*
+            #(#doc_cfg_attrs)*
+            #allow_deprecated
+            #coverage_off
+            impl #impl_generics ::core::convert::From<#ty> for #name #ty_generics #where_clause {
+                fn from(payload: #ty) -> Self {
+                    Self::#variant_name(payload)
+                }
+            }
+        });
+    }
+
+    Ok(impls)
+}
@@ -0,0 +1,81 @@
+use crate::utils::{coverage_off, newtype_variants, NewtypeVariant};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::DeriveInput;
+
+pub(crate) fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let coverage_off = coverage_off(&input.attrs)?;
+    let variants = newtype_variants(input, "VariantIndex")?;
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let variant_count = count(&variants);
+
+    let arms = variants.iter().enumerate().map(|(i, variant)| {
+        let variant_name = variant.name;
+        let cfg_attrs = variant.cfg_attrs();
+        let allow_deprecated = variant.allow_deprecated();
+        let index = count(&variants[..i]);
+
+        quote! {
+            #(#cfg_attrs)*
+            #allow_deprecated
+            Self::#variant_name(_) => #index
+        }
+    });
+
+    let count_doc = format!(
+        "Number of [`{}`] variants, that are enabled in the current configuration.",
+        name
+    );
+
+    let index_doc = format!(
+        "Returns the position of the variant among the enabled [`{}`] variants, that is always \
+         less than [`{}::VARIANT_COUNT`].",
+        name, name
+    );
+
+    Ok(quote! {
+        #coverage_off
+        impl #impl_generics #name #ty_generics #where_clause {
+            #[doc = #count_doc]
+            #vis const VARIANT_COUNT: usize = #variant_count;
+
+            #[doc = #index_doc]
+            #vis const fn index(&self) -> usize {
+                match self {
+                    #(#arms,)*
+                }
+            }
+        }
+    })
+}
+
+/// Counts the variants in a const expression, skipping the ones that are disabled by `#[cfg]`.
+fn count(variants: &[NewtypeVariant]) -> TokenStream {
+    if variants.is_empty() {
+        return quote!(0);
+    }
+
+    let counters = variants.iter().map(|variant| {
+        let cfg_attrs = variant.cfg_attrs();
+
+        quote! {
+            #(#cfg_attrs)*
+            {
+                count += 1;
+            }
+        }
+    });
+
+    quote! {{
+        #[allow(unused_mut)]
+        let mut count = 0;
+
+        #(#counters)*
+
+        count
+    }}
+}
@@ -0,0 +1,227 @@
+use crate::utils::CratePath;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::token::{Brace, Comma};
+use syn::{braced, bracketed, Attribute, Ident, ItemEnum, Token};
+
+pub(crate) fn expand(item: &ItemEnum, krate: &CratePath) -> TokenStream {
+    let name = &item.ident;
+    let module_name = format_ident!("__all_the_same_{}", name);
+
+    let variants = item.variants.iter().map(|variant| {
+        let variant_name = &variant.ident;
+        let cfg_attrs = variant
+            .attrs
+            .iter()
+            .filter(|attr| attr.path.is_ident("cfg"));
+
+        let allow_deprecated = variant
+            .attrs
+            .iter()
+            .any(|attr| attr.path.is_ident("deprecated"))
+            .then(|| quote!(#[allow(deprecated)]));
+
+        quote!(#(#cfg_attrs)* #allow_deprecated #variant_name)
+    });
+
+    let lookup_macro = lookup_macro(name, variants, krate);
+
+    // The macro has the same name as the enum, so it's imported along with the enum and
+    // `Enum::[*]` can be resolved by the enum path. It can't be re-exported from the module that
+    // already has the enum under this name, hence the intermediate module. Both names are derived
+    // from the enum name only, not from spans or counters, so they are stable across the edits of
+    // the other items, that would otherwise invalidate the code using the macro.
+    quote! {
+        #item
+
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        mod #module_name {
+            #lookup_macro
+        }
+
+        #[allow(unused_imports)]
+        pub(crate) use #module_name::#name;
+    }
+}
+
+/// `NAME = [A, B, ...]` set of variants, optionally preceded by `crate = path;`.
+pub(crate) struct VariantSet {
+    krate: CratePath,
+    name: Ident,
+    variants: Punctuated<SetVariant, Comma>,
+}
+
+/// Variant name in the list, with its attributes, e.g. `#[cfg(unix)] Unix`.
+pub(crate) struct SetVariant {
+    pub(crate) attrs: Vec<Attribute>,
+    pub(crate) name: Ident,
+}
+
+impl Parse for SetVariant {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(SetVariant {
+            attrs: input.call(Attribute::parse_outer)?,
+            name: input.parse()?,
+        })
+    }
+}
+
+impl ListVariant for SetVariant {
+    fn name(&self) -> &Ident {
+        &self.name
+    }
+
+    fn attrs(&self) -> &[Attribute] {
+        &self.attrs
+    }
+
+    fn add_group_attrs(&mut self, attrs: &[Attribute]) {
+        self.attrs.splice(0..0, attrs.iter().cloned());
+    }
+}
+
+/// Variant in the list, that can be in a group with shared attributes.
+pub(crate) trait ListVariant: Parse {
+    fn name(&self) -> &Ident;
+    fn attrs(&self) -> &[Attribute];
+    fn add_group_attrs(&mut self, attrs: &[Attribute]);
+}
+
+/// Parses the variant list, where the variants can be grouped to share the attributes, e.g.
+/// `A, #[cfg(unix)] { B, C }`.
+pub(crate) fn parse_variant_list<T: ListVariant>(
+    input: ParseStream,
+) -> syn::Result<Punctuated<T, Comma>> {
+    let variants = parse_variant_group::<T>(input)?;
+
+    if variants.is_empty() {
+        return Err(input.error("expected at least one variant"));
+    }
+
+    Ok(variants)
+}
+
+/// Rejects the variants that are listed more than once, which would make their arms unreachable.
+pub(crate) fn check_duplicates<T: ListVariant>(variants: &Punctuated<T, Comma>) -> syn::Result<()> {
+    // The same variant can be listed under different `#[cfg]`s, e.g. with different adapters.
+    let is_cfg = |variant: &T| variant.attrs().iter().any(|attr| attr.path.is_ident("cfg"));
+
+    for (i, variant) in variants.iter().enumerate() {
+        let is_duplicate = variants
+            .iter()
+            .take(i)
+            .any(|prev| prev.name() == variant.name() && !is_cfg(prev) && !is_cfg(variant));
+
+        if is_duplicate {
+            return Err(syn::Error::new_spanned(
+                variant.name(),
+                format!("variant `{}` is listed more than once", variant.name()),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_variant_group<T: ListVariant>(input: ParseStream) -> syn::Result<Punctuated<T, Comma>> {
+    let mut variants = Punctuated::new();
+
+    while !input.is_empty() {
+        let fork = input.fork();
+
+        fork.call(Attribute::parse_outer)?;
+
+        if fork.peek(Brace) {
+            let attrs = input.call(Attribute::parse_outer)?;
+            let group_content;
+
+            braced!(group_content in input);
+
+            for mut variant in parse_variant_group::<T>(&group_content)? {
+                variant.add_group_attrs(&attrs);
+                variants.push(variant);
+            }
+        } else {
+            variants.push(input.parse()?);
+        }
+
+        if input.is_empty() {
+            break;
+        }
+
+        if !input.peek(Token!(,)) {
+            return Err(input.error("expected `,` between the variants"));
+        }
+
+        input.parse::<Token!(,)>()?;
+    }
+
+    Ok(variants)
+}
+
+impl Parse for VariantSet {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let variants_list_content;
+
+        let set = VariantSet {
+            krate: if CratePath::peek(input) {
+                let krate = input.parse()?;
+
+                input.parse::<Token!(;)>()?;
+
+                krate
+            } else {
+                CratePath::default()
+            },
+            name: input.parse()?,
+            variants: {
+                input.parse::<Token!(=)>()?;
+                bracketed!(variants_list_content in input);
+
+                let variants = variants_list_content.call(parse_variant_list)?;
+
+                check_duplicates(&variants)?;
+
+                variants
+            },
+        };
+
+        input.parse::<Option<Token!(;)>>()?;
+
+        Ok(set)
+    }
+}
+
+pub(crate) fn expand_set(set: &VariantSet) -> TokenStream {
+    let variants = set.variants.iter().map(|variant| {
+        let attrs = &variant.attrs;
+        let name = &variant.name;
+
+        quote!(#(#attrs)* #name)
+    });
+
+    lookup_macro(&set.name, variants, &set.krate)
+}
+
+/// Macro that invokes `all_the_same!` back with the variant list prepended to the input, so that
+/// `[*]` and `[..SET]` can be replaced with the variants.
+fn lookup_macro(
+    name: &Ident,
+    variants: impl Iterator<Item = TokenStream>,
+    krate: &CratePath,
+) -> TokenStream {
+    quote! {
+        #[allow(unused_macros)]
+        macro_rules! #name {
+            ($($tokens:tt)*) => {
+                #krate::all_the_same! { @[#(#variants),*] $($tokens)* }
+            };
+        }
+
+        #[allow(unused_imports)]
+        pub(crate) use #name;
+    }
+}